@@ -0,0 +1,3 @@
+-- owner-initiated vault-to-vault transfers get their own type so they don't mix
+-- with transfers driven by authorized programs
+ALTER TYPE transaction_type ADD VALUE IF NOT EXISTS 'peer_transfer';
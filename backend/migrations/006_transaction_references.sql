@@ -0,0 +1,7 @@
+-- deposits can carry a 32-byte reference (hex encoded here) so accounting can match them to invoices
+ALTER TABLE public.transactions ADD COLUMN IF NOT EXISTS reference CHAR(64);
+
+-- most rows have no reference, no point indexing those
+CREATE INDEX IF NOT EXISTS idx_transactions_reference
+    ON public.transactions(reference)
+    WHERE reference IS NOT NULL;
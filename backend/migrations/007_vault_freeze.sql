@@ -0,0 +1,8 @@
+-- mirrors the on-chain freeze set by the protocol admin (freeze_vault / unfreeze_vault)
+ALTER TABLE public.vaults ADD COLUMN IF NOT EXISTS is_frozen BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE public.vaults ADD COLUMN IF NOT EXISTS freeze_reason SMALLINT;
+ALTER TABLE public.vaults ADD COLUMN IF NOT EXISTS frozen_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_vaults_frozen
+    ON public.vaults(vault_address)
+    WHERE is_frozen;
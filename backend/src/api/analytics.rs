@@ -7,6 +7,7 @@ use std::sync::Arc;
 use sqlx::Row;
 
 use crate::AppState;
+use crate::db::models::{BalanceDrift, InsuranceFundStatus};
 use crate::error::Result;
 
 #[derive(Debug, Serialize)]
@@ -53,6 +54,23 @@ pub struct YieldMetrics {
     pub total_yield_vaults: i64,
 }
 
+#[derive(Debug, Serialize)]
+pub struct DriftReport {
+    pub drifting_vaults: Vec<BalanceDrift>,
+    pub total_surplus: i64,
+    pub total_deficit: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InsuranceReport {
+    pub initialized: bool,
+    pub fund: Option<InsuranceFundStatus>,
+    // shortfall the fund could still cover right now, i.e. its token balance
+    pub coverage_available: i64,
+    // vaults currently holding fewer tokens than their book balance
+    pub outstanding_deficit: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TimeRangeQuery {
     pub days: Option<i32>,
@@ -331,3 +349,39 @@ pub async fn get_tvl_chart(
         
     Ok(Json(chart_data))
 }
+
+/// Get vaults whose book balance does not match their token account
+// reads from chain for every vault, so this sits in the expensive tier
+pub async fn get_balance_drift(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<DriftReport>> {
+    let drifting_vaults = state.vault_manager.get_balance_drift().await?;
+
+    let total_surplus = drifting_vaults.iter().map(|d| d.drift.max(0)).sum();
+    let total_deficit = drifting_vaults.iter().map(|d| (-d.drift).max(0)).sum();
+
+    Ok(Json(DriftReport {
+        drifting_vaults,
+        total_surplus,
+        total_deficit,
+    }))
+}
+
+/// Get insurance fund balance, contributions and shortfall draws
+// also walks every vault for the current deficit, so this sits in the expensive tier
+pub async fn get_insurance_fund(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<InsuranceReport>> {
+    let fund = state.vault_manager.get_insurance_fund().await?;
+    let drifting_vaults = state.vault_manager.get_balance_drift().await?;
+
+    let outstanding_deficit = drifting_vaults.iter().map(|d| (-d.drift).max(0)).sum();
+    let coverage_available = fund.as_ref().map(|f| f.token_balance).unwrap_or(0);
+
+    Ok(Json(InsuranceReport {
+        initialized: fund.is_some(),
+        fund,
+        coverage_available,
+        outstanding_deficit,
+    }))
+}
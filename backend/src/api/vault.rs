@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::AppState;
-use crate::db::models::{TransactionRecord, VaultRecord};
+use crate::db::models::{JournalEntryRecord, LockExpiry, TransactionRecord, VaultRecord};
 use crate::error::{Result, VaultError};
 
 use anchor_client::solana_sdk::{
@@ -54,6 +54,52 @@ pub async fn get_transactions(
     Ok(Json(TransactionsResponse { transactions }))
 }
 
+// accounting systems look deposits up by the reference they attached, across all vaults
+pub async fn get_transactions_by_reference(
+    State(state): State<Arc<AppState>>,
+    Path(reference): Path<String>,
+) -> Result<Json<TransactionsResponse>> {
+    let reference = normalize_reference(&reference)?;
+    let transactions = state
+        .vault_manager
+        .get_transactions_by_reference(&reference)
+        .await?;
+
+    Ok(Json(TransactionsResponse { transactions }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockExpiriesResponse {
+    pub locks: Vec<LockExpiry>,
+}
+
+pub async fn get_lock_expiries(
+    State(state): State<Arc<AppState>>,
+    Path(user): Path<String>,
+) -> Result<Json<LockExpiriesResponse>> {
+    let locks = state.vault_manager.get_lock_expiries(&user).await?;
+
+    Ok(Json(LockExpiriesResponse { locks }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct JournalResponse {
+    pub journal_enabled: bool,
+    pub entries: Vec<JournalEntryRecord>,
+}
+
+pub async fn get_journal(
+    State(state): State<Arc<AppState>>,
+    Path(user): Path<String>,
+) -> Result<Json<JournalResponse>> {
+    let entries = state.vault_manager.get_journal(&user).await?;
+
+    Ok(Json(JournalResponse {
+        journal_enabled: entries.is_some(),
+        entries: entries.unwrap_or_default(),
+    }))
+}
+
 #[derive(Debug, Serialize)]
 pub struct TvlResponse {
     pub total_value_locked: i64,
@@ -78,6 +124,31 @@ pub struct BuildInitializeVaultTxRequest {
 pub struct BuildDepositTxRequest {
     pub user_pubkey: String,
     pub amount: u64,
+    // 32 bytes as 64 hex chars, emitted in DepositEvent
+    pub reference: Option<String>,
+}
+
+// references travel as hex in the API and get stored lowercase so lookups are exact matches
+fn normalize_reference(reference: &str) -> Result<String> {
+    if reference.len() != 64 || !reference.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(VaultError::InvalidAmount(
+            "Reference must be 32 bytes encoded as 64 hex characters".to_string(),
+        ));
+    }
+
+    Ok(reference.to_ascii_lowercase())
+}
+
+fn parse_reference(reference: &str) -> Result<[u8; 32]> {
+    let reference = normalize_reference(reference)?;
+
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&reference[i * 2..i * 2 + 2], 16)
+            .map_err(|e| VaultError::InvalidAmount(format!("Invalid reference: {e}")))?;
+    }
+
+    Ok(bytes)
 }
 
 #[derive(Debug, Serialize)]
@@ -150,13 +221,19 @@ pub async fn build_deposit_tx(
 
     let user = Pubkey::from_str(&req.user_pubkey)
         .map_err(|e| VaultError::InvalidAmount(format!("Invalid pubkey: {e}")))?;
+    let reference = req.reference.as_deref().map(parse_reference).transpose()?;
 
     let solana_client = state.vault_manager.solana_client();
 
     let (vault_pda, _bump) = solana_client.derive_vault_pda(&user);
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
     let vault_token_account = get_associated_token_address(&vault_pda, &solana_client.usdt_mint);
     let user_token_account = get_associated_token_address(&user, &solana_client.usdt_mint);
 
+    let journal = solana_client
+        .existing_journal(&vault_pda)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
     let ix: Instruction = Instruction {
         program_id: solana_client.program_id,
         accounts: collateral_vault::accounts::Deposit {
@@ -165,10 +242,16 @@ pub async fn build_deposit_tx(
             user_token_account,
             vault_token_account,
             owner: user,
+            protocol_config,
             token_program: anchor_spl::token::ID,
+            journal,
         }
         .to_account_metas(None),
-        data: collateral_vault::instruction::Deposit { amount: req.amount }.data(),
+        data: collateral_vault::instruction::Deposit {
+            amount: req.amount,
+            reference,
+        }
+        .data(),
     };
 
     let recent_blockhash = solana_client
@@ -179,6 +262,75 @@ pub async fn build_deposit_tx(
     Ok(Json(make_unsigned_tx(vec![ix], user, recent_blockhash)?))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BuildDepositForTxRequest {
+    pub payer_pubkey: String,
+    pub beneficiary_pubkey: String,
+    pub amount: u64,
+    pub memo: Option<String>,
+    pub reference: Option<String>,
+}
+
+// payer funds someone else's vault from its own token account, payer is the fee payer too
+pub async fn build_deposit_for_tx(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BuildDepositForTxRequest>,
+) -> Result<Json<BuildTxResponse>> {
+    if req.amount == 0 {
+        return Err(VaultError::InvalidAmount("Amount must be greater than zero".to_string()));
+    }
+
+    // same limit the program enforces, failing here saves the payer a wasted tx fee
+    if req.memo.as_ref().is_some_and(|m| m.len() > 64) {
+        return Err(VaultError::InvalidAmount("Memo must be at most 64 bytes".to_string()));
+    }
+
+    let payer = Pubkey::from_str(&req.payer_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid payer pubkey: {e}")))?;
+    let beneficiary = Pubkey::from_str(&req.beneficiary_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid beneficiary pubkey: {e}")))?;
+    let reference = req.reference.as_deref().map(parse_reference).transpose()?;
+
+    let solana_client = state.vault_manager.solana_client();
+
+    let (vault_pda, _bump) = solana_client.derive_vault_pda(&beneficiary);
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
+    let vault_token_account = get_associated_token_address(&vault_pda, &solana_client.usdt_mint);
+    let payer_token_account = get_associated_token_address(&payer, &solana_client.usdt_mint);
+
+    let journal = solana_client
+        .existing_journal(&vault_pda)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
+    let ix: Instruction = Instruction {
+        program_id: solana_client.program_id,
+        accounts: collateral_vault::accounts::DepositFor {
+            payer,
+            beneficiary,
+            vault: vault_pda,
+            payer_token_account,
+            vault_token_account,
+            protocol_config,
+            token_program: anchor_spl::token::ID,
+            journal,
+        }
+        .to_account_metas(None),
+        data: collateral_vault::instruction::DepositFor {
+            amount: req.amount,
+            memo: req.memo,
+            reference,
+        }
+        .data(),
+    };
+
+    let recent_blockhash = solana_client
+        .rpc
+        .get_latest_blockhash()
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
+    Ok(Json(make_unsigned_tx(vec![ix], payer, recent_blockhash)?))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BuildWithdrawTxRequest {
     pub user_pubkey: String,
@@ -214,9 +366,14 @@ pub async fn build_withdraw_unsigned(
     let solana_client = state.vault_manager.solana_client();
 
     let (vault_pda, _bump) = solana_client.derive_vault_pda(&user);
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
     let vault_token_account = get_associated_token_address(&vault_pda, &solana_client.usdt_mint);
     let user_token_account = get_associated_token_address(&user, &solana_client.usdt_mint);
 
+    let journal = solana_client
+        .existing_journal(&vault_pda)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
     let ix: Instruction = Instruction {
         program_id: solana_client.program_id,
         accounts: collateral_vault::accounts::Withdraw {
@@ -225,7 +382,9 @@ pub async fn build_withdraw_unsigned(
             user_token_account,
             vault_token_account,
             owner: user,
+            protocol_config,
             token_program: anchor_spl::token::ID,
+            journal,
         }
         .to_account_metas(None),
         data: collateral_vault::instruction::Withdraw { amount: req.amount }.data(),
@@ -239,6 +398,237 @@ pub async fn build_withdraw_unsigned(
     Ok(Json(make_unsigned_tx(vec![ix], user, recent_blockhash)?))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BuildVaultTransferTxRequest {
+    pub user_pubkey: String,
+    pub recipient_pubkey: String,
+    pub amount: u64,
+}
+
+// moves funds straight into another user's vault, the program applies the sender's
+// whitelist / rate limit / timelock policies same as a withdrawal
+pub async fn build_vault_transfer_tx(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BuildVaultTransferTxRequest>,
+) -> Result<Json<BuildTxResponse>> {
+    if req.amount == 0 {
+        return Err(VaultError::InvalidAmount("Amount must be greater than zero".to_string()));
+    }
+
+    let user = Pubkey::from_str(&req.user_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid pubkey: {e}")))?;
+    let recipient = Pubkey::from_str(&req.recipient_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid recipient pubkey: {e}")))?;
+
+    if user == recipient {
+        return Err(VaultError::InvalidAmount("Cannot transfer to your own vault".to_string()));
+    }
+
+    let solana_client = state.vault_manager.solana_client();
+
+    let (from_vault, _) = solana_client.derive_vault_pda(&user);
+    let (to_vault, _) = solana_client.derive_vault_pda(&recipient);
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
+
+    let from_journal = solana_client
+        .existing_journal(&from_vault)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+    let to_journal = solana_client
+        .existing_journal(&to_vault)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
+    let ix: Instruction = Instruction {
+        program_id: solana_client.program_id,
+        accounts: collateral_vault::accounts::TransferToVault {
+            owner: user,
+            from_vault,
+            to_vault,
+            from_token_account: get_associated_token_address(&from_vault, &solana_client.usdt_mint),
+            to_token_account: get_associated_token_address(&to_vault, &solana_client.usdt_mint),
+            protocol_config,
+            token_program: anchor_spl::token::ID,
+            from_journal,
+            to_journal,
+        }
+        .to_account_metas(None),
+        data: collateral_vault::instruction::TransferToVault { amount: req.amount }.data(),
+    };
+
+    let recent_blockhash = solana_client
+        .rpc
+        .get_latest_blockhash()
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
+    Ok(Json(make_unsigned_tx(vec![ix], user, recent_blockhash)?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PrepareWithdrawPermitRequest {
+    pub user_pubkey: String,
+    pub recipient_pubkey: String,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WithdrawPermitResponse {
+    pub vault: String,
+    pub recipient: String,
+    pub amount: u64,
+    pub nonce: u64,
+    pub expires_at: i64,
+    // exact bytes the owner signs with their wallet (signMessage), base64 encoded
+    pub message_base64: String,
+}
+
+fn parse_permit(
+    solana_client: &crate::solana::SolanaClient,
+    user: &Pubkey,
+    recipient_pubkey: &str,
+    amount: u64,
+    nonce: u64,
+    expires_at: i64,
+) -> Result<collateral_vault::state::WithdrawalPermit> {
+    if amount == 0 {
+        return Err(VaultError::InvalidAmount("Amount must be greater than zero".to_string()));
+    }
+
+    let recipient = Pubkey::from_str(recipient_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid recipient pubkey: {e}")))?;
+    let (vault_pda, _bump) = solana_client.derive_vault_pda(user);
+
+    Ok(collateral_vault::state::WithdrawalPermit {
+        vault: vault_pda,
+        amount,
+        recipient,
+        nonce,
+        expires_at,
+    })
+}
+
+// fills in the vault's current permit nonce and returns the message to sign, nothing is stored
+pub async fn prepare_withdraw_permit(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PrepareWithdrawPermitRequest>,
+) -> Result<Json<WithdrawPermitResponse>> {
+    let user = Pubkey::from_str(&req.user_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid pubkey: {e}")))?;
+
+    let solana_client = state.vault_manager.solana_client();
+    let (vault_pda, _bump) = solana_client.derive_vault_pda(&user);
+
+    let account_data = solana_client
+        .rpc
+        .get_account_data(&vault_pda)
+        .map_err(|e| VaultError::SolanaClient(format!("Failed to fetch vault account {vault_pda}: {e}")))?;
+    let vault = crate::vault::manager::VaultManager::decode_vault(&account_data)?;
+
+    let permit = parse_permit(
+        solana_client,
+        &user,
+        &req.recipient_pubkey,
+        req.amount,
+        vault.permit_nonce,
+        req.expires_at,
+    )?;
+
+    Ok(Json(WithdrawPermitResponse {
+        vault: permit.vault.to_string(),
+        recipient: permit.recipient.to_string(),
+        amount: permit.amount,
+        nonce: permit.nonce,
+        expires_at: permit.expires_at,
+        message_base64: BASE64_STANDARD.encode(permit.message()),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelayWithdrawPermitRequest {
+    pub user_pubkey: String,
+    pub recipient_pubkey: String,
+    pub amount: u64,
+    pub nonce: u64,
+    pub expires_at: i64,
+    // owner's ed25519 signature over the permit message, base58
+    pub signature: String,
+}
+
+// gasless withdrawal: the backend payer submits the owner-signed permit and pays the fee.
+// the program checks the signature and nonce, so a bad permit only costs the relayer a fee
+pub async fn relay_withdraw_permit(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RelayWithdrawPermitRequest>,
+) -> Result<Json<SyncTxResponse>> {
+    use anchor_client::solana_sdk::signature::Signature;
+
+    let user = Pubkey::from_str(&req.user_pubkey)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid pubkey: {e}")))?;
+    let owner_signature = Signature::from_str(&req.signature)
+        .map_err(|e| VaultError::InvalidAmount(format!("Invalid signature: {e}")))?;
+
+    let solana_client = state.vault_manager.solana_client();
+
+    let permit = parse_permit(
+        solana_client,
+        &user,
+        &req.recipient_pubkey,
+        req.amount,
+        req.nonce,
+        req.expires_at,
+    )?;
+
+    // checking here too so an obviously bad signature doesn't cost a transaction fee
+    if !owner_signature.verify(user.as_ref(), &permit.message()) {
+        return Err(VaultError::InvalidAmount("Signature does not match the permit".to_string()));
+    }
+
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
+    let vault_token_account = get_associated_token_address(&permit.vault, &solana_client.usdt_mint);
+    let recipient_token_account = get_associated_token_address(&permit.recipient, &solana_client.usdt_mint);
+
+    let journal = solana_client
+        .existing_journal(&permit.vault)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
+    let verify_ix = crate::solana::ed25519_verify_instruction(&user, &owner_signature, &permit.message());
+    let withdraw_ix: Instruction = Instruction {
+        program_id: solana_client.program_id,
+        accounts: collateral_vault::accounts::WithdrawWithPermit {
+            relayer: solana_client.payer_pubkey(),
+            vault: permit.vault,
+            vault_token_account,
+            recipient_token_account,
+            owner: user,
+            protocol_config,
+            instructions: sysvar::instructions::ID,
+            token_program: anchor_spl::token::ID,
+            journal,
+        }
+        .to_account_metas(None),
+        data: collateral_vault::instruction::WithdrawWithPermit {
+            permit: permit.clone(),
+        }
+        .data(),
+    };
+
+    let signature = solana_client
+        .relay(&[verify_ix, withdraw_ix])
+        .map_err(|e| VaultError::TransactionFailed(e.to_string()))?;
+
+    let vault = state
+        .vault_manager
+        .sync_confirmed_tx(
+            &req.user_pubkey,
+            &signature.to_string(),
+            crate::db::models::TransactionType::Withdrawal,
+            Some(permit.amount as i64),
+            None,
+        )
+        .await?;
+
+    Ok(Json(SyncTxResponse { vault, recorded: true }))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SyncTxRequest {
     pub user_pubkey: String,
@@ -246,6 +636,8 @@ pub struct SyncTxRequest {
     pub transaction_type: crate::db::models::TransactionType,
     // optional because older clients might not send it
     pub amount: Option<i64>,
+    // hex reference the deposit was built with, stored for reconciliation lookups
+    pub reference: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -259,6 +651,8 @@ pub async fn sync_tx(
     State(state): State<Arc<AppState>>,
     Json(req): Json<SyncTxRequest>,
 ) -> Result<Json<SyncTxResponse>> {
+    let reference = req.reference.as_deref().map(normalize_reference).transpose()?;
+
     let vault = state
         .vault_manager
         .sync_confirmed_tx(
@@ -266,6 +660,7 @@ pub async fn sync_tx(
             &req.signature,
             req.transaction_type,
             req.amount,
+            reference,
         )
         .await?;
 
@@ -302,34 +697,16 @@ pub async fn force_sync_vault(
             ))
         })?;
 
-    // manually deserialize because we can't use Anchor's Account wrapper without the program
-    // Anchor format: 8 byte discriminator + account data
-    if account_data.len() < 8 {
-        return Err(VaultError::SolanaClient(
-            "Invalid account data: too short".to_string(),
-        ));
-    }
-
-    // CollateralVault layout from program:
-    // owner: Pubkey (32), token_account: Pubkey (32),
-    // total_balance: u64 (8), locked_balance: u64 (8), available_balance: u64 (8),
-    // total_deposited: u64 (8), total_withdrawn: u64 (8)
-    let data = &account_data[8..];
-    
-    if data.len() < 32 + 32 + 8 + 8 + 8 + 8 + 8 {
-        return Err(VaultError::SolanaClient(
-            "Invalid account data: insufficient length".to_string(),
-        ));
-    }
+    let vault = crate::vault::manager::VaultManager::decode_vault(&account_data)?;
+    let total_balance = vault.total_balance as i64;
+    let locked_balance = vault.locked_balance as i64;
+    let available_balance = vault.available_balance as i64;
+    let total_deposited = vault.total_deposited as i64;
+    let total_withdrawn = vault.total_withdrawn as i64;
 
-    use std::convert::TryInto;
-    
-    let offset = 32 + 32;
-    let total_balance = u64::from_le_bytes(data[offset..offset+8].try_into().unwrap()) as i64;
-    let locked_balance = u64::from_le_bytes(data[offset+8..offset+16].try_into().unwrap()) as i64;
-    let available_balance = u64::from_le_bytes(data[offset+16..offset+24].try_into().unwrap()) as i64;
-    let total_deposited = u64::from_le_bytes(data[offset+24..offset+32].try_into().unwrap()) as i64;
-    let total_withdrawn = u64::from_le_bytes(data[offset+32..offset+40].try_into().unwrap()) as i64;
+    // freeze_vault / unfreeze_vault are admin-signed and never go through /vault/sync,
+    // so this is how the freeze gets mirrored
+    let freeze = crate::vault::manager::VaultManager::freeze_status(&vault);
 
     // sanity check - this invariant is enforced by the program
     if total_balance != locked_balance + available_balance {
@@ -352,9 +729,12 @@ pub async fn force_sync_vault(
             locked_balance,
             available_balance,
             total_deposited,
-            total_withdrawn
+            total_withdrawn,
+            is_frozen,
+            freeze_reason,
+            frozen_at
         )
-        VALUES ($1,$2,$3,$4,$5,$6,$7)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10)
         ON CONFLICT (vault_address)
         DO UPDATE SET
             owner = EXCLUDED.owner,
@@ -363,6 +743,9 @@ pub async fn force_sync_vault(
             available_balance = EXCLUDED.available_balance,
             total_deposited = EXCLUDED.total_deposited,
             total_withdrawn = EXCLUDED.total_withdrawn,
+            is_frozen = EXCLUDED.is_frozen,
+            freeze_reason = EXCLUDED.freeze_reason,
+            frozen_at = EXCLUDED.frozen_at,
             updated_at = NOW()
         RETURNING *
         "#,
@@ -374,6 +757,9 @@ pub async fn force_sync_vault(
     .bind(available_balance)
     .bind(total_deposited)
     .bind(total_withdrawn)
+    .bind(freeze.is_frozen)
+    .bind(freeze.freeze_reason)
+    .bind(freeze.frozen_at)
     .fetch_one(&state.db_pool)
     .await
     .map_err(|e| VaultError::Database(e.to_string()))?;
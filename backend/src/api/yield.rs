@@ -58,6 +58,11 @@ pub async fn build_compound_yield_tx(
 
     let solana_client = state.vault_manager.solana_client();
     let (vault_pda, _bump) = solana_client.derive_vault_pda(&user);
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
+
+    let journal = solana_client
+        .existing_journal(&vault_pda)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
 
     let ix: Instruction = Instruction {
         program_id: solana_client.program_id,
@@ -65,6 +70,8 @@ pub async fn build_compound_yield_tx(
             user,
             vault: vault_pda,
             owner: user,
+            protocol_config,
+            journal,
         }
         .to_account_metas(None),
         data: collateral_vault::instruction::CompoundYield {}.data(),
@@ -96,12 +103,19 @@ pub async fn build_auto_compound_tx(
 
     let solana_client = state.vault_manager.solana_client();
     let (vault_pda, _bump) = solana_client.derive_vault_pda(&vault_owner);
+    let (protocol_config, _) = solana_client.derive_protocol_config_pda();
+
+    let journal = solana_client
+        .existing_journal(&vault_pda)
+        .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
 
     let ix: Instruction = Instruction {
         program_id: solana_client.program_id,
         accounts: collateral_vault::accounts::AutoCompound {
             caller,
             vault: vault_pda,
+            protocol_config,
+            journal,
         }
         .to_account_metas(None),
         data: collateral_vault::instruction::AutoCompound {}.data(),
@@ -183,39 +197,11 @@ pub async fn get_yield_info(
             ))
         })?;
 
-    if account_data.len() < 8 {
-        return Err(VaultError::SolanaClient(
-            "Invalid account data: too short".to_string(),
-        ));
-    }
-
-    let data = &account_data[8..];
-    use std::convert::TryInto;
-    
-    let total_balance = u64::from_le_bytes(data[64..72].try_into().unwrap_or([0u8; 8]));
-    
-    // Navigate through dynamic Vec fields to reach yield data
-    let signers_len_offset = 114;
-    let signers_len = u32::from_le_bytes(data[signers_len_offset..signers_len_offset+4].try_into().unwrap_or([0u8; 4])) as usize;
-    let mut offset = signers_len_offset + 4 + (signers_len * 32);
-    
-    let delegates_len = u32::from_le_bytes(data[offset..offset+4].try_into().unwrap_or([0u8; 4])) as usize;
-    offset += 4 + (delegates_len * 32);
-    offset += 8;
-    
-    let has_pending = data[offset] != 0;
-    offset += 1;
-    if has_pending {
-        offset += 56;
-    }
-    
-    offset += 1;
-    let yield_enabled = data[offset] != 0;
-    offset += 1;
-    
-    let total_yield_earned = u64::from_le_bytes(data[offset..offset+8].try_into().unwrap_or([0u8; 8]));
-    offset += 8;
-    let last_yield_compound = i64::from_le_bytes(data[offset..offset+8].try_into().unwrap_or([0u8; 8]));
+    let vault = crate::vault::manager::VaultManager::decode_vault(&account_data)?;
+    let total_balance = vault.total_balance;
+    let yield_enabled = vault.yield_enabled != 0;
+    let total_yield_earned = vault.total_yield_earned;
+    let last_yield_compound = vault.last_yield_compound;
 
     let current_time = chrono::Utc::now().timestamp();
     let time_since_last = current_time - last_yield_compound;
@@ -280,11 +266,8 @@ pub async fn sync_yield_tx(
         .get_account_data(&vault_pda)
         .map_err(|e| VaultError::SolanaClient(format!("Failed to fetch vault: {e}")))?;
 
-    let yield_amount = if account_data.len() >= 80 {
-        let data = &account_data[8..];
-        use std::convert::TryInto;
-        
-        let new_total_balance = u64::from_le_bytes(data[64..72].try_into().unwrap_or([0u8; 8])) as i64;
+    let yield_amount = if let Ok(vault) = crate::vault::manager::VaultManager::decode_vault(&account_data) {
+        let new_total_balance = vault.total_balance as i64;
         
         // Calculate the yield amount as the difference in total_balance
         if let Some(old) = old_vault {
@@ -303,6 +286,7 @@ pub async fn sync_yield_tx(
             &req.signature,
             crate::db::models::TransactionType::Deposit,
             yield_amount,
+            None,
         )
         .await?;
 
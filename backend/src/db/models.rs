@@ -14,6 +14,10 @@ pub struct VaultRecord {
     pub available_balance: i64,
     pub total_deposited: i64,
     pub total_withdrawn: i64,
+    // mirrored from the on-chain freeze, owner operations fail while this is set
+    pub is_frozen: bool,
+    pub freeze_reason: Option<i16>,
+    pub frozen_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -26,6 +30,9 @@ pub enum TransactionType {
     Lock,
     Unlock,
     Transfer,
+    // owner-initiated vault-to-vault transfer, "transfer" stays for program-driven moves
+    #[sqlx(rename = "peer_transfer")]
+    PeerTransfer,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -36,6 +43,7 @@ pub struct TransactionRecord {
     pub amount: i64,
     pub signature: String,
     pub created_at: DateTime<Utc>,
+    pub reference: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -51,3 +59,57 @@ pub struct TvlSnapshot {
     pub created_at: DateTime<Utc>,
 }
 
+
+// book = CollateralVault.total_balance, token = what the vault ATA actually holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceDrift {
+    pub owner: String,
+    pub vault_address: String,
+    pub book_balance: i64,
+    pub token_balance: i64,
+    // positive means unaccounted tokens that sync_balance can credit,
+    // negative means the vault is short and sync_balance will refuse
+    pub drift: i64,
+}
+
+// freeze fields as read off the CollateralVault account
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FreezeStatus {
+    pub is_frozen: bool,
+    pub freeze_reason: Option<i16>,
+    pub frozen_at: Option<DateTime<Utc>>,
+}
+
+// on-chain InsuranceFund totals plus what its token account currently holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsuranceFundStatus {
+    pub fund_address: String,
+    pub token_account: String,
+    pub token_balance: i64,
+    pub fee_share_bps: u16,
+    pub total_contributed: i64,
+    pub total_paid_out: i64,
+}
+
+// one record from the vault's on-chain journal ring buffer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntryRecord {
+    pub seq: u64,
+    pub slot: u64,
+    pub op: String,
+    pub amount: i64,
+    pub counterparty: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockExpiry {
+    pub lock_address: String,
+    pub lock_id: u64,
+    pub caller_program: String,
+    pub amount: i64,
+    pub reason_code: u8,
+    // unix timestamps straight from the on-chain record
+    pub expires_at: Option<i64>,
+    pub seconds_until_expiry: Option<i64>,
+    pub expired: bool,
+}
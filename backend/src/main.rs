@@ -110,7 +110,11 @@ async fn main() -> anyhow::Result<()> {
 
         .route("/vault/initialize", post(api::vault::build_initialize_unsigned))
         .route("/vault/deposit", post(api::vault::build_deposit_unsigned))
+        .route("/vault/deposit-for", post(api::vault::build_deposit_for_tx))
         .route("/vault/withdraw", post(api::vault::build_withdraw_unsigned))
+        .route("/vault/transfer", post(api::vault::build_vault_transfer_tx))
+        .route("/vault/withdraw-permit/prepare", post(api::vault::prepare_withdraw_permit))
+        .route("/vault/withdraw-permit", post(api::vault::relay_withdraw_permit))
         .route("/vault/sync", post(api::vault::sync_tx))
         .route("/vault/force-sync", post(api::vault::force_sync_vault))
         .route_layer({
@@ -123,6 +127,9 @@ async fn main() -> anyhow::Result<()> {
 
         .route("/vault/balance/:user", get(api::vault::get_balance))
         .route("/vault/transactions/:user", get(api::vault::get_transactions))
+        .route("/vault/reference/:reference", get(api::vault::get_transactions_by_reference))
+        .route("/vault/locks/:user", get(api::vault::get_lock_expiries))
+        .route("/vault/journal/:user", get(api::vault::get_journal))
         .route("/vault/tvl", get(api::vault::get_tvl))
         .route_layer({
             let limiter = rate_limit_read.clone();
@@ -159,6 +166,8 @@ async fn main() -> anyhow::Result<()> {
         .route("/analytics/flow", get(api::analytics::get_flow_metrics))
         .route("/analytics/yield", get(api::analytics::get_yield_metrics))
         .route("/analytics/chart/tvl", get(api::analytics::get_tvl_chart))
+        .route("/analytics/drift", get(api::analytics::get_balance_drift))
+        .route("/analytics/insurance", get(api::analytics::get_insurance_fund))
         .route_layer({
             let limiter = rate_limit_expensive.clone();
             axum_middleware::from_fn(move |headers, req, next| {
@@ -1,8 +1,11 @@
 use anchor_client::{Client, Cluster, Program};
 use anchor_client::solana_sdk::{
+    ed25519_program,
+    instruction::Instruction,
     pubkey::Pubkey,
-    signature::{read_keypair_file, Keypair, Signer},
+    signature::{read_keypair_file, Keypair, Signature, Signer},
     commitment_config::CommitmentConfig,
+    transaction::Transaction,
 };
 use anchor_client::ClientError;
 use solana_client::rpc_client::RpcClient;
@@ -81,4 +84,84 @@ impl SolanaClient {
             &self.program_id,
         )
     }
-}
\ No newline at end of file
+
+    pub fn derive_protocol_config_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"protocol_config"],
+            &self.program_id,
+        )
+    }
+
+    pub fn derive_insurance_fund_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"insurance_fund"],
+            &self.program_id,
+        )
+    }
+
+    pub fn derive_journal_pda(&self, vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"journal", vault.as_ref()],
+            &self.program_id,
+        )
+    }
+
+    // the journal is opt-in, built transactions only include it when the owner created one
+    pub fn existing_journal(&self, vault: &Pubkey) -> Result<Option<Pubkey>> {
+        let (journal_pda, _) = self.derive_journal_pda(vault);
+        let account = self
+            .rpc
+            .get_account_with_commitment(&journal_pda, self.rpc.commitment())?
+            .value;
+
+        Ok(account.map(|_| journal_pda))
+    }
+
+    // the payer is the relayer for signed withdrawal permits: it pays the fee and is the only
+    // signer, the owner's authorization travels in the ed25519 instruction
+    pub fn relay(&self, ixs: &[Instruction]) -> Result<Signature> {
+        let recent_blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.payer.pubkey()),
+            &[self.payer.as_ref()],
+            recent_blockhash,
+        );
+
+        Ok(self.rpc.send_and_confirm_transaction(&tx)?)
+    }
+}
+
+// ed25519 program instruction checking one signature, with the key, signature and message all
+// stored inline. withdraw_with_permit expects exactly this shape right before it
+pub fn ed25519_verify_instruction(pubkey: &Pubkey, signature: &Signature, message: &[u8]) -> Instruction {
+    const OFFSETS_START: u16 = 2;
+    const PUBKEY_OFFSET: u16 = OFFSETS_START + 14;
+    const SIGNATURE_OFFSET: u16 = PUBKEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    // u16::MAX points every offset at this instruction's own data
+    const THIS_IX: u16 = u16::MAX;
+
+    let mut data = Vec::with_capacity(MESSAGE_OFFSET as usize + message.len());
+    data.extend_from_slice(&[1, 0]);
+    for field in [
+        SIGNATURE_OFFSET,
+        THIS_IX,
+        PUBKEY_OFFSET,
+        THIS_IX,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        THIS_IX,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
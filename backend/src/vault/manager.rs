@@ -3,7 +3,10 @@ use sqlx::PgPool;
 use std::str::FromStr;
 use sqlx::Row;
 
-use crate::db::models::{VaultRecord, TransactionRecord};
+use crate::db::models::{
+    BalanceDrift, FreezeStatus, InsuranceFundStatus, JournalEntryRecord, VaultRecord, TransactionRecord,
+    LockExpiry,
+};
 use crate::error::{Result, VaultError};
 use crate::solana::SolanaClient;
 
@@ -83,6 +86,23 @@ impl VaultManager {
         Ok(transactions)
     }
 
+    pub async fn get_transactions_by_reference(&self, reference: &str) -> Result<Vec<TransactionRecord>> {
+        let transactions = sqlx::query_as::<_, TransactionRecord>(
+            r#"
+            SELECT * FROM public.transactions
+            WHERE reference = $1
+            ORDER BY created_at DESC
+            LIMIT 100
+            "#,
+        )
+        .bind(reference)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| VaultError::Database(e.to_string()))?;
+
+        Ok(transactions)
+    }
+
     pub async fn get_tvl(&self) -> Result<i64> {
         let row = sqlx::query(
             r#"SELECT COALESCE(SUM(total_balance), 0)::BIGINT AS tvl FROM public.vaults"#,
@@ -98,10 +118,337 @@ impl VaultManager {
         Ok(tvl)
     }
 
+    // lock records live in their own PDAs, so we go straight to the chain instead of the DB
+    pub async fn get_lock_expiries(&self, user_pubkey: &str) -> Result<Vec<LockExpiry>> {
+        use anchor_lang::{AccountDeserialize, Discriminator};
+        use collateral_vault::state::LockRecord;
+        use solana_client::rpc_config::RpcProgramAccountsConfig;
+        use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+        let user = Pubkey::from_str(user_pubkey)
+            .map_err(|e| VaultError::InvalidAmount(format!("Invalid pubkey: {}", e)))?;
+
+        let (vault_pda, _) = self.solana_client.derive_vault_pda(&user);
+
+        // discriminator first, then the vault pubkey right after it
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, LockRecord::DISCRIMINATOR)),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8, vault_pda.as_ref())),
+            ]),
+            ..Default::default()
+        };
+
+        let accounts = self
+            .solana_client
+            .rpc
+            .get_program_accounts_with_config(&self.solana_client.program_id, config)
+            .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+
+        let now = chrono::Utc::now().timestamp();
+
+        let mut locks = Vec::with_capacity(accounts.len());
+        for (address, account) in accounts {
+            let record = LockRecord::try_deserialize(&mut account.data.as_slice())
+                .map_err(|e| VaultError::SolanaClient(format!("Invalid lock record {}: {}", address, e)))?;
+
+            locks.push(LockExpiry {
+                lock_address: address.to_string(),
+                lock_id: record.lock_id,
+                caller_program: record.caller_program.to_string(),
+                amount: record.outstanding as i64,
+                reason_code: record.reason_code,
+                expires_at: record.expires_at,
+                seconds_until_expiry: record.expires_at.map(|t| t - now),
+                expired: record.expires_at.map(|t| now >= t).unwrap_or(false),
+            });
+        }
+
+        // soonest expiry first, locks without an expiry go last
+        locks.sort_by_key(|l| l.expires_at.unwrap_or(i64::MAX));
+
+        Ok(locks)
+    }
+
+    // compares each known vault's on-chain book balance with its token account,
+    // only vaults that are actually off are returned
+    pub async fn get_balance_drift(&self) -> Result<Vec<BalanceDrift>> {
+        use anchor_spl::associated_token::get_associated_token_address;
+
+        let rows = sqlx::query(r#"SELECT owner, vault_address FROM public.vaults"#)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| VaultError::Database(e.to_string()))?;
+
+        let mut drifts = Vec::new();
+        for row in rows {
+            let owner: String = row.get("owner");
+            let vault_address: String = row.get("vault_address");
+
+            let vault_pda = Pubkey::from_str(&vault_address)
+                .map_err(|e| VaultError::Internal(format!("Invalid vault address in DB: {e}")))?;
+
+            let account_data = self
+                .solana_client
+                .rpc
+                .get_account_data(&vault_pda)
+                .map_err(|e| VaultError::SolanaClient(e.to_string()))?;
+            let vault = Self::decode_vault(&account_data)
+                .map_err(|e| VaultError::SolanaClient(format!("Failed to decode vault {vault_address}: {e}")))?;
+
+            let token_account = get_associated_token_address(&vault_pda, &self.solana_client.usdt_mint);
+            let token_balance = self
+                .solana_client
+                .rpc
+                .get_token_account_balance(&token_account)
+                .map_err(|e| VaultError::SolanaClient(e.to_string()))?
+                .amount
+                .parse::<u64>()
+                .map_err(|e| VaultError::SolanaClient(format!("Invalid token amount: {e}")))?;
+
+            let book_balance = vault.total_balance as i64;
+            let token_balance = token_balance as i64;
+            if book_balance == token_balance {
+                continue;
+            }
+
+            drifts.push(BalanceDrift {
+                owner,
+                vault_address,
+                book_balance,
+                token_balance,
+                drift: token_balance - book_balance,
+            });
+        }
+
+        // biggest discrepancies first, in either direction
+        drifts.sort_by_key(|d| std::cmp::Reverse(d.drift.abs()));
+
+        Ok(drifts)
+    }
+
+    // None until the admin has run initialize_insurance_fund
+    pub async fn get_insurance_fund(&self) -> Result<Option<InsuranceFundStatus>> {
+        use anchor_lang::AccountDeserialize;
+        use collateral_vault::state::InsuranceFund;
+
+        let (fund_pda, _) = self.solana_client.derive_insurance_fund_pda();
+
+        let account = self
+            .solana_client
+            .rpc
+            .get_account_with_commitment(&fund_pda, self.solana_client.rpc.commitment())
+            .map_err(|e| VaultError::SolanaClient(e.to_string()))?
+            .value;
+        let Some(account) = account else {
+            return Ok(None);
+        };
+
+        let fund = InsuranceFund::try_deserialize(&mut account.data.as_slice())
+            .map_err(|e| VaultError::SolanaClient(format!("Failed to decode insurance fund: {e}")))?;
+
+        let token_balance = self
+            .solana_client
+            .rpc
+            .get_token_account_balance(&fund.token_account)
+            .map_err(|e| VaultError::SolanaClient(e.to_string()))?
+            .amount
+            .parse::<u64>()
+            .map_err(|e| VaultError::SolanaClient(format!("Invalid token amount: {e}")))?;
+
+        Ok(Some(InsuranceFundStatus {
+            fund_address: fund_pda.to_string(),
+            token_account: fund.token_account.to_string(),
+            token_balance: token_balance as i64,
+            fee_share_bps: fund.fee_share_bps,
+            total_contributed: fund.total_contributed as i64,
+            total_paid_out: fund.total_paid_out as i64,
+        }))
+    }
+
+    // None when the owner never created a journal. entries come back oldest first
+    pub async fn get_journal(&self, user_pubkey: &str) -> Result<Option<Vec<JournalEntryRecord>>> {
+        use anchor_lang::Discriminator;
+        use collateral_vault::state::{JournalOp, VaultJournal};
+
+        let user = Pubkey::from_str(user_pubkey)
+            .map_err(|e| VaultError::InvalidAmount(format!("Invalid pubkey: {}", e)))?;
+
+        let (vault_pda, _) = self.solana_client.derive_vault_pda(&user);
+        let (journal_pda, _) = self.solana_client.derive_journal_pda(&vault_pda);
+
+        let account = self
+            .solana_client
+            .rpc
+            .get_account_with_commitment(&journal_pda, self.solana_client.rpc.commitment())
+            .map_err(|e| VaultError::SolanaClient(e.to_string()))?
+            .value;
+        let Some(account) = account else {
+            return Ok(None);
+        };
+
+        let disc_len = VaultJournal::DISCRIMINATOR.len();
+        let end = disc_len + std::mem::size_of::<VaultJournal>();
+        if account.data.len() < end || &account.data[..disc_len] != VaultJournal::DISCRIMINATOR {
+            return Err(VaultError::SolanaClient(
+                "Invalid journal account data".to_string(),
+            ));
+        }
+        let journal: VaultJournal = bytemuck::pod_read_unaligned(&account.data[disc_len..end]);
+
+        let entries = journal
+            .entries_in_order()
+            .into_iter()
+            .filter_map(|entry| {
+                let op = match JournalOp::from_u8(entry.op)? {
+                    JournalOp::Deposit => "deposit",
+                    JournalOp::Withdraw => "withdraw",
+                    JournalOp::Lock => "lock",
+                    JournalOp::Unlock => "unlock",
+                    JournalOp::ExecuteWithdrawal => "execute_withdrawal",
+                    JournalOp::ScheduledWithdrawal => "scheduled_withdrawal",
+                    JournalOp::PermitWithdrawal => "permit_withdrawal",
+                    JournalOp::TransferIn => "transfer_in",
+                    JournalOp::TransferOut => "transfer_out",
+                    JournalOp::Seize => "seize",
+                    JournalOp::Donation => "donation",
+                    JournalOp::Yield => "yield",
+                };
+
+                Some(JournalEntryRecord {
+                    seq: entry.seq,
+                    slot: entry.slot,
+                    op: op.to_string(),
+                    amount: entry.amount as i64,
+                    counterparty: entry.counterparty.to_string(),
+                })
+            })
+            .collect();
+
+        Ok(Some(entries))
+    }
+
+    // the vault is a fixed-layout zero-copy account, so this is a straight copy out of the
+    // account data. rpc buffers aren't 8-byte aligned, hence the unaligned read
+    pub fn decode_vault(account_data: &[u8]) -> Result<collateral_vault::state::CollateralVault> {
+        use anchor_lang::Discriminator;
+        use collateral_vault::state::CollateralVault;
+
+        let disc_len = CollateralVault::DISCRIMINATOR.len();
+        let end = disc_len + std::mem::size_of::<CollateralVault>();
+
+        if account_data.len() < end {
+            return Err(VaultError::SolanaClient(
+                "Invalid vault account data: too short".to_string(),
+            ));
+        }
+        if &account_data[..disc_len] != CollateralVault::DISCRIMINATOR {
+            return Err(VaultError::SolanaClient(
+                "Vault account is still on the old layout, run migrate_vault".to_string(),
+            ));
+        }
+
+        Ok(bytemuck::pod_read_unaligned(&account_data[disc_len..end]))
+    }
+
+    pub fn freeze_status(vault: &collateral_vault::state::CollateralVault) -> FreezeStatus {
+        use chrono::TimeZone;
+
+        if !vault.is_frozen() {
+            return FreezeStatus::default();
+        }
+
+        FreezeStatus {
+            is_frozen: true,
+            freeze_reason: Some(vault.freeze_reason as i16),
+            frozen_at: chrono::Utc.timestamp_opt(vault.frozen_at, 0).single(),
+        }
+    }
+
     pub fn solana_client(&self) -> &SolanaClient {
         &self.solana_client
     }
 
+    // references of every DepositEvent the program emitted for this vault in the transaction,
+    // hex encoded like the API takes them. other programs in the same transaction can write
+    // "Program data:" lines too, so only lines logged while our program is on top of the
+    // invocation stack count
+    fn deposit_references(
+        &self,
+        sig: &anchor_client::solana_sdk::signature::Signature,
+        vault_pda: &Pubkey,
+    ) -> Result<Vec<String>> {
+        use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+        use anchor_lang::{AnchorDeserialize, Discriminator};
+        use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+        use base64::Engine;
+        use collateral_vault::state::DepositEvent;
+        use solana_client::rpc_config::RpcTransactionConfig;
+
+        let config = RpcTransactionConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+            ..Default::default()
+        };
+
+        let tx = self
+            .solana_client
+            .rpc
+            .get_transaction_with_config(sig, config)
+            .map_err(|e| VaultError::SolanaClient(format!("Failed to fetch transaction {sig}: {e}")))?;
+
+        let logs: Vec<String> = tx
+            .transaction
+            .meta
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+            .unwrap_or_default();
+
+        let program_id = self.solana_client.program_id.to_string();
+        let mut stack: Vec<&str> = Vec::new();
+        let mut references = Vec::new();
+
+        for line in logs.iter() {
+            if let Some(rest) = line.strip_prefix("Program ") {
+                if let Some((id, tail)) = rest.split_once(' ') {
+                    if tail.starts_with("invoke [") {
+                        stack.push(id);
+                        continue;
+                    }
+                    if tail == "success" || tail.starts_with("failed") {
+                        stack.pop();
+                        continue;
+                    }
+                }
+            }
+
+            let Some(data) = line.strip_prefix("Program data: ") else {
+                continue;
+            };
+            if stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+
+            let Ok(bytes) = BASE64_STANDARD.decode(data) else {
+                continue;
+            };
+            if bytes.len() < 8 || &bytes[..8] != DepositEvent::DISCRIMINATOR {
+                continue;
+            }
+
+            let event = DepositEvent::deserialize(&mut &bytes[8..])
+                .map_err(|e| VaultError::SolanaClient(format!("Invalid DepositEvent in {sig}: {e}")))?;
+            if event.vault != *vault_pda {
+                continue;
+            }
+
+            if let Some(reference) = event.reference {
+                references.push(reference.iter().map(|b| format!("{b:02x}")).collect());
+            }
+        }
+
+        Ok(references)
+    }
+
     // called after client submits a tx to sync DB with on-chain state
     // polls for confirmation since client might call this immediately after submit
     pub async fn sync_confirmed_tx(
@@ -110,6 +457,7 @@ impl VaultManager {
         signature: &str,
         expected_type: crate::db::models::TransactionType,
         expected_amount: Option<i64>,
+        reference: Option<String>,
     ) -> Result<VaultRecord> {
         use anchor_client::solana_sdk::signature::Signature;
 
@@ -168,22 +516,13 @@ impl VaultManager {
                 ))
             })?;
 
-        // manually parsing account data since we're not using Anchor's Account wrapper
-        if account_data.len() < 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 {
-            return Err(VaultError::SolanaClient(
-                "Invalid vault account data".to_string(),
-            ));
-        }
-
-        let data = &account_data[8..];
-        let offset = 32 + 32;
-        
-        use std::convert::TryInto;
-        let total_balance = u64::from_le_bytes(data[offset..offset+8].try_into().unwrap()) as i64;
-        let locked_balance = u64::from_le_bytes(data[offset+8..offset+16].try_into().unwrap()) as i64;
-        let available_balance = u64::from_le_bytes(data[offset+16..offset+24].try_into().unwrap()) as i64;
-        let total_deposited = u64::from_le_bytes(data[offset+24..offset+32].try_into().unwrap()) as i64;
-        let total_withdrawn = u64::from_le_bytes(data[offset+32..offset+40].try_into().unwrap()) as i64;
+        let vault = Self::decode_vault(&account_data)?;
+        let total_balance = vault.total_balance as i64;
+        let locked_balance = vault.locked_balance as i64;
+        let available_balance = vault.available_balance as i64;
+        let total_deposited = vault.total_deposited as i64;
+        let total_withdrawn = vault.total_withdrawn as i64;
+        let freeze = Self::freeze_status(&vault);
 
         if let Some(expected_amount) = expected_amount {
             if expected_amount <= 0 {
@@ -193,6 +532,22 @@ impl VaultManager {
             }
         }
 
+        // reconciliation looks deposits up by reference, so the stored one has to be what the
+        // program actually emitted for this vault, not whatever the client sent along
+        let on_chain_references = self.deposit_references(&sig, &vault_pda)?;
+        let reference = match reference {
+            Some(claimed) => {
+                if !on_chain_references.contains(&claimed) {
+                    return Err(VaultError::InvalidAmount(format!(
+                        "Reference {claimed} does not match any deposit to {vault_pda} in {signature}"
+                    )));
+                }
+                Some(claimed)
+            }
+            None if on_chain_references.len() == 1 => on_chain_references.into_iter().next(),
+            None => None,
+        };
+
         let vault_row = sqlx::query_as::<_, VaultRecord>(
             r#"
             INSERT INTO public.vaults (
@@ -202,9 +557,12 @@ impl VaultManager {
                 locked_balance,
                 available_balance,
                 total_deposited,
-                total_withdrawn
+                total_withdrawn,
+                is_frozen,
+                freeze_reason,
+                frozen_at
             )
-            VALUES ($1,$2,$3,$4,$5,$6,$7)
+            VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10)
             ON CONFLICT (vault_address)
             DO UPDATE SET
                 owner = EXCLUDED.owner,
@@ -213,6 +571,9 @@ impl VaultManager {
                 available_balance = EXCLUDED.available_balance,
                 total_deposited = EXCLUDED.total_deposited,
                 total_withdrawn = EXCLUDED.total_withdrawn,
+                is_frozen = EXCLUDED.is_frozen,
+                freeze_reason = EXCLUDED.freeze_reason,
+                frozen_at = EXCLUDED.frozen_at,
                 updated_at = NOW()
             RETURNING *
             "#,
@@ -224,6 +585,9 @@ impl VaultManager {
         .bind(available_balance)
         .bind(total_deposited)
         .bind(total_withdrawn)
+        .bind(freeze.is_frozen)
+        .bind(freeze.freeze_reason)
+        .bind(freeze.frozen_at)
         .fetch_one(&self.db_pool)
         .await
         .map_err(|e| VaultError::Database(e.to_string()))?;
@@ -245,15 +609,17 @@ impl VaultManager {
                     vault_address,
                     transaction_type,
                     amount,
-                    signature
+                    signature,
+                    reference
                 )
-                VALUES ($1,$2,$3,$4)
+                VALUES ($1,$2,$3,$4,$5)
                 "#,
             )
             .bind(vault_pda.to_string())
             .bind(expected_type)
             .bind(amount_to_store)
             .bind(signature)
+            .bind(reference)
             .execute(&self.db_pool)
             .await
             .map_err(|e| VaultError::Database(e.to_string()))?;
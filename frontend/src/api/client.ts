@@ -18,6 +18,9 @@ export interface BalanceResponse {
     available_balance: number
     total_deposited: number
     total_withdrawn: number
+    is_frozen: boolean
+    freeze_reason: number | null
+    frozen_at: string | null
     created_at: string
     updated_at: string
   }
@@ -33,10 +36,11 @@ export interface TransactionsResponse {
   transactions: Array<{
     id: number
     vault_address: string
-    transaction_type: 'deposit' | 'withdrawal' | 'lock' | 'unlock' | 'transfer'
+    transaction_type: 'deposit' | 'withdrawal' | 'lock' | 'unlock' | 'transfer' | 'peer_transfer'
     amount: number
     signature: string
     created_at: string
+    reference: string | null
   }>
 }
 
@@ -88,6 +92,9 @@ export interface ForceSyncResponse {
     available_balance: number
     total_deposited: number
     total_withdrawn: number
+    is_frozen: boolean
+    freeze_reason: number | null
+    frozen_at: string | null
     created_at: string
     updated_at: string
   }
@@ -112,6 +119,9 @@ export interface SyncYieldResponse {
     available_balance: number
     total_deposited: number
     total_withdrawn: number
+    is_frozen: boolean
+    freeze_reason: number | null
+    frozen_at: string | null
     created_at: string
     updated_at: string
   }
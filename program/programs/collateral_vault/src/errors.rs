@@ -130,5 +130,130 @@ pub enum VaultError {
     
     #[msg("Operation not allowed")]
     OperationNotAllowed,
-}
 
+    #[msg("Deposit would exceed the per-vault balance cap")]
+    VaultDepositCapExceeded,
+
+    #[msg("Deposit would exceed the global deposit cap")]
+    GlobalDepositCapExceeded,
+
+    #[msg("Lock expiry must be in the future")]
+    InvalidLockExpiry,
+
+    #[msg("Lock has no expiry or has not expired yet")]
+    LockNotExpired,
+
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+
+    #[msg("Nothing has vested since the last release")]
+    NothingToRelease,
+
+    #[msg("Settlement debits and credits do not balance")]
+    SettlementNotConserved,
+
+    #[msg("Memo is too long")]
+    MemoTooLong,
+
+    #[msg("Vault book balance is higher than its token account balance")]
+    BookBalanceExceedsActual,
+
+    #[msg("Vault book balance already matches its token account")]
+    BalanceAlreadySynced,
+
+    #[msg("Pull would exceed the allowance for the current period")]
+    AllowanceExceeded,
+
+    #[msg("Allowance period must be positive")]
+    InvalidAllowancePeriod,
+
+    #[msg("Escrow deadline must be in the future")]
+    InvalidEscrowDeadline,
+
+    #[msg("Escrow deadline has passed")]
+    EscrowExpired,
+
+    #[msg("Escrow deadline has not passed yet")]
+    EscrowNotExpired,
+
+    #[msg("Preimage does not match the escrow hashlock")]
+    InvalidPreimage,
+
+    #[msg("Sub-account name must be 1 to 32 bytes")]
+    InvalidSubAccountName,
+
+    #[msg("Sub-account still holds funds")]
+    SubAccountNotEmpty,
+
+    #[msg("Insufficient sub-account balance")]
+    InsufficientSubAccountBalance,
+
+    #[msg("Insufficient pool shares")]
+    InsufficientShares,
+
+    #[msg("Share price moved past the caller's limit")]
+    SlippageExceeded,
+
+    #[msg("Insurance fund balance is too low")]
+    InsufficientInsuranceFunds,
+
+    #[msg("Program is not permitted to perform this operation")]
+    ProgramPermissionDenied,
+
+    #[msg("Program exposure cap exceeded")]
+    ProgramExposureCapExceeded,
+
+    #[msg("Vault is frozen")]
+    VaultFrozen,
+
+    #[msg("Vault is not frozen")]
+    VaultNotFrozen,
+
+    #[msg("Vault is already on the current layout")]
+    VaultAlreadyMigrated,
+
+    #[msg("Invalid withdrawal schedule")]
+    InvalidWithdrawalSchedule,
+
+    #[msg("Scheduled withdrawal is not due yet")]
+    WithdrawalNotDue,
+
+    #[msg("Scheduled withdrawal has run its last execution")]
+    WithdrawalScheduleFinished,
+
+    #[msg("Withdrawal permit signature is missing or invalid")]
+    InvalidPermitSignature,
+
+    #[msg("Withdrawal permit has expired")]
+    PermitExpired,
+
+    #[msg("Withdrawal permit nonce does not match the vault")]
+    InvalidPermitNonce,
+
+    #[msg("Invalid session key configuration")]
+    InvalidSessionKey,
+
+    #[msg("Session key has expired")]
+    SessionExpired,
+
+    #[msg("Session key is not allowed to perform this operation")]
+    SessionScopeNotAllowed,
+
+    #[msg("Session key amount cap exceeded")]
+    SessionCapExceeded,
+
+    #[msg("Insurance fund account is required once the fund exists")]
+    InsuranceFundRequired,
+
+    #[msg("Program still has collateral locked")]
+    ProgramHasExposure,
+
+    #[msg("Not enough locked collateral outside other reservations")]
+    InsufficientLockedBalance,
+
+    #[msg("Journal account is required while the vault has a journal")]
+    JournalRequired,
+
+    #[msg("Program can't release more than it has locked in this vault")]
+    ProgramExposureExceeded,
+}
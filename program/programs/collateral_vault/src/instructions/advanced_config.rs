@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 
-use crate::state::{CollateralVault, DelegationEvent, EmergencyModeToggled, MultiSigConfigured};
+use crate::state::{
+    CollateralVault, DelegationEvent, EmergencyModeToggled, MultiSigConfigured, RateLimitConfigured,
+    TimelockConfigured, WhitelistToggled, WhitelistUpdated, EVENT_VERSION,
+};
 use crate::errors::VaultError;
 
 pub fn configure_multisig(
@@ -8,7 +11,8 @@ pub fn configure_multisig(
     threshold: u8,
     signers: Vec<Pubkey>,
 ) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
     require!(threshold > 0, VaultError::InvalidMultiSigThreshold);
@@ -19,10 +23,12 @@ pub fn configure_multisig(
     require!(signers.len() <= 10, VaultError::MaxSignersReached);
 
     vault.multisig_threshold = threshold;
-    vault.authorized_signers = signers.clone();
+    vault.set_signers(&signers)?;
 
     emit!(MultiSigConfigured {
-        vault: vault.key(),
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
         threshold,
         signers_count: signers.len() as u8,
         timestamp: clock.unix_timestamp,
@@ -33,13 +39,16 @@ pub fn configure_multisig(
 }
 
 pub fn add_delegate(ctx: Context<ManageDelegate>, user: Pubkey) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
     vault.add_delegated_user(user)?;
 
     emit!(DelegationEvent {
-        vault: vault.key(),
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
         user,
         action: "added".to_string(),
         timestamp: clock.unix_timestamp,
@@ -50,13 +59,16 @@ pub fn add_delegate(ctx: Context<ManageDelegate>, user: Pubkey) -> Result<()> {
 }
 
 pub fn remove_delegate(ctx: Context<ManageDelegate>, user: Pubkey) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
     vault.remove_delegated_user(&user)?;
 
     emit!(DelegationEvent {
-        vault: vault.key(),
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
         user,
         action: "removed".to_string(),
         timestamp: clock.unix_timestamp,
@@ -67,27 +79,62 @@ pub fn remove_delegate(ctx: Context<ManageDelegate>, user: Pubkey) -> Result<()>
 }
 
 pub fn add_to_whitelist(ctx: Context<ManageWhitelist>, address: Pubkey) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let clock = Clock::get()?;
+
     vault.add_to_whitelist(address)?;
+
+    emit!(WhitelistUpdated {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        address,
+        added: true,
+        whitelist_count: vault.whitelist_count,
+        timestamp: clock.unix_timestamp,
+    });
+
     msg!("Added {} to withdrawal whitelist", address);
     Ok(())
 }
 
 pub fn remove_from_whitelist(ctx: Context<ManageWhitelist>, address: Pubkey) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
-    
-    if let Some(pos) = vault.withdrawal_whitelist.iter().position(|x| x == &address) {
-        vault.withdrawal_whitelist.remove(pos);
-        msg!("Removed {} from withdrawal whitelist", address);
-        Ok(())
-    } else {
-        Err(error!(VaultError::AddressNotWhitelisted))
-    }
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let clock = Clock::get()?;
+
+    vault.remove_from_whitelist(&address)?;
+
+    emit!(WhitelistUpdated {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        address,
+        added: false,
+        whitelist_count: vault.whitelist_count,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Removed {} from withdrawal whitelist", address);
+    Ok(())
 }
 
 pub fn toggle_whitelist(ctx: Context<ConfigureVault>, enabled: bool) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
-    vault.whitelist_enabled = enabled;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let clock = Clock::get()?;
+
+    vault.whitelist_enabled = enabled as u8;
+
+    emit!(WhitelistToggled {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        enabled,
+        timestamp: clock.unix_timestamp,
+    });
+
     msg!("Withdrawal whitelist {}", if enabled { "enabled" } else { "disabled" });
     Ok(())
 }
@@ -97,7 +144,8 @@ pub fn configure_rate_limit(
     max_amount: u64,
     time_window: i64,
 ) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
     require!(time_window > 0, VaultError::InvalidRateLimitConfig);
@@ -107,13 +155,34 @@ pub fn configure_rate_limit(
     vault.rate_limit_window_start = clock.unix_timestamp;
     vault.rate_limit_withdrawn = 0;
 
+    emit!(RateLimitConfigured {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        max_amount,
+        time_window,
+        timestamp: clock.unix_timestamp,
+    });
+
     msg!("Rate limit configured: {} per {} seconds", max_amount, time_window);
     Ok(())
 }
 
 pub fn configure_timelock(ctx: Context<ConfigureVault>, duration: i64) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let clock = Clock::get()?;
+
     vault.withdrawal_timelock = duration;
+
+    emit!(TimelockConfigured {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        duration,
+        timestamp: clock.unix_timestamp,
+    });
+
     msg!("Withdrawal timelock set to {} seconds", duration);
     Ok(())
 }
@@ -121,13 +190,16 @@ pub fn configure_timelock(ctx: Context<ConfigureVault>, duration: i64) -> Result
 // emergency mode bypasses timelock but not whitelist
 // this is intentional - whitelist is for regulatory/compliance, timelock is just for safety
 pub fn toggle_emergency_mode(ctx: Context<ConfigureVault>, enabled: bool) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
-    vault.emergency_mode = enabled;
+    vault.emergency_mode = enabled as u8;
 
     emit!(EmergencyModeToggled {
-        vault: vault.key(),
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
         enabled,
         timestamp: clock.unix_timestamp,
     });
@@ -144,10 +216,11 @@ pub struct ConfigureMultiSig<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
@@ -161,10 +234,11 @@ pub struct ManageDelegate<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
@@ -178,10 +252,11 @@ pub struct ManageWhitelist<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
@@ -195,10 +270,11 @@ pub struct ConfigureVault<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
@@ -0,0 +1,272 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    Allowance, AllowanceGranted, AllowancePulled, AllowanceRevoked, CollateralVault, JournalOp,
+    ProtocolConfig, VaultJournal, WithdrawEvent, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::{record_journal, settle_vesting_schedules};
+
+// changing an allowance is revoke + grant, that way the period counter can't be reset
+// by accident while the spender is mid-period
+pub fn grant_allowance(
+    ctx: Context<GrantAllowance>,
+    spender: Pubkey,
+    amount_per_period: u64,
+    period: i64,
+) -> Result<()> {
+    require!(amount_per_period > 0, VaultError::InvalidAmount);
+    require!(period > 0, VaultError::InvalidAllowancePeriod);
+    require!(spender != ctx.accounts.owner.key(), VaultError::OperationNotAllowed);
+
+    let clock = Clock::get()?;
+    let allowance = &mut ctx.accounts.allowance;
+    allowance.vault = ctx.accounts.vault.key();
+    allowance.spender = spender;
+    allowance.amount_per_period = amount_per_period;
+    allowance.period = period;
+    allowance.period_start = clock.unix_timestamp;
+    allowance.pulled_in_period = 0;
+    allowance.total_pulled = 0;
+    allowance.created_at = clock.unix_timestamp;
+    allowance.bump = ctx.bumps.allowance;
+
+    emit!(AllowanceGranted {
+        version: EVENT_VERSION,
+        vault: allowance.vault,
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        spender,
+        amount_per_period,
+        period,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Granted {} an allowance of {} every {} seconds",
+        spender,
+        amount_per_period,
+        period
+    );
+
+    Ok(())
+}
+
+pub fn revoke_allowance(ctx: Context<RevokeAllowance>, spender: Pubkey) -> Result<()> {
+    let clock = Clock::get()?;
+
+    emit!(AllowanceRevoked {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        spender,
+        total_pulled: ctx.accounts.allowance.total_pulled,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Revoked allowance for {}", spender);
+
+    Ok(())
+}
+
+// spender pulls from available_balance into any token account it likes, no owner signature
+pub fn pull_allowance<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PullAllowance<'info>>,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let vault_key = ctx.accounts.vault.key();
+    let allowance = &mut ctx.accounts.allowance;
+    let clock = Clock::get()?;
+
+    allowance.check_and_update(amount, &clock)?;
+
+    let (owner_key, bump) = {
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        settle_vesting_schedules(
+            &mut vault,
+            &vault_key,
+            ctx.accounts.journal.as_ref(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+
+        // the spender picks the destination, so it gets the same whitelist check as an
+        // owner-built withdrawal to a third party
+        if vault.whitelist_enabled != 0 {
+            require!(
+                vault.is_withdrawal_allowed(&ctx.accounts.destination_token_account.owner),
+                VaultError::RecipientNotWhitelisted
+            );
+        }
+
+        // the owner's own rate limit still caps total outflow across all spenders
+        if vault.has_rate_limit() {
+            vault.check_and_update_rate_limit(amount, &clock)?;
+        }
+
+        require!(
+            vault.available_balance >= amount,
+            VaultError::InsufficientAvailableBalance
+        );
+        (vault.owner, vault.bump)
+    };
+
+    let seeds = &[
+        b"vault",
+        owner_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.vault_token_account.to_account_info(),
+        to: ctx.accounts.destination_token_account.to_account_info(),
+        authority: ctx.accounts.vault.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.sub_withdrawal(amount)?;
+    ctx.accounts.protocol_config.record_outflow(amount);
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Withdraw,
+        amount,
+        ctx.accounts.destination_token_account.owner,
+        &clock,
+    )?;
+
+    emit!(WithdrawEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        user: vault.owner,
+        amount,
+        new_balance: vault.total_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(AllowancePulled {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        spender: allowance.spender,
+        destination: ctx.accounts.destination_token_account.key(),
+        amount,
+        remaining_in_period: allowance.amount_per_period - allowance.pulled_in_period,
+        period_start: allowance.period_start,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "{} pulled {} from vault. Remaining this period: {}",
+        allowance.spender,
+        amount,
+        allowance.amount_per_period - allowance.pulled_in_period
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(spender: Pubkey)]
+pub struct GrantAllowance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Allowance::LEN,
+        seeds = [b"allowance", vault.key().as_ref(), spender.as_ref()],
+        bump
+    )]
+    pub allowance: Account<'info, Allowance>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(spender: Pubkey)]
+pub struct RevokeAllowance<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"allowance", vault.key().as_ref(), spender.as_ref()],
+        bump = allowance.bump,
+    )]
+    pub allowance: Account<'info, Allowance>,
+}
+
+#[derive(Accounts)]
+pub struct PullAllowance<'info> {
+    pub spender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"allowance", vault.key().as_ref(), spender.key().as_ref()],
+        bump = allowance.bump,
+        has_one = spender @ VaultError::InvalidAuthority,
+    )]
+    pub allowance: Account<'info, Allowance>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
@@ -1,8 +1,14 @@
 use anchor_lang::prelude::*;
 
-use crate::state::VaultAuthority;
+use crate::state::{
+    AuthorizedProgram, CollateralVault, ProgramDeregistered, ProgramExposure,
+    ProgramExposureOpened, ProgramLimitsConfigured, ProgramRegistered, ProgramRegistry,
+    VaultAuthority, EVENT_VERSION,
+};
 use crate::errors::VaultError;
 
+// authorized_programs only stages programs for migrate_authorized_program now, access itself
+// comes from the per-program registry accounts created by register_program or the migration
 pub fn initialize_authority(
     ctx: Context<InitializeAuthority>,
     authorized_programs: Vec<Pubkey>,
@@ -40,6 +46,146 @@ pub fn remove_authorized_program(ctx: Context<UpdateAuthority>, program: Pubkey)
     Ok(())
 }
 
+pub fn register_program(ctx: Context<RegisterProgram>, program: Pubkey) -> Result<()> {
+    let registry = &mut ctx.accounts.program_registry;
+    let clock = Clock::get()?;
+
+    registry.limits = AuthorizedProgram::unrestricted(program);
+    registry.registered_at = clock.unix_timestamp;
+    registry.bump = ctx.bumps.program_registry;
+
+    emit!(ProgramRegistered {
+        version: EVENT_VERSION,
+        program_id: program,
+        registry: registry.key(),
+        migrated: false,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Registered program: {}", program);
+
+    Ok(())
+}
+
+// moves one entry out of the old VaultAuthority list into its own registry account. list
+// entries had no limits, so it starts unrestricted like a fresh register_program
+pub fn migrate_authorized_program(ctx: Context<MigrateAuthorizedProgram>, program: Pubkey) -> Result<()> {
+    ctx.accounts.vault_authority.remove_program(&program)?;
+
+    let registry = &mut ctx.accounts.program_registry;
+    let clock = Clock::get()?;
+
+    registry.limits = AuthorizedProgram::unrestricted(program);
+    registry.registered_at = clock.unix_timestamp;
+    registry.bump = ctx.bumps.program_registry;
+
+    emit!(ProgramRegistered {
+        version: EVENT_VERSION,
+        program_id: program,
+        registry: registry.key(),
+        migrated: true,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Migrated program {}, {} left in the legacy list",
+        program,
+        ctx.accounts.vault_authority.authorized_programs.len()
+    );
+
+    Ok(())
+}
+
+// closing the registry with funds still locked would leave them with no program to release
+// them and no exposure left to account for, so everything has to be unlocked first
+pub fn deregister_program(ctx: Context<DeregisterProgram>, program: Pubkey) -> Result<()> {
+    let registry = &ctx.accounts.program_registry;
+    require!(
+        registry.limits.current_exposure == 0,
+        VaultError::ProgramHasExposure
+    );
+
+    let clock = Clock::get()?;
+    emit!(ProgramDeregistered {
+        version: EVENT_VERSION,
+        program_id: program,
+        registry: registry.key(),
+        current_exposure: registry.limits.current_exposure,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Deregistered program: {}", program);
+
+    Ok(())
+}
+
+// current_exposure is left alone so tightening a cap doesn't forget what's already locked
+pub fn configure_program_limits(
+    ctx: Context<ConfigureProgramLimits>,
+    program: Pubkey,
+    enabled: bool,
+    permissions: u8,
+    max_lock_per_vault: u64,
+    global_exposure_cap: u64,
+) -> Result<()> {
+    require!(
+        permissions & !AuthorizedProgram::PERMISSION_ALL == 0,
+        VaultError::InvalidConfiguration
+    );
+
+    let limits = &mut ctx.accounts.program_registry.limits;
+
+    limits.enabled = enabled;
+    limits.permissions = permissions;
+    limits.max_lock_per_vault = max_lock_per_vault;
+    limits.global_exposure_cap = global_exposure_cap;
+
+    let clock = Clock::get()?;
+    emit!(ProgramLimitsConfigured {
+        version: EVENT_VERSION,
+        program_id: program,
+        enabled,
+        permissions,
+        max_lock_per_vault,
+        global_exposure_cap,
+        current_exposure: limits.current_exposure,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Program {} limits: enabled {}, permissions {:#05b}, per-vault {}, global {}",
+        program,
+        enabled,
+        permissions,
+        max_lock_per_vault,
+        global_exposure_cap
+    );
+
+    Ok(())
+}
+
+// permissionless like open_pool_position, whoever sets up the integration pays the rent.
+// the account has to exist before the program's first lock in this vault
+pub fn open_program_exposure(ctx: Context<OpenProgramExposure>) -> Result<()> {
+    let exposure = &mut ctx.accounts.program_exposure;
+    exposure.program_id = ctx.accounts.program_registry.limits.program_id;
+    exposure.vault = ctx.accounts.vault.key();
+    exposure.locked = 0;
+    exposure.bump = ctx.bumps.program_exposure;
+
+    let clock = Clock::get()?;
+    emit!(ProgramExposureOpened {
+        version: EVENT_VERSION,
+        vault: exposure.vault,
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        program_id: exposure.program_id,
+        exposure: exposure.key(),
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct InitializeAuthority<'info> {
     #[account(mut)]
@@ -73,3 +219,136 @@ pub struct UpdateAuthority<'info> {
     pub vault_authority: Account<'info, VaultAuthority>,
 }
 
+#[derive(Accounts)]
+#[instruction(program: Pubkey)]
+pub struct RegisterProgram<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == vault_authority.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"vault_authority"],
+        bump = vault_authority.bump,
+    )]
+    pub vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ProgramRegistry::LEN,
+        seeds = [b"program_registry", program.as_ref()],
+        bump
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(program: Pubkey)]
+pub struct MigrateAuthorizedProgram<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == vault_authority.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault_authority"],
+        bump = vault_authority.bump,
+    )]
+    pub vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ProgramRegistry::LEN,
+        seeds = [b"program_registry", program.as_ref()],
+        bump
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(program: Pubkey)]
+pub struct DeregisterProgram<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == vault_authority.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"vault_authority"],
+        bump = vault_authority.bump,
+    )]
+    pub vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"program_registry", program.as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(program: Pubkey)]
+pub struct ConfigureProgramLimits<'info> {
+    #[account(
+        constraint = admin.key() == vault_authority.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"vault_authority"],
+        bump = vault_authority.bump,
+    )]
+    pub vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", program.as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct OpenProgramExposure<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = ProgramExposure::LEN,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), vault.key().as_ref()],
+        bump
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::state::{CollateralVault, DepositEvent, WithdrawEvent};
+use crate::state::{
+    CollateralVault, DepositEvent, JournalOp, ProtocolConfig, VaultJournal, WhitelistUpdated, WithdrawEvent,
+    WithdrawalCancelled, WithdrawalRequested, EVENT_VERSION,
+};
 use crate::errors::VaultError;
+use crate::instructions::{record_journal, settle_vesting_schedules};
 
 const MAX_BATCH_SIZE: usize = 10;
 
@@ -16,19 +20,39 @@ pub struct BatchWithdrawItem {
     pub amount: u64,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum BatchOperation {
+    Deposit(BatchDepositItem),
+    Withdraw(BatchWithdrawItem),
+    RequestWithdrawal { amount: u64, recipient: Pubkey },
+    CancelWithdrawal,
+    AddToWhitelist { address: Pubkey },
+    RemoveFromWhitelist { address: Pubkey },
+}
+
 pub fn batch_deposit(ctx: Context<BatchDeposit>, amounts: Vec<u64>) -> Result<()> {
     require!(
-        amounts.len() > 0 && amounts.len() <= MAX_BATCH_SIZE,
+        !amounts.is_empty() && amounts.len() <= MAX_BATCH_SIZE,
         VaultError::BatchLimitExceeded
     );
 
-    let vault = &mut ctx.accounts.vault;
+    // checking caps against the whole batch up front so we never stop halfway through
+    let mut batch_total = 0u64;
+    for amount in amounts.iter() {
+        require!(*amount > 0, VaultError::InvalidAmount);
+        batch_total = batch_total.checked_add(*amount)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+    }
+
+    ctx.accounts
+        .protocol_config
+        .check_deposit_caps(ctx.accounts.vault.load()?.total_balance, batch_total)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
     let mut total_deposited = 0u64;
 
     for amount in amounts.iter() {
-        require!(*amount > 0, VaultError::InvalidAmount);
-
         let cpi_accounts = Transfer {
             from: ctx.accounts.user_token_account.to_account_info(),
             to: ctx.accounts.vault_token_account.to_account_info(),
@@ -42,26 +66,42 @@ pub fn batch_deposit(ctx: Context<BatchDeposit>, amounts: Vec<u64>) -> Result<()
         vault.add_deposit(*amount)?;
         total_deposited = total_deposited.checked_add(*amount)
             .ok_or(error!(VaultError::NumericalOverflow))?;
+        record_journal(
+            &vault,
+            ctx.accounts.journal.as_ref(),
+            JournalOp::Deposit,
+            *amount,
+            ctx.accounts.user.key(),
+            &clock,
+        )?;
 
         emit!(DepositEvent {
+            version: EVENT_VERSION,
+            vault: ctx.accounts.vault.key(),
+            event_seq: vault.next_event_seq()?,
             user: ctx.accounts.owner.key(),
             amount: *amount,
             new_balance: vault.total_balance,
             timestamp: clock.unix_timestamp,
+            reference: None,
         });
     }
 
+    ctx.accounts.protocol_config.record_inflow(total_deposited)?;
+
     msg!("Batch deposited {} items, total: {}", amounts.len(), total_deposited);
     Ok(())
 }
 
-pub fn batch_withdraw(ctx: Context<BatchWithdraw>, amounts: Vec<u64>) -> Result<()> {
+pub fn batch_withdraw<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchWithdraw<'info>>,
+    amounts: Vec<u64>,
+) -> Result<()> {
     require!(
-        amounts.len() > 0 && amounts.len() <= MAX_BATCH_SIZE,
+        !amounts.is_empty() && amounts.len() <= MAX_BATCH_SIZE,
         VaultError::BatchLimitExceeded
     );
 
-    let vault = &mut ctx.accounts.vault;
     let clock = Clock::get()?;
     let mut total_withdrawn = 0u64;
 
@@ -72,27 +112,40 @@ pub fn batch_withdraw(ctx: Context<BatchWithdraw>, amounts: Vec<u64>) -> Result<
             .ok_or(error!(VaultError::NumericalOverflow))?;
     }
 
-    require!(
-        vault.available_balance >= total_withdrawn,
-        VaultError::InsufficientAvailableBalance
-    );
-
-    if vault.rate_limit_amount < u64::MAX {
-        vault.check_and_update_rate_limit(total_withdrawn, &clock)?;
-    }
+    let bump = {
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        let vault_key = ctx.accounts.vault.key();
+        settle_vesting_schedules(
+            &mut vault,
+            &vault_key,
+            ctx.accounts.journal.as_ref(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
 
-    if vault.whitelist_enabled {
         require!(
-            vault.is_withdrawal_allowed(&ctx.accounts.owner.key()),
-            VaultError::RecipientNotWhitelisted
+            vault.available_balance >= total_withdrawn,
+            VaultError::InsufficientAvailableBalance
         );
-    }
+
+        if vault.has_rate_limit() {
+            vault.check_and_update_rate_limit(total_withdrawn, &clock)?;
+        }
+
+        if vault.whitelist_enabled != 0 {
+            require!(
+                vault.is_withdrawal_allowed(&ctx.accounts.owner.key()),
+                VaultError::RecipientNotWhitelisted
+            );
+        }
+        vault.bump
+    };
 
     let owner_key = ctx.accounts.owner.key();
     let seeds = &[
         b"vault",
         owner_key.as_ref(),
-        &[vault.bump],
+        &[bump],
     ];
     let signer = &[&seeds[..]];
 
@@ -100,16 +153,28 @@ pub fn batch_withdraw(ctx: Context<BatchWithdraw>, amounts: Vec<u64>) -> Result<
         let cpi_accounts = Transfer {
             from: ctx.accounts.vault_token_account.to_account_info(),
             to: ctx.accounts.user_token_account.to_account_info(),
-            authority: vault.to_account_info(),
+            authority: ctx.accounts.vault.to_account_info(),
         };
 
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         token::transfer(cpi_ctx, *amount)?;
 
+        let mut vault = ctx.accounts.vault.load_mut()?;
         vault.sub_withdrawal(*amount)?;
+        record_journal(
+            &vault,
+            ctx.accounts.journal.as_ref(),
+            JournalOp::Withdraw,
+            *amount,
+            ctx.accounts.user_token_account.owner,
+            &clock,
+        )?;
 
         emit!(WithdrawEvent {
+            version: EVENT_VERSION,
+            vault: ctx.accounts.vault.key(),
+            event_seq: vault.next_event_seq()?,
             user: ctx.accounts.owner.key(),
             amount: *amount,
             new_balance: vault.total_balance,
@@ -117,10 +182,263 @@ pub fn batch_withdraw(ctx: Context<BatchWithdraw>, amounts: Vec<u64>) -> Result<
         });
     }
 
+    ctx.accounts.protocol_config.record_outflow(total_withdrawn);
+
     msg!("Batch withdrew {} items, total: {}", amounts.len(), total_withdrawn);
     Ok(())
 }
 
+// every operation is applied to the vault state first, then all deposits and withdrawals
+// are netted into at most one token transfer. any failure reverts the whole instruction.
+// only the net amount counts towards total_deposited/total_withdrawn and the rate limit,
+// a deposit and withdrawal of the same tokens in one batch never leaves the owner's wallet
+pub fn batch_execute<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchExecute<'info>>,
+    operations: Vec<BatchOperation>,
+) -> Result<()> {
+    require!(
+        !operations.is_empty() && operations.len() <= MAX_BATCH_SIZE,
+        VaultError::BatchLimitExceeded
+    );
+
+    let vault_key = ctx.accounts.vault.key();
+    let clock = Clock::get()?;
+    let owner_key = ctx.accounts.owner.key();
+
+    let mut total_deposited = 0u64;
+    let mut total_withdrawn = 0u64;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    settle_vesting_schedules(
+        &mut vault,
+        &vault_key,
+        ctx.accounts.journal.as_ref(),
+        ctx.remaining_accounts,
+        &clock,
+    )?;
+    let starting_balance = vault.total_balance;
+    let bump = vault.bump;
+
+    for operation in operations.iter() {
+        match operation {
+            BatchOperation::Deposit(item) => {
+                require!(item.amount > 0, VaultError::InvalidAmount);
+
+                vault.credit_available(item.amount)?;
+                total_deposited = total_deposited.checked_add(item.amount)
+                    .ok_or(error!(VaultError::NumericalOverflow))?;
+                record_journal(
+                    &vault,
+                    ctx.accounts.journal.as_ref(),
+                    JournalOp::Deposit,
+                    item.amount,
+                    owner_key,
+                    &clock,
+                )?;
+
+                emit!(DepositEvent {
+                    version: EVENT_VERSION,
+                    vault: vault_key,
+                    event_seq: vault.next_event_seq()?,
+                    user: owner_key,
+                    amount: item.amount,
+                    new_balance: vault.total_balance,
+                    timestamp: clock.unix_timestamp,
+                    reference: None,
+                });
+            }
+            BatchOperation::Withdraw(item) => {
+                require!(item.amount > 0, VaultError::InvalidAmount);
+                require!(
+                    vault.available_balance >= item.amount,
+                    VaultError::InsufficientAvailableBalance
+                );
+
+                if vault.whitelist_enabled != 0 {
+                    require!(
+                        vault.is_withdrawal_allowed(&owner_key),
+                        VaultError::RecipientNotWhitelisted
+                    );
+                }
+
+                vault.debit_available(item.amount)?;
+                total_withdrawn = total_withdrawn.checked_add(item.amount)
+                    .ok_or(error!(VaultError::NumericalOverflow))?;
+                record_journal(
+                    &vault,
+                    ctx.accounts.journal.as_ref(),
+                    JournalOp::Withdraw,
+                    item.amount,
+                    owner_key,
+                    &clock,
+                )?;
+
+                emit!(WithdrawEvent {
+                    version: EVENT_VERSION,
+                    vault: vault_key,
+                    event_seq: vault.next_event_seq()?,
+                    user: owner_key,
+                    amount: item.amount,
+                    new_balance: vault.total_balance,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+            BatchOperation::RequestWithdrawal { amount, recipient } => {
+                require!(*amount > 0, VaultError::InvalidAmount);
+                require!(vault.withdrawal_timelock > 0, VaultError::FeatureNotEnabled);
+
+                if vault.whitelist_enabled != 0 {
+                    require!(
+                        vault.is_withdrawal_allowed(recipient),
+                        VaultError::RecipientNotWhitelisted
+                    );
+                }
+
+                vault.request_withdrawal(*amount, *recipient, &clock)?;
+
+                emit!(WithdrawalRequested {
+                    version: EVENT_VERSION,
+                    vault: vault_key,
+                    event_seq: vault.next_event_seq()?,
+                    amount: *amount,
+                    recipient: *recipient,
+                    executable_at: vault.pending_withdrawal.executable_at,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+            BatchOperation::CancelWithdrawal => {
+                let pending = *vault.pending()
+                    .ok_or(error!(VaultError::NoPendingWithdrawal))?;
+
+                require!(
+                    clock.unix_timestamp < pending.executable_at,
+                    VaultError::CannotCancelExpiredWithdrawal
+                );
+
+                vault.clear_pending();
+
+                emit!(WithdrawalCancelled {
+                    version: EVENT_VERSION,
+                    vault: vault_key,
+                    event_seq: vault.next_event_seq()?,
+                    amount: pending.amount,
+                    recipient: pending.recipient,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+            BatchOperation::AddToWhitelist { address } => {
+                vault.add_to_whitelist(*address)?;
+
+                emit!(WhitelistUpdated {
+                    version: EVENT_VERSION,
+                    vault: vault_key,
+                    event_seq: vault.next_event_seq()?,
+                    address: *address,
+                    added: true,
+                    whitelist_count: vault.whitelist_count,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+            BatchOperation::RemoveFromWhitelist { address } => {
+                vault.remove_from_whitelist(address)?;
+
+                emit!(WhitelistUpdated {
+                    version: EVENT_VERSION,
+                    vault: vault_key,
+                    event_seq: vault.next_event_seq()?,
+                    address: *address,
+                    added: false,
+                    whitelist_count: vault.whitelist_count,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+        }
+    }
+
+    if total_deposited > total_withdrawn {
+        let net_deposit = total_deposited - total_withdrawn;
+        vault.total_deposited = vault.total_deposited
+            .checked_add(net_deposit)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+    } else if total_withdrawn > total_deposited {
+        let net_withdrawal = total_withdrawn - total_deposited;
+        if vault.has_rate_limit() {
+            vault.check_and_update_rate_limit(net_withdrawal, &clock)?;
+        }
+        vault.total_withdrawn = vault.total_withdrawn
+            .checked_add(net_withdrawal)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+    }
+
+    // the vault signs the withdrawal transfer below, its data can't stay borrowed over the cpi
+    drop(vault);
+
+    if total_deposited > total_withdrawn {
+        let net_deposit = total_deposited - total_withdrawn;
+
+        ctx.accounts
+            .protocol_config
+            .check_deposit_caps(starting_balance, net_deposit)?;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.vault_token_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, net_deposit)?;
+
+        ctx.accounts.protocol_config.record_inflow(net_deposit)?;
+    } else if total_withdrawn > total_deposited {
+        let net_withdrawal = total_withdrawn - total_deposited;
+
+        let seeds = &[
+            b"vault",
+            owner_key.as_ref(),
+            &[bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.vault.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, net_withdrawal)?;
+
+        ctx.accounts.protocol_config.record_outflow(net_withdrawal);
+    }
+
+    let total_amount = total_deposited.checked_add(total_withdrawn)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+
+    // the per-operation events above carry the state changes, this one just closes the batch
+    emit!(BatchOperationEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        user: owner_key,
+        operation_type: "execute".to_string(),
+        count: operations.len() as u8,
+        total_amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Batch executed {} operations. Deposited: {}, Withdrawn: {}",
+        operations.len(),
+        total_deposited,
+        total_withdrawn
+    );
+
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct BatchDeposit<'info> {
     #[account(mut)]
@@ -129,24 +447,39 @@ pub struct BatchDeposit<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = vault_token_account.key() == vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub vault_token_account: Account<'info, TokenAccount>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
     pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
 #[derive(Accounts)]
@@ -157,28 +490,92 @@ pub struct BatchWithdraw<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = vault_token_account.key() == vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub vault_token_account: Account<'info, TokenAccount>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
     pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+pub struct BatchExecute<'info> {
+    // whitelist and withdrawal changes are owner-only, so the owner signs directly here
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    // net withdrawals land here, so it has to be the owner's own account for the whitelist
+    // check on the owner to mean anything
+    #[account(
+        mut,
+        constraint = user_token_account.owner == owner.key() @ VaultError::InvalidTokenAccount
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
 #[event]
 pub struct BatchOperationEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
     pub user: Pubkey,
     pub operation_type: String,
     pub count: u8,
@@ -1,12 +1,17 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::state::{CollateralVault, DepositEvent};
+use crate::state::{CollateralVault, DepositEvent, JournalOp, ProtocolConfig, VaultJournal, EVENT_VERSION};
 use crate::errors::VaultError;
+use crate::instructions::record_journal;
 
-pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+pub fn deposit(ctx: Context<Deposit>, amount: u64, reference: Option<[u8; 32]>) -> Result<()> {
     require!(amount > 0, VaultError::InvalidAmount);
 
+    ctx.accounts
+        .protocol_config
+        .check_deposit_caps(ctx.accounts.vault.load()?.total_balance, amount)?;
+
     let cpi_accounts = Transfer {
         from: ctx.accounts.user_token_account.to_account_info(),
         to: ctx.accounts.vault_token_account.to_account_info(),
@@ -18,15 +23,29 @@ pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
     
     token::transfer(cpi_ctx, amount)?;
 
-    let vault = &mut ctx.accounts.vault;
+    let mut vault = ctx.accounts.vault.load_mut()?;
     vault.add_deposit(amount)?;
+    ctx.accounts.protocol_config.record_inflow(amount)?;
 
     let clock = Clock::get()?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Deposit,
+        amount,
+        ctx.accounts.user.key(),
+        &clock,
+    )?;
+
     emit!(DepositEvent {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
         user: ctx.accounts.user.key(),
         amount,
         new_balance: vault.total_balance,
         timestamp: clock.unix_timestamp,
+        reference,
     });
 
     msg!("Deposited {} to vault. New balance: {}", amount, vault.total_balance);
@@ -42,23 +61,38 @@ pub struct Deposit<'info> {
     #[account(
         mut,
         seeds = [b"vault", user.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = vault_token_account.key() == vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub vault_token_account: Account<'info, TokenAccount>,
 
     /// CHECK: This is checked in the constraint above
     pub owner: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
     pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
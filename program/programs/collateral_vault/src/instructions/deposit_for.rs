@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    CollateralVault, DepositEvent, DepositForEvent, JournalOp, ProtocolConfig, VaultJournal, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+const MAX_MEMO_LEN: usize = 64;
+
+// lets anyone (exchange, payroll program, another protocol) fund somebody else's vault
+// the payer only ever signs for its own token account, the beneficiary doesn't need to sign
+pub fn deposit_for(
+    ctx: Context<DepositFor>,
+    amount: u64,
+    memo: Option<String>,
+    reference: Option<[u8; 32]>,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    if let Some(memo) = memo.as_ref() {
+        require!(memo.len() <= MAX_MEMO_LEN, VaultError::MemoTooLong);
+    }
+
+    ctx.accounts
+        .protocol_config
+        .check_deposit_caps(ctx.accounts.vault.load()?.total_balance, amount)?;
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.payer_token_account.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: ctx.accounts.payer.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+    token::transfer(cpi_ctx, amount)?;
+
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.add_deposit(amount)?;
+    ctx.accounts.protocol_config.record_inflow(amount)?;
+
+    let clock = Clock::get()?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Deposit,
+        amount,
+        ctx.accounts.payer.key(),
+        &clock,
+    )?;
+
+    // DepositEvent keeps existing indexers counting this as a normal deposit for the beneficiary
+    emit!(DepositEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        user: vault.owner,
+        amount,
+        new_balance: vault.total_balance,
+        timestamp: clock.unix_timestamp,
+        reference,
+    });
+
+    emit!(DepositForEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        payer: ctx.accounts.payer.key(),
+        beneficiary: vault.owner,
+        amount,
+        new_balance: vault.total_balance,
+        memo,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "{} deposited {} for {}. New balance: {}",
+        ctx.accounts.payer.key(),
+        amount,
+        vault.owner,
+        vault.total_balance
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DepositFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Only used to derive the vault PDA, must match the vault owner
+    pub beneficiary: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", beneficiary.key().as_ref()],
+        bump = vault.load()?.bump,
+        constraint = vault.load()?.owner == beneficiary.key() @ VaultError::InvalidAuthority,
+        // same as the owner's own deposit, otherwise third parties could keep moving funds
+        // into an account that's under a compliance hold
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(mut)]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
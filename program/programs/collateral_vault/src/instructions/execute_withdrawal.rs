@@ -1,14 +1,28 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::state::{CollateralVault, WithdrawEvent};
+use crate::state::{CollateralVault, JournalOp, ProtocolConfig, VaultJournal, WithdrawEvent, EVENT_VERSION};
 use crate::errors::VaultError;
+use crate::instructions::{record_journal, settle_vesting_schedules};
 
-pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+pub fn execute_withdrawal<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExecuteWithdrawal<'info>>,
+) -> Result<()> {
     let clock = Clock::get()?;
 
-    let (amount, recipient) = vault.execute_pending_withdrawal(&clock)?;
+    let (amount, recipient, bump) = {
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        let vault_key = ctx.accounts.vault.key();
+        settle_vesting_schedules(
+            &mut vault,
+            &vault_key,
+            ctx.accounts.journal.as_ref(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+        let (amount, recipient) = vault.execute_pending_withdrawal(&clock)?;
+        (amount, recipient, vault.bump)
+    };
 
     require!(
         recipient == ctx.accounts.recipient.key(),
@@ -19,21 +33,35 @@ pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
     let seeds = &[
         b"vault",
         owner_key.as_ref(),
-        &[vault.bump],
+        &[bump],
     ];
     let signer = &[&seeds[..]];
 
     let cpi_accounts = Transfer {
         from: ctx.accounts.vault_token_account.to_account_info(),
         to: ctx.accounts.recipient_token_account.to_account_info(),
-        authority: vault.to_account_info(),
+        authority: ctx.accounts.vault.to_account_info(),
     };
 
     let cpi_program = ctx.accounts.token_program.to_account_info();
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
     token::transfer(cpi_ctx, amount)?;
 
+    ctx.accounts.protocol_config.record_outflow(amount);
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::ExecuteWithdrawal,
+        amount,
+        recipient,
+        &clock,
+    )?;
     emit!(WithdrawEvent {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
         user: ctx.accounts.owner.key(),
         amount,
         new_balance: vault.total_balance,
@@ -52,17 +80,23 @@ pub struct ExecuteWithdrawal<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
-    #[account(mut)]
+    // user isn't tied to the vault, so anyone can crank a matured request. the funds still
+    // have to land with the recipient that was fixed at request time
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == recipient.key() @ VaultError::InvalidTokenAccount
+    )]
     pub recipient_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = vault_token_account.key() == vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub vault_token_account: Account<'info, TokenAccount>,
 
@@ -72,5 +106,19 @@ pub struct ExecuteWithdrawal<'info> {
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
     pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
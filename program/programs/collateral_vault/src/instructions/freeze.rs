@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{CollateralVault, ProtocolConfig, VaultFrozen, VaultUnfrozen, EVENT_VERSION};
+use crate::errors::VaultError;
+
+// compliance freeze: every owner, delegate and spender instruction checks `frozen` in its
+// account constraints, including deposit_for so nothing new flows in either. authorized
+// programs can still unlock so open positions can be unwound
+pub fn freeze_vault(ctx: Context<FreezeVault>, reason_code: u8) -> Result<()> {
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    require!(!vault.is_frozen(), VaultError::VaultFrozen);
+
+    let clock = Clock::get()?;
+    vault.frozen = 1;
+    vault.freeze_reason = reason_code;
+    vault.frozen_at = clock.unix_timestamp;
+
+    emit!(VaultFrozen {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        owner: vault.owner,
+        admin: ctx.accounts.admin.key(),
+        reason_code,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Vault of {} frozen, reason code {}", vault.owner, reason_code);
+
+    Ok(())
+}
+
+pub fn unfreeze_vault(ctx: Context<FreezeVault>) -> Result<()> {
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    require!(vault.is_frozen(), VaultError::VaultNotFrozen);
+
+    let clock = Clock::get()?;
+    let reason_code = vault.freeze_reason;
+    let frozen_for = clock.unix_timestamp.saturating_sub(vault.frozen_at);
+
+    vault.frozen = 0;
+    vault.freeze_reason = 0;
+    vault.frozen_at = 0;
+
+    emit!(VaultUnfrozen {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        owner: vault.owner,
+        admin: ctx.accounts.admin.key(),
+        reason_code,
+        frozen_for,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Vault of {} unfrozen after {} seconds", vault.owner, frozen_for);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FreezeVault<'info> {
+    #[account(
+        constraint = admin.key() == protocol_config.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+}
@@ -0,0 +1,391 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    CollateralVault, HashlockEscrow, HashlockEscrowClaimed, HashlockEscrowCreated,
+    HashlockEscrowRefunded, JournalOp, LockBucket, LockEvent, ProtocolConfig, TransferEvent, UnlockEvent,
+    VaultJournal, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+const MAX_PREIMAGE_LEN: usize = 64;
+
+pub fn create_hashlock_escrow(
+    ctx: Context<CreateHashlockEscrow>,
+    escrow_id: u64,
+    amount: u64,
+    hashlock: [u8; 32],
+    deadline: i64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let from_vault_key = ctx.accounts.from_vault.key();
+    let to_vault_key = ctx.accounts.to_vault.key();
+    require!(
+        from_vault_key != to_vault_key,
+        VaultError::OperationNotAllowed
+    );
+
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    let to_owner = ctx.accounts.to_vault.load()?.owner;
+
+    let clock = Clock::get()?;
+    require!(deadline > clock.unix_timestamp, VaultError::InvalidEscrowDeadline);
+
+    // once claimed the funds leave the vault, so the same policies as transfer_to_vault apply
+    // up front. a refund only puts them back into available
+    if from_vault.whitelist_enabled != 0 {
+        require!(
+            from_vault.is_withdrawal_allowed(&to_owner)
+                || from_vault.is_withdrawal_allowed(&to_vault_key),
+            VaultError::RecipientNotWhitelisted
+        );
+    }
+
+    if from_vault.has_rate_limit() {
+        from_vault.check_and_update_rate_limit(amount, &clock)?;
+    }
+
+    if from_vault.withdrawal_timelock > 0 {
+        let (pending_amount, recipient) = from_vault.take_pending_withdrawal(&clock)?;
+        require!(pending_amount == amount, VaultError::InvalidAmount);
+        require!(
+            recipient == to_vault_key || recipient == to_owner,
+            VaultError::InvalidAuthority
+        );
+    }
+
+    from_vault.lock_into(LockBucket::Escrow, amount)?;
+    record_journal(
+        &from_vault,
+        ctx.accounts.from_journal.as_ref(),
+        JournalOp::Lock,
+        amount,
+        to_vault_key,
+        &clock,
+    )?;
+
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.from_vault = from_vault_key;
+    escrow.to_vault = to_vault_key;
+    escrow.escrow_id = escrow_id;
+    escrow.amount = amount;
+    escrow.hashlock = hashlock;
+    escrow.deadline = deadline;
+    escrow.created_at = clock.unix_timestamp;
+    escrow.bump = ctx.bumps.escrow;
+
+    emit!(LockEvent {
+        version: EVENT_VERSION,
+        vault: from_vault_key,
+        event_seq: from_vault.next_event_seq()?,
+        user: from_vault.owner,
+        amount,
+        locked_balance: from_vault.locked_balance,
+        available_balance: from_vault.available_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(HashlockEscrowCreated {
+        version: EVENT_VERSION,
+        from_vault: escrow.from_vault,
+        from_event_seq: from_vault.next_event_seq()?,
+        to_vault: escrow.to_vault,
+        escrow_id,
+        amount,
+        hashlock,
+        deadline,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Escrowed {} toward {} under id {}, deadline: {}",
+        amount,
+        to_owner,
+        escrow_id,
+        deadline
+    );
+
+    Ok(())
+}
+
+// permissionless, whoever knows the secret can push the funds to the receiving vault
+pub fn claim_hashlock_escrow(
+    ctx: Context<ClaimHashlockEscrow>,
+    _escrow_id: u64,
+    preimage: Vec<u8>,
+) -> Result<()> {
+    require!(preimage.len() <= MAX_PREIMAGE_LEN, VaultError::InvalidPreimage);
+
+    let escrow = &ctx.accounts.escrow;
+    let clock = Clock::get()?;
+
+    require!(!escrow.is_expired(&clock), VaultError::EscrowExpired);
+    require!(escrow.matches_preimage(&preimage), VaultError::InvalidPreimage);
+
+    let amount = escrow.amount;
+
+    ctx.accounts
+        .protocol_config
+        .check_vault_cap(ctx.accounts.to_vault.load()?.total_balance, amount)?;
+
+    let (from_owner, bump) = {
+        let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+
+        // the escrowed amount leaves straight out of the escrow bucket, available is never touched
+        from_vault.seize_from(LockBucket::Escrow, amount)?;
+        (from_vault.owner, from_vault.bump)
+    };
+
+    let seeds = &[
+        b"vault",
+        from_owner.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.from_token_account.to_account_info(),
+        to: ctx.accounts.to_token_account.to_account_info(),
+        authority: ctx.accounts.from_vault.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    let mut to_vault = ctx.accounts.to_vault.load_mut()?;
+    to_vault.add_deposit(amount)?;
+    record_journal(
+        &from_vault,
+        ctx.accounts.from_journal.as_ref(),
+        JournalOp::Seize,
+        amount,
+        escrow.to_vault,
+        &clock,
+    )?;
+    record_journal(
+        &to_vault,
+        ctx.accounts.to_journal.as_ref(),
+        JournalOp::TransferIn,
+        amount,
+        escrow.from_vault,
+        &clock,
+    )?;
+
+    emit!(TransferEvent {
+        version: EVENT_VERSION,
+        from_vault: escrow.from_vault,
+        from_event_seq: from_vault.next_event_seq()?,
+        to_vault: escrow.to_vault,
+        to_event_seq: to_vault.next_event_seq()?,
+        from: from_owner,
+        to: to_vault.owner,
+        amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(HashlockEscrowClaimed {
+        version: EVENT_VERSION,
+        from_vault: escrow.from_vault,
+        from_event_seq: from_vault.next_event_seq()?,
+        to_vault: escrow.to_vault,
+        to_event_seq: to_vault.next_event_seq()?,
+        escrow_id: escrow.escrow_id,
+        amount,
+        preimage,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Escrow {} claimed, {} sent to {}", escrow.escrow_id, amount, to_vault.owner);
+
+    Ok(())
+}
+
+// permissionless too, after the deadline the only place the funds can go is back to the sender
+pub fn refund_hashlock_escrow(ctx: Context<RefundHashlockEscrow>, _escrow_id: u64) -> Result<()> {
+    let escrow = &ctx.accounts.escrow;
+    let clock = Clock::get()?;
+
+    require!(escrow.is_expired(&clock), VaultError::EscrowNotExpired);
+
+    // nothing but claim and refund touches the escrow bucket, so the full amount is still there
+    let amount = escrow.amount;
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    from_vault.unlock_from(LockBucket::Escrow, amount)?;
+    record_journal(
+        &from_vault,
+        ctx.accounts.from_journal.as_ref(),
+        JournalOp::Unlock,
+        amount,
+        escrow.to_vault,
+        &clock,
+    )?;
+
+    emit!(UnlockEvent {
+        version: EVENT_VERSION,
+        vault: escrow.from_vault,
+        event_seq: from_vault.next_event_seq()?,
+        user: from_vault.owner,
+        amount,
+        locked_balance: from_vault.locked_balance,
+        available_balance: from_vault.available_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(HashlockEscrowRefunded {
+        version: EVENT_VERSION,
+        from_vault: escrow.from_vault,
+        from_event_seq: from_vault.next_event_seq()?,
+        escrow_id: escrow.escrow_id,
+        amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Escrow {} refunded ({})", escrow.escrow_id, amount);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct CreateHashlockEscrow<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = from_vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !from_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub from_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"vault", to_vault.load()?.owner.as_ref()],
+        bump = to_vault.load()?.bump,
+    )]
+    pub to_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = HashlockEscrow::LEN,
+        seeds = [b"escrow", from_vault.key().as_ref(), &escrow_id.to_le_bytes()],
+        bump
+    )]
+    pub escrow: Account<'info, HashlockEscrow>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", from_vault.key().as_ref()],
+        bump = from_journal.load()?.bump,
+    )]
+    pub from_journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct ClaimHashlockEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", from_vault.load()?.owner.as_ref()],
+        bump = from_vault.load()?.bump,
+        constraint = !from_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub from_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", to_vault.load()?.owner.as_ref()],
+        bump = to_vault.load()?.bump,
+        // frozen vaults can't be paid into, the sender can refund after the deadline
+        constraint = !to_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub to_vault: AccountLoader<'info, CollateralVault>,
+
+    // rent goes back to the sender, they paid for the escrow account
+    #[account(
+        mut,
+        close = sender,
+        seeds = [b"escrow", from_vault.key().as_ref(), &escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+        has_one = from_vault @ VaultError::InvalidAuthority,
+        has_one = to_vault @ VaultError::InvalidAuthority,
+    )]
+    pub escrow: Account<'info, HashlockEscrow>,
+
+    /// CHECK: Only receives the escrow rent, must match the sending vault owner
+    #[account(mut, address = from_vault.load()?.owner @ VaultError::InvalidAuthority)]
+    pub sender: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.key() == from_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.key() == to_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", from_vault.key().as_ref()],
+        bump = from_journal.load()?.bump,
+    )]
+    pub from_journal: Option<AccountLoader<'info, VaultJournal>>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", to_vault.key().as_ref()],
+        bump = to_journal.load()?.bump,
+    )]
+    pub to_journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct RefundHashlockEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", from_vault.load()?.owner.as_ref()],
+        bump = from_vault.load()?.bump,
+    )]
+    pub from_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = sender,
+        seeds = [b"escrow", from_vault.key().as_ref(), &escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+        has_one = from_vault @ VaultError::InvalidAuthority,
+    )]
+    pub escrow: Account<'info, HashlockEscrow>,
+
+    /// CHECK: Only receives the escrow rent, must match the sending vault owner
+    #[account(mut, address = from_vault.load()?.owner @ VaultError::InvalidAuthority)]
+    pub sender: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", from_vault.key().as_ref()],
+        bump = from_journal.load()?.bump,
+    )]
+    pub from_journal: Option<AccountLoader<'info, VaultJournal>>,
+}
@@ -2,10 +2,10 @@ use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
-use crate::state::CollateralVault;
+use crate::state::{CollateralVault, VaultInitialized, EVENT_VERSION};
 
 pub fn initialize_vault(ctx: Context<InitializeVault>) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let mut vault = ctx.accounts.vault.load_init()?;
     let clock = Clock::get()?;
 
     vault.owner = ctx.accounts.owner.key();
@@ -17,6 +17,16 @@ pub fn initialize_vault(ctx: Context<InitializeVault>) -> Result<()> {
     vault.total_withdrawn = 0;
     vault.created_at = clock.unix_timestamp;
     vault.bump = ctx.bumps.vault;
+    vault.initialize_advanced_features(&clock);
+
+    emit!(VaultInitialized {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
+        owner: vault.owner,
+        token_account: vault.token_account,
+        timestamp: clock.unix_timestamp,
+    });
 
     msg!("Vault initialized for user: {}", ctx.accounts.owner.key());
 
@@ -35,7 +45,7 @@ pub struct InitializeVault<'info> {
         seeds = [b"vault", owner.key().as_ref()],
         bump
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     // using ATA here so the vault PDA owns the token account
     // this way we can CPI without needing the user to sign every time
@@ -0,0 +1,297 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    CollateralVault, InsuranceFund, InsuranceFundConfigured, InsuranceFundContribution,
+    InsuranceFundInitialized, InsuranceSource, JournalOp, ProtocolConfig, ShortfallCovered, VaultJournal,
+    EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+pub fn initialize_insurance_fund(ctx: Context<InitializeInsuranceFund>, fee_share_bps: u16) -> Result<()> {
+    require!(
+        fee_share_bps as u64 <= ProtocolConfig::BPS_DENOMINATOR,
+        VaultError::InvalidConfiguration
+    );
+
+    let fund = &mut ctx.accounts.insurance_fund;
+    let clock = Clock::get()?;
+
+    fund.token_account = ctx.accounts.fund_token_account.key();
+    fund.fee_share_bps = fee_share_bps;
+    fund.total_contributed = 0;
+    fund.total_paid_out = 0;
+    fund.created_at = clock.unix_timestamp;
+    fund.bump = ctx.bumps.insurance_fund;
+
+    ctx.accounts.protocol_config.has_insurance_fund = true;
+
+    emit!(InsuranceFundInitialized {
+        version: EVENT_VERSION,
+        admin: ctx.accounts.admin.key(),
+        token_account: fund.token_account,
+        fee_share_bps,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Insurance fund initialized, fee share: {} bps", fee_share_bps);
+
+    Ok(())
+}
+
+pub fn configure_insurance_fund(ctx: Context<ConfigureInsuranceFund>, fee_share_bps: u16) -> Result<()> {
+    require!(
+        fee_share_bps as u64 <= ProtocolConfig::BPS_DENOMINATOR,
+        VaultError::InvalidConfiguration
+    );
+
+    ctx.accounts.insurance_fund.fee_share_bps = fee_share_bps;
+
+    let clock = Clock::get()?;
+    emit!(InsuranceFundConfigured {
+        version: EVENT_VERSION,
+        admin: ctx.accounts.admin.key(),
+        fee_share_bps,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Insurance fund fee share set to {} bps", fee_share_bps);
+
+    Ok(())
+}
+
+// anyone can top the fund up directly, e.g. the treasury moving fees over
+pub fn contribute_insurance_fund(ctx: Context<ContributeInsuranceFund>, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.contributor_token_account.to_account_info(),
+        to: ctx.accounts.fund_token_account.to_account_info(),
+        authority: ctx.accounts.contributor.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, amount)?;
+
+    let fund = &mut ctx.accounts.insurance_fund;
+    fund.record_contribution(amount)?;
+
+    ctx.accounts.fund_token_account.reload()?;
+
+    let clock = Clock::get()?;
+    emit!(InsuranceFundContribution {
+        version: EVENT_VERSION,
+        source: InsuranceSource::Direct,
+        contributor: ctx.accounts.contributor.key(),
+        amount,
+        fund_balance: ctx.accounts.fund_token_account.amount,
+        total_contributed: fund.total_contributed,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Insurance fund received {}", amount);
+
+    Ok(())
+}
+
+// pays bad debt left behind by a liquidation back into the affected vault
+// reason_code is free-form for off-chain bookkeeping, same as on lock records
+pub fn cover_shortfall(ctx: Context<CoverShortfall>, amount: u64, reason_code: u8) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    require!(
+        ctx.accounts.fund_token_account.amount >= amount,
+        VaultError::InsufficientInsuranceFunds
+    );
+    ctx.accounts
+        .protocol_config
+        .check_vault_cap(ctx.accounts.vault.load()?.total_balance, amount)?;
+
+    let fund = &mut ctx.accounts.insurance_fund;
+    let seeds = &[
+        b"insurance_fund".as_ref(),
+        &[fund.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.fund_token_account.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: fund.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    fund.record_payout(amount)?;
+
+    // not the owner's deposit, so it's credited like a donation
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.add_donation(amount)?;
+    ctx.accounts.protocol_config.record_inflow(amount)?;
+
+    ctx.accounts.fund_token_account.reload()?;
+
+    let clock = Clock::get()?;
+    record_journal(&vault, ctx.accounts.journal.as_ref(), JournalOp::Donation, amount, fund.key(), &clock)?;
+
+    emit!(ShortfallCovered {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        owner: vault.owner,
+        amount,
+        reason_code,
+        fund_balance: ctx.accounts.fund_token_account.amount,
+        total_paid_out: fund.total_paid_out,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Insurance fund covered {} for {}. Fund balance: {}",
+        amount,
+        vault.owner,
+        ctx.accounts.fund_token_account.amount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceFund<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == protocol_config.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = InsuranceFund::LEN,
+        seeds = [b"insurance_fund"],
+        bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = insurance_fund,
+    )]
+    pub fund_token_account: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureInsuranceFund<'info> {
+    #[account(
+        constraint = admin.key() == protocol_config.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump,
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+}
+
+#[derive(Accounts)]
+pub struct ContributeInsuranceFund<'info> {
+    pub contributor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump,
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(mut)]
+    pub contributor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = fund_token_account.key() == insurance_fund.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub fund_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CoverShortfall<'info> {
+    #[account(
+        constraint = admin.key() == protocol_config.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump,
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        mut,
+        constraint = fund_token_account.key() == insurance_fund.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub fund_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        // frozen vaults can't be paid into, same as deposit_for
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
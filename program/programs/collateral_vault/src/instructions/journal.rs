@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{
+    CollateralVault, JournalClosed, JournalInitialized, JournalOp, VaultJournal, EVENT_VERSION,
+    JOURNAL_CAPACITY,
+};
+use crate::errors::VaultError;
+
+pub fn initialize_journal(ctx: Context<InitializeJournal>) -> Result<()> {
+    let mut journal = ctx.accounts.journal.load_init()?;
+    journal.vault = ctx.accounts.vault.key();
+    journal.next_seq = 0;
+    journal.bump = ctx.bumps.journal;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.has_journal = 1;
+
+    let clock = Clock::get()?;
+    emit!(JournalInitialized {
+        version: EVENT_VERSION,
+        vault: journal.vault,
+        event_seq: vault.next_event_seq()?,
+        journal: ctx.accounts.journal.key(),
+        capacity: JOURNAL_CAPACITY as u32,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Journal initialized for vault {}", journal.vault);
+
+    Ok(())
+}
+
+pub fn close_journal(ctx: Context<CloseJournal>) -> Result<()> {
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.has_journal = 0;
+
+    let clock = Clock::get()?;
+    emit!(JournalClosed {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
+        journal: ctx.accounts.journal.key(),
+        entries_recorded: ctx.accounts.journal.load()?.next_seq,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Journal closed");
+    Ok(())
+}
+
+// every instruction that moves a vault's funds calls this after the balance change went
+// through. the journal account is optional so vaults without one just pass None, but once the
+// vault has a journal leaving it out fails the instruction instead of skipping the entry
+pub fn record_journal(
+    vault: &CollateralVault,
+    journal: Option<&AccountLoader<VaultJournal>>,
+    op: JournalOp,
+    amount: u64,
+    counterparty: Pubkey,
+    clock: &Clock,
+) -> Result<()> {
+    match journal {
+        Some(journal) => journal.load_mut()?.record(op, amount, counterparty, clock.slot),
+        None => {
+            require!(!vault.has_journal(), VaultError::JournalRequired);
+            Ok(())
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeJournal<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = VaultJournal::LEN,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump
+    )]
+    pub journal: AccountLoader<'info, VaultJournal>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseJournal<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: AccountLoader<'info, VaultJournal>,
+}
@@ -0,0 +1,314 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    AuthorizedProgram, CollateralVault, InsuranceFund, InsuranceFundContribution, InsuranceSource, JournalOp,
+    LiquidationEvent, LockBucket, LockRecord, ProgramExposure, ProgramRegistry, ProtocolConfig, VaultJournal,
+    EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+// amount is the collateral backing the debt being repaid, the bonus is added on top
+// and the whole thing gets clamped to the close factor so a single call can only take
+// part of the position. only position collateral is reachable: the named lock record or the
+// calling program's own plain locks, never escrowed, vesting or sub-account funds and never
+// what another program locked
+pub fn liquidate(ctx: Context<Liquidate>, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_TRANSFER)?;
+
+    require!(
+        ctx.accounts.victim_vault.key() != ctx.accounts.liquidator_vault.key(),
+        VaultError::OperationNotAllowed
+    );
+
+    let config = &ctx.accounts.protocol_config;
+    // the close factor applies to the position being liquidated, without a record that's what
+    // this program has locked in the vault
+    let exposure_locked = ctx.accounts.program_exposure.locked;
+    let (locked, victim_owner, victim_bump) = {
+        let victim_vault = ctx.accounts.victim_vault.load()?;
+        let locked = match ctx.accounts.lock_record.as_ref() {
+            Some(lock_record) => lock_record.outstanding,
+            None => exposure_locked.min(victim_vault.free_locked()),
+        };
+        (locked, victim_vault.owner, victim_vault.bump)
+    };
+
+    let bonus = ProtocolConfig::apply_bps(amount, config.liquidation_bonus_bps)?;
+    let requested = amount
+        .checked_add(bonus)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+    let max_seizable = ProtocolConfig::apply_bps(locked, config.close_factor_bps)?;
+    let seized = requested.min(max_seizable);
+
+    require!(seized > 0, VaultError::InsufficientBalance);
+    require!(seized <= exposure_locked, VaultError::ProgramExposureExceeded);
+
+    let protocol_fee = ProtocolConfig::apply_bps(seized, config.protocol_fee_bps)?;
+    let liquidator_amount = seized
+        .checked_sub(protocol_fee)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+
+    // once the insurance fund exists it takes its share of the fee first, the treasury gets
+    // whatever is left. the liquidator can't skip it by leaving the fund out
+    let insurance_share = if config.has_insurance_fund {
+        let fund = ctx.accounts
+            .insurance_fund
+            .as_ref()
+            .ok_or(error!(VaultError::InsuranceFundRequired))?;
+        ProtocolConfig::apply_bps(protocol_fee, fund.fee_share_bps)?
+    } else {
+        0
+    };
+    let treasury_fee = protocol_fee
+        .checked_sub(insurance_share)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+
+    let seeds = &[
+        b"vault",
+        victim_owner.as_ref(),
+        &[victim_bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.victim_token_account.to_account_info(),
+        to: ctx.accounts.liquidator_token_account.to_account_info(),
+        authority: ctx.accounts.victim_vault.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, liquidator_amount)?;
+
+    if treasury_fee > 0 {
+        let treasury = ctx.accounts
+            .treasury_token_account
+            .as_ref()
+            .ok_or(error!(VaultError::InvalidTokenAccount))?;
+
+        require!(
+            treasury.key() == ctx.accounts.protocol_config.treasury,
+            VaultError::InvalidTokenAccount
+        );
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.victim_token_account.to_account_info(),
+            to: treasury.to_account_info(),
+            authority: ctx.accounts.victim_vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, treasury_fee)?;
+    }
+
+    if insurance_share > 0 {
+        let fund_token_account = ctx.accounts
+            .insurance_token_account
+            .as_mut()
+            .ok_or(error!(VaultError::InvalidTokenAccount))?;
+        let fund = ctx.accounts
+            .insurance_fund
+            .as_mut()
+            .ok_or(error!(VaultError::InvalidTokenAccount))?;
+
+        require!(
+            fund_token_account.key() == fund.token_account,
+            VaultError::InvalidTokenAccount
+        );
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.victim_token_account.to_account_info(),
+            to: fund_token_account.to_account_info(),
+            authority: ctx.accounts.victim_vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, insurance_share)?;
+
+        fund.record_contribution(insurance_share)?;
+        fund_token_account.reload()?;
+
+        let clock = Clock::get()?;
+        emit!(InsuranceFundContribution {
+            version: EVENT_VERSION,
+            source: InsuranceSource::LiquidationFee,
+            contributor: ctx.accounts.victim_vault.key(),
+            amount: insurance_share,
+            fund_balance: fund_token_account.amount,
+            total_contributed: fund.total_contributed,
+            timestamp: clock.unix_timestamp,
+        });
+    }
+
+    let mut victim_vault = ctx.accounts.victim_vault.load_mut()?;
+    let mut liquidator_vault = ctx.accounts.liquidator_vault.load_mut()?;
+
+    ctx.accounts
+        .program_registry
+        .record_release(&mut ctx.accounts.program_exposure, seized)?;
+    let remaining_locked = match ctx.accounts.lock_record.as_mut() {
+        Some(lock_record) => {
+            lock_record.take(seized)?;
+            victim_vault.seize_from(LockBucket::LockRecords, seized)?;
+            lock_record.outstanding
+        }
+        None => {
+            victim_vault.seize_locked(seized)?;
+            ctx.accounts.program_exposure.locked.min(victim_vault.free_locked())
+        }
+    };
+    liquidator_vault.add_deposit(liquidator_amount)?;
+
+    // the fee leaves the vault system entirely, the rest just moves between vaults
+    ctx.accounts.protocol_config.record_outflow(protocol_fee);
+
+    let clock = Clock::get()?;
+    let victim_vault_key = ctx.accounts.victim_vault.key();
+    let liquidator_vault_key = ctx.accounts.liquidator_vault.key();
+    record_journal(
+        &victim_vault,
+        ctx.accounts.victim_journal.as_ref(),
+        JournalOp::Seize,
+        seized,
+        liquidator_vault_key,
+        &clock,
+    )?;
+    record_journal(
+        &liquidator_vault,
+        ctx.accounts.liquidator_journal.as_ref(),
+        JournalOp::TransferIn,
+        liquidator_amount,
+        victim_vault_key,
+        &clock,
+    )?;
+
+    emit!(LiquidationEvent {
+        version: EVENT_VERSION,
+        victim_vault: victim_vault_key,
+        victim_event_seq: victim_vault.next_event_seq()?,
+        liquidator_vault: liquidator_vault_key,
+        liquidator_event_seq: liquidator_vault.next_event_seq()?,
+        victim: victim_vault.owner,
+        liquidator: liquidator_vault.owner,
+        seized_amount: seized,
+        bonus: ProtocolConfig::bonus_portion(seized, ctx.accounts.protocol_config.liquidation_bonus_bps)?,
+        protocol_fee,
+        insurance_share,
+        remaining_locked,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Liquidated {} from {} (fee: {}), remaining locked: {}",
+        seized,
+        victim_vault.owner,
+        protocol_fee,
+        remaining_locked
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Liquidate<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", victim_vault.load()?.owner.as_ref()],
+        bump = victim_vault.load()?.bump,
+        constraint = !victim_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub victim_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", liquidator_vault.load()?.owner.as_ref()],
+        bump = liquidator_vault.load()?.bump,
+        // frozen vaults can't be paid into, same as deposit_for
+        constraint = !liquidator_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub liquidator_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        constraint = victim_token_account.key() == victim_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub victim_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = liquidator_token_account.key() == liquidator_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub liquidator_token_account: Account<'info, TokenAccount>,
+
+    // only needed when the protocol fee is non-zero
+    #[account(mut)]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    // required once protocol_config.has_insurance_fund is set
+    #[account(
+        mut,
+        seeds = [b"insurance_fund"],
+        bump = insurance_fund.bump,
+    )]
+    pub insurance_fund: Option<Account<'info, InsuranceFund>>,
+
+    // only needed when the insurance fund takes a non-zero share of the fee
+    #[account(mut)]
+    pub insurance_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), victim_vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    // liquidates the position behind this record instead of the plain locks
+    #[account(
+        mut,
+        constraint = lock_record.vault == victim_vault.key() @ VaultError::InvalidAuthority,
+        constraint = lock_record.caller_program == caller_program.key() @ VaultError::UnauthorizedProgram,
+    )]
+    pub lock_record: Option<Account<'info, LockRecord>>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", victim_vault.key().as_ref()],
+        bump = victim_journal.load()?.bump,
+    )]
+    pub victim_journal: Option<AccountLoader<'info, VaultJournal>>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", liquidator_vault.key().as_ref()],
+        bump = liquidator_journal.load()?.bump,
+    )]
+    pub liquidator_journal: Option<AccountLoader<'info, VaultJournal>>,
+}
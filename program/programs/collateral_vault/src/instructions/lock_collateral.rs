@@ -1,26 +1,40 @@
 use anchor_lang::prelude::*;
 
-use crate::state::{CollateralVault, VaultAuthority, LockEvent};
+use crate::state::{
+    CollateralVault, JournalOp, LockEvent, ProgramExposure, ProgramRegistry, VaultJournal,
+    EVENT_VERSION,
+};
 use crate::errors::VaultError;
+use crate::instructions::record_journal;
 
 pub fn lock_collateral(ctx: Context<LockCollateral>, amount: u64) -> Result<()> {
     require!(amount > 0, VaultError::InvalidAmount);
 
     // this is meant to be called via CPI from other programs (like a position manager)
-    // checking caller_program against the authorized list prevents random programs from locking user funds
-    let caller_program = ctx.accounts.caller_program.key();
-    let authority = &ctx.accounts.vault_authority;
-    
-    require!(
-        authority.is_authorized(&caller_program),
-        VaultError::UnauthorizedProgram
-    );
+    // the registry PDA only exists for programs the admin registered, which prevents random programs
+    // from locking user funds, and its limits cap how much it can lock in this vault and across all vaults
+    ctx.accounts
+        .program_registry
+        .record_lock(&mut ctx.accounts.program_exposure, amount)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
 
-    let vault = &mut ctx.accounts.vault;
     vault.lock(amount)?;
 
     let clock = Clock::get()?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Lock,
+        amount,
+        ctx.accounts.caller_program.key(),
+        &clock,
+    )?;
+
     emit!(LockEvent {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
         user: vault.owner,
         amount,
         locked_balance: vault.locked_balance,
@@ -42,19 +56,40 @@ pub fn lock_collateral(ctx: Context<LockCollateral>, amount: u64) -> Result<()>
 pub struct LockCollateral<'info> {
     #[account(
         mut,
-        seeds = [b"vault", vault.owner.as_ref()],
-        bump = vault.bump,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    /// CHECK: Only used to derive program_registry. The registry only exists for programs
+    /// the admin registered, so only those can lock user collateral.
+    pub caller_program: UncheckedAccount<'info>,
 
     #[account(
-        seeds = [b"vault_authority"],
-        bump = vault_authority.bump,
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
     )]
-    pub vault_authority: Account<'info, VaultAuthority>,
-    
-    /// CHECK: This account is verified against the vault_authority.authorized_programs list
-    /// in the instruction logic. Only programs in the authorized list can lock user collateral.
-    pub caller_program: UncheckedAccount<'info>,
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+
+use crate::state::{CollateralVault, LegacyCollateralVault, VaultMigrated, EVENT_VERSION};
+use crate::errors::VaultError;
+
+// rewrites an old borsh vault into the zero-copy layout in place, so the PDA, its token
+// account and everything keyed by the vault address stay as they are. permissionless since
+// nothing about the vault changes, the payer only covers the rent for the extra bytes
+pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+    let vault_info = ctx.accounts.vault.to_account_info();
+    let old_size = vault_info.data_len();
+
+    let legacy = {
+        let data = vault_info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == LegacyCollateralVault::DISCRIMINATOR,
+            VaultError::VaultAlreadyMigrated
+        );
+
+        LegacyCollateralVault::decode(&data[8..])?
+    };
+
+    // an UncheckedAccount gets no seed constraint, so make sure this really is the owner's vault PDA
+    let expected = Pubkey::create_program_address(
+        &[b"vault", legacy.owner.as_ref(), &[legacy.bump]],
+        &crate::ID,
+    )
+    .map_err(|_| error!(VaultError::InvalidAuthority))?;
+    require!(expected == vault_info.key(), VaultError::InvalidAuthority);
+
+    let mut migrated = <CollateralVault as bytemuck::Zeroable>::zeroed();
+    legacy.write_into(&mut migrated)?;
+    let event_seq = migrated.next_event_seq()?;
+
+    let rent_needed = Rent::get()?.minimum_balance(CollateralVault::LEN);
+    let shortfall = rent_needed.saturating_sub(vault_info.lamports());
+    if shortfall > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.payer.to_account_info(),
+            to: vault_info.clone(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, shortfall)?;
+    }
+
+    vault_info.resize(CollateralVault::LEN)?;
+
+    {
+        let mut data = vault_info.try_borrow_mut_data()?;
+        data.fill(0);
+        data[..8].copy_from_slice(CollateralVault::DISCRIMINATOR);
+        data[8..CollateralVault::LEN].copy_from_slice(bytemuck::bytes_of(&migrated));
+    }
+
+    let clock = Clock::get()?;
+    emit!(VaultMigrated {
+        version: EVENT_VERSION,
+        vault: vault_info.key(),
+        event_seq,
+        owner: legacy.owner,
+        old_size: old_size as u32,
+        new_size: CollateralVault::LEN as u32,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Migrated vault of {} to the fixed layout", legacy.owner);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: still on the old layout so it can't be loaded as CollateralVault. the
+    /// discriminator and the vault PDA derivation are checked in the instruction
+    #[account(mut, owner = crate::ID @ VaultError::InvalidAuthority)]
+    pub vault: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -10,6 +10,26 @@ pub mod yield_compound;
 pub mod advanced_config;
 pub mod request_withdrawal;
 pub mod execute_withdrawal;
+pub mod protocol_config;
+pub mod liquidate;
+pub mod timed_lock;
+pub mod vesting;
+pub mod settle_batch;
+pub mod vault_transfer;
+pub mod deposit_for;
+pub mod sync_balance;
+pub mod allowance;
+pub mod hashlock_escrow;
+pub mod sub_account;
+pub mod share_pool;
+pub mod insurance_fund;
+pub mod freeze;
+pub mod views;
+pub mod migrate_vault;
+pub mod scheduled_withdrawal;
+pub mod journal;
+pub mod permit;
+pub mod session_key;
 
 pub use initialize_vault::*;
 pub use deposit::*;
@@ -23,4 +43,23 @@ pub use yield_compound::*;
 pub use advanced_config::*;
 pub use request_withdrawal::*;
 pub use execute_withdrawal::*;
-
+pub use protocol_config::*;
+pub use liquidate::*;
+pub use timed_lock::*;
+pub use vesting::*;
+pub use settle_batch::*;
+pub use vault_transfer::*;
+pub use deposit_for::*;
+pub use sync_balance::*;
+pub use allowance::*;
+pub use hashlock_escrow::*;
+pub use sub_account::*;
+pub use share_pool::*;
+pub use insurance_fund::*;
+pub use freeze::*;
+pub use views::*;
+pub use migrate_vault::*;
+pub use scheduled_withdrawal::*;
+pub use journal::*;
+pub use permit::*;
+pub use session_key::*;
@@ -0,0 +1,225 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use solana_instructions_sysvar::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::state::{
+    CollateralVault, JournalOp, ProtocolConfig, VaultJournal, WithdrawEvent, WithdrawalPermit,
+    WithdrawalPermitUsed, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::{record_journal, settle_vesting_schedules};
+
+// offsets into the ed25519 program's instruction data: a count byte, a padding byte, then one
+// 14 byte offsets struct per signature
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+
+// gasless withdrawal. the owner signs the permit off chain and whoever submits it pays the fee,
+// the signature itself is checked by the ed25519 program in the instruction right before this one
+pub fn withdraw_with_permit<'info>(
+    ctx: Context<'_, '_, 'info, 'info, WithdrawWithPermit<'info>>,
+    permit: WithdrawalPermit,
+) -> Result<()> {
+    require!(permit.amount > 0, VaultError::InvalidAmount);
+
+    let clock = Clock::get()?;
+    let vault_key = ctx.accounts.vault.key();
+
+    require!(permit.vault == vault_key, VaultError::InvalidPermitSignature);
+    require!(clock.unix_timestamp <= permit.expires_at, VaultError::PermitExpired);
+
+    verify_owner_signature(
+        &ctx.accounts.instructions,
+        &ctx.accounts.owner.key(),
+        &permit.message(),
+    )?;
+
+    let (owner_key, bump) = {
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        settle_vesting_schedules(
+            &mut vault,
+            &vault_key,
+            ctx.accounts.journal.as_ref(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+
+        require!(permit.nonce == vault.permit_nonce, VaultError::InvalidPermitNonce);
+        vault.permit_nonce = vault.permit_nonce
+            .checked_add(1)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+
+        // the relayer picks nothing but the fee, so the same policies as an owner-built
+        // withdrawal to a third party apply to the signed recipient
+        require!(
+            vault.is_withdrawal_allowed(&permit.recipient),
+            VaultError::RecipientNotWhitelisted
+        );
+
+        if vault.has_rate_limit() {
+            vault.check_and_update_rate_limit(permit.amount, &clock)?;
+        }
+
+        require!(
+            vault.available_balance >= permit.amount,
+            VaultError::InsufficientAvailableBalance
+        );
+        (vault.owner, vault.bump)
+    };
+
+    let seeds = &[
+        b"vault",
+        owner_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.vault_token_account.to_account_info(),
+        to: ctx.accounts.recipient_token_account.to_account_info(),
+        authority: ctx.accounts.vault.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, permit.amount)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.sub_withdrawal(permit.amount)?;
+    ctx.accounts.protocol_config.record_outflow(permit.amount);
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::PermitWithdrawal,
+        permit.amount,
+        permit.recipient,
+        &clock,
+    )?;
+
+    emit!(WithdrawEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        user: owner_key,
+        amount: permit.amount,
+        new_balance: vault.total_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(WithdrawalPermitUsed {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        relayer: ctx.accounts.relayer.key(),
+        recipient: permit.recipient,
+        amount: permit.amount,
+        nonce: permit.nonce,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Relayed permit {} withdrawal of {} to {}",
+        permit.nonce,
+        permit.amount,
+        permit.recipient
+    );
+
+    Ok(())
+}
+
+// the ed25519 program has already failed the transaction if the signature is bad, so all
+// that's left is making sure it verified the owner's key over exactly this permit. every
+// offset has to point into that same instruction, otherwise the key or message it checked
+// could live somewhere the caller controls
+fn verify_owner_signature(instructions: &AccountInfo, owner: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, VaultError::InvalidPermitSignature);
+
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(
+        ix.program_id == solana_sdk_ids::ed25519_program::ID,
+        VaultError::InvalidPermitSignature
+    );
+
+    let data = &ix.data;
+    require!(
+        data.len() >= ED25519_OFFSETS_START + ED25519_OFFSETS_LEN && data[0] == 1,
+        VaultError::InvalidPermitSignature
+    );
+
+    let read_u16 = |field: usize| {
+        let at = ED25519_OFFSETS_START + field * 2;
+        u16::from_le_bytes([data[at], data[at + 1]]) as usize
+    };
+    let signature_ix = read_u16(1);
+    let pubkey_offset = read_u16(2);
+    let pubkey_ix = read_u16(3);
+    let message_offset = read_u16(4);
+    let message_len = read_u16(5);
+    let message_ix = read_u16(6);
+
+    let this_ix = u16::MAX as usize;
+    require!(
+        signature_ix == this_ix && pubkey_ix == this_ix && message_ix == this_ix,
+        VaultError::InvalidPermitSignature
+    );
+
+    let signed_pubkey = data.get(pubkey_offset..pubkey_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_len);
+    require!(
+        signed_pubkey == Some(owner.as_ref()) && signed_message == Some(message),
+        VaultError::InvalidPermitSignature
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(permit: WithdrawalPermit)]
+pub struct WithdrawWithPermit<'info> {
+    pub relayer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    // the relayer only passes the account in, the signed permit decides whose it has to be
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == permit.recipient @ VaultError::InvalidTokenAccount
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Verified via has_one constraint, signs the permit off chain
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Instructions sysvar, checked by address
+    #[account(address = solana_sdk_ids::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
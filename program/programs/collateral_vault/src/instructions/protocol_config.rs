@@ -0,0 +1,159 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{
+    DepositCapsUpdated, LiquidationConfigUpdated, ProtocolConfig, VaultAuthority, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+
+pub fn initialize_protocol_config(
+    ctx: Context<InitializeProtocolConfig>,
+    max_vault_balance: u64,
+    global_deposit_cap: u64,
+) -> Result<()> {
+    require!(
+        max_vault_balance > 0 && global_deposit_cap > 0,
+        VaultError::InvalidConfiguration
+    );
+
+    let config = &mut ctx.accounts.protocol_config;
+    config.admin = ctx.accounts.admin.key();
+    config.max_vault_balance = max_vault_balance;
+    config.global_deposit_cap = global_deposit_cap;
+    config.total_value_locked = 0;
+    config.bump = ctx.bumps.protocol_config;
+    config.treasury = Pubkey::default();
+    config.liquidation_bonus_bps = ProtocolConfig::DEFAULT_LIQUIDATION_BONUS_BPS;
+    config.protocol_fee_bps = 0;
+    config.close_factor_bps = ProtocolConfig::DEFAULT_CLOSE_FACTOR_BPS;
+    config.has_insurance_fund = false;
+
+    msg!(
+        "Protocol config initialized. Vault cap: {}, Global cap: {}",
+        max_vault_balance,
+        global_deposit_cap
+    );
+
+    Ok(())
+}
+
+// lowering a cap below current balances is allowed on purpose
+// it just blocks new deposits until withdrawals bring things back under
+pub fn update_deposit_caps(
+    ctx: Context<UpdateProtocolConfig>,
+    max_vault_balance: u64,
+    global_deposit_cap: u64,
+) -> Result<()> {
+    require!(
+        max_vault_balance > 0 && global_deposit_cap > 0,
+        VaultError::InvalidConfiguration
+    );
+
+    let config = &mut ctx.accounts.protocol_config;
+    let clock = Clock::get()?;
+
+    config.max_vault_balance = max_vault_balance;
+    config.global_deposit_cap = global_deposit_cap;
+
+    emit!(DepositCapsUpdated {
+        version: EVENT_VERSION,
+        admin: ctx.accounts.admin.key(),
+        max_vault_balance,
+        global_deposit_cap,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Deposit caps updated. Vault cap: {}, Global cap: {}",
+        max_vault_balance,
+        global_deposit_cap
+    );
+
+    Ok(())
+}
+
+pub fn configure_liquidation(
+    ctx: Context<UpdateProtocolConfig>,
+    treasury: Pubkey,
+    liquidation_bonus_bps: u16,
+    protocol_fee_bps: u16,
+    close_factor_bps: u16,
+) -> Result<()> {
+    let max_bps = ProtocolConfig::BPS_DENOMINATOR as u16;
+    require!(
+        liquidation_bonus_bps <= max_bps
+            && protocol_fee_bps <= max_bps
+            && close_factor_bps > 0
+            && close_factor_bps <= max_bps,
+        VaultError::InvalidConfiguration
+    );
+
+    let config = &mut ctx.accounts.protocol_config;
+    let clock = Clock::get()?;
+
+    config.treasury = treasury;
+    config.liquidation_bonus_bps = liquidation_bonus_bps;
+    config.protocol_fee_bps = protocol_fee_bps;
+    config.close_factor_bps = close_factor_bps;
+
+    emit!(LiquidationConfigUpdated {
+        version: EVENT_VERSION,
+        admin: ctx.accounts.admin.key(),
+        treasury,
+        liquidation_bonus_bps,
+        protocol_fee_bps,
+        close_factor_bps,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Liquidation config updated. Bonus: {} bps, Fee: {} bps, Close factor: {} bps",
+        liquidation_bonus_bps,
+        protocol_fee_bps,
+        close_factor_bps
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeProtocolConfig<'info> {
+    // the singleton can only be created once, so only the registry admin gets to create it
+    #[account(
+        mut,
+        constraint = admin.key() == vault_authority.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"vault_authority"],
+        bump = vault_authority.bump,
+    )]
+    pub vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ProtocolConfig::LEN,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == protocol_config.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
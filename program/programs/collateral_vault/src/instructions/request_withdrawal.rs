@@ -1,24 +1,33 @@
 use anchor_lang::prelude::*;
 
-use crate::state::{CollateralVault, WithdrawalRequested};
+use crate::state::{CollateralVault, VaultJournal, WithdrawalCancelled, WithdrawalRequested, EVENT_VERSION};
 use crate::errors::VaultError;
+use crate::instructions::settle_vesting_schedules;
 
-pub fn request_withdrawal(
-    ctx: Context<RequestWithdrawal>,
+pub fn request_withdrawal<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RequestWithdrawal<'info>>,
     amount: u64,
     recipient: Pubkey,
 ) -> Result<()> {
     require!(amount > 0, VaultError::InvalidAmount);
 
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
+    settle_vesting_schedules(
+        &mut vault,
+        &vault_key,
+        ctx.accounts.journal.as_ref(),
+        ctx.remaining_accounts,
+        &clock,
+    )?;
 
     require!(
         vault.withdrawal_timelock > 0,
         VaultError::FeatureNotEnabled
     );
 
-    if vault.whitelist_enabled {
+    if vault.whitelist_enabled != 0 {
         require!(
             vault.is_withdrawal_allowed(&recipient),
             VaultError::RecipientNotWhitelisted
@@ -28,15 +37,18 @@ pub fn request_withdrawal(
     vault.request_withdrawal(amount, recipient, &clock)?;
 
     emit!(WithdrawalRequested {
-        vault: vault.key(),
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
         amount,
-        executable_at: vault.pending_withdrawal.as_ref().unwrap().executable_at,
+        recipient,
+        executable_at: vault.pending_withdrawal.executable_at,
         timestamp: clock.unix_timestamp,
     });
 
     msg!("Withdrawal requested: {} tokens, executable at: {}", 
         amount, 
-        vault.pending_withdrawal.as_ref().unwrap().executable_at
+        vault.pending_withdrawal.executable_at
     );
 
     Ok(())
@@ -45,11 +57,11 @@ pub fn request_withdrawal(
 // preventing cancellation after timelock expires to avoid race conditions
 // where user cancels right as someone else tries to execute
 pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
-    let pending = vault.pending_withdrawal
-        .as_ref()
+    let pending = *vault.pending()
         .ok_or(error!(VaultError::NoPendingWithdrawal))?;
 
     require!(
@@ -57,7 +69,17 @@ pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
         VaultError::CannotCancelExpiredWithdrawal
     );
 
-    vault.pending_withdrawal = None;
+    vault.clear_pending();
+
+    emit!(WithdrawalCancelled {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        amount: pending.amount,
+        recipient: pending.recipient,
+        timestamp: clock.unix_timestamp,
+    });
+
     msg!("Pending withdrawal cancelled");
 
     Ok(())
@@ -71,13 +93,21 @@ pub struct RequestWithdrawal<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
 #[derive(Accounts)]
@@ -88,10 +118,11 @@ pub struct CancelWithdrawal<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
@@ -0,0 +1,328 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    CollateralVault, JournalOp, ProtocolConfig, ScheduledWithdrawal, ScheduledWithdrawalCancelled,
+    ScheduledWithdrawalCreated, ScheduledWithdrawalExecuted, VaultJournal, WithdrawEvent,
+    EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::{record_journal, settle_vesting_schedules};
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_scheduled_withdrawal(
+    ctx: Context<CreateScheduledWithdrawal>,
+    schedule_id: u64,
+    recipient: Pubkey,
+    amount: u64,
+    interval: i64,
+    first_execution_at: i64,
+    max_executions: u32,
+    keeper_tip: u64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    require!(interval > 0, VaultError::InvalidWithdrawalSchedule);
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let clock = Clock::get()?;
+
+    // nobody signs the individual runs, so the recipient has to be on the list explicitly,
+    // even when the whitelist isn't enforced for the owner's own withdrawals
+    require!(
+        vault.whitelist().contains(&recipient),
+        VaultError::RecipientNotWhitelisted
+    );
+
+    // creating the schedule stands in for request_withdrawal, so the first run can't come
+    // sooner than a timelocked withdrawal would
+    let earliest = clock.unix_timestamp
+        .checked_add(vault.withdrawal_timelock)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+    require!(first_execution_at >= earliest, VaultError::InvalidWithdrawalSchedule);
+
+    let schedule = &mut ctx.accounts.scheduled_withdrawal;
+    schedule.vault = ctx.accounts.vault.key();
+    schedule.schedule_id = schedule_id;
+    schedule.recipient = recipient;
+    schedule.amount = amount;
+    schedule.interval = interval;
+    schedule.next_execution_at = first_execution_at;
+    schedule.max_executions = max_executions;
+    schedule.executions = 0;
+    schedule.keeper_tip = keeper_tip;
+    schedule.total_paid = 0;
+    schedule.created_at = clock.unix_timestamp;
+    schedule.bump = ctx.bumps.scheduled_withdrawal;
+
+    emit!(ScheduledWithdrawalCreated {
+        version: EVENT_VERSION,
+        vault: schedule.vault,
+        event_seq: vault.next_event_seq()?,
+        schedule_id,
+        recipient,
+        amount,
+        interval,
+        first_execution_at,
+        max_executions,
+        keeper_tip,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Scheduled {} to {} every {} seconds, first run at {}",
+        amount,
+        recipient,
+        interval,
+        first_execution_at
+    );
+
+    Ok(())
+}
+
+pub fn cancel_scheduled_withdrawal(ctx: Context<CancelScheduledWithdrawal>, schedule_id: u64) -> Result<()> {
+    let schedule = &ctx.accounts.scheduled_withdrawal;
+    let clock = Clock::get()?;
+
+    emit!(ScheduledWithdrawalCancelled {
+        version: EVENT_VERSION,
+        vault: schedule.vault,
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        schedule_id,
+        executions: schedule.executions,
+        total_paid: schedule.total_paid,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Cancelled scheduled withdrawal {} after {} runs", schedule_id, schedule.executions);
+
+    Ok(())
+}
+
+// permissionless crank. the keeper only chooses where its tip goes, the payout itself can
+// only land in a token account owned by the scheduled recipient
+pub fn execute_scheduled_withdrawal<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExecuteScheduledWithdrawal<'info>>,
+    _schedule_id: u64,
+) -> Result<()> {
+    let schedule = &mut ctx.accounts.scheduled_withdrawal;
+    let clock = Clock::get()?;
+
+    require!(!schedule.is_finished(), VaultError::WithdrawalScheduleFinished);
+    require!(
+        clock.unix_timestamp >= schedule.next_execution_at,
+        VaultError::WithdrawalNotDue
+    );
+
+    let amount = schedule.amount;
+    let tip = schedule.keeper_tip;
+    let total = amount
+        .checked_add(tip)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+
+    let (owner_key, bump) = {
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        let vault_key = ctx.accounts.vault.key();
+        settle_vesting_schedules(
+            &mut vault,
+            &vault_key,
+            ctx.accounts.journal.as_ref(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+
+        // taking the recipient off the whitelist is how the owner pauses payouts without cancelling
+        require!(
+            vault.whitelist().contains(&schedule.recipient),
+            VaultError::RecipientNotWhitelisted
+        );
+
+        if vault.has_rate_limit() {
+            vault.check_and_update_rate_limit(total, &clock)?;
+        }
+
+        require!(
+            vault.available_balance >= total,
+            VaultError::InsufficientAvailableBalance
+        );
+        (vault.owner, vault.bump)
+    };
+
+    let seeds = &[
+        b"vault",
+        owner_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.vault_token_account.to_account_info(),
+        to: ctx.accounts.recipient_token_account.to_account_info(),
+        authority: ctx.accounts.vault.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    if tip > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            to: ctx.accounts.keeper_token_account.to_account_info(),
+            authority: ctx.accounts.vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, tip)?;
+    }
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.sub_withdrawal(total)?;
+    ctx.accounts.protocol_config.record_outflow(total);
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::ScheduledWithdrawal,
+        amount,
+        schedule.recipient,
+        &clock,
+    )?;
+
+    schedule.record_execution()?;
+
+    emit!(WithdrawEvent {
+        version: EVENT_VERSION,
+        vault: schedule.vault,
+        event_seq: vault.next_event_seq()?,
+        user: owner_key,
+        amount: total,
+        new_balance: vault.total_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(ScheduledWithdrawalExecuted {
+        version: EVENT_VERSION,
+        vault: schedule.vault,
+        event_seq: vault.next_event_seq()?,
+        schedule_id: schedule.schedule_id,
+        recipient: schedule.recipient,
+        keeper: ctx.accounts.keeper.key(),
+        amount,
+        keeper_tip: tip,
+        executions: schedule.executions,
+        next_execution_at: schedule.next_execution_at,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Scheduled withdrawal {} run {}: {} to {}, tip {}",
+        schedule.schedule_id,
+        schedule.executions,
+        amount,
+        schedule.recipient,
+        tip
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CreateScheduledWithdrawal<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = ScheduledWithdrawal::LEN,
+        seeds = [b"scheduled_withdrawal", vault.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump
+    )]
+    pub scheduled_withdrawal: Account<'info, ScheduledWithdrawal>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// cancelling stays possible on a frozen vault, it only ever stops money from leaving
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CancelScheduledWithdrawal<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"scheduled_withdrawal", vault.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump = scheduled_withdrawal.bump,
+    )]
+    pub scheduled_withdrawal: Account<'info, ScheduledWithdrawal>,
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct ExecuteScheduledWithdrawal<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"scheduled_withdrawal", vault.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump = scheduled_withdrawal.bump,
+    )]
+    pub scheduled_withdrawal: Account<'info, ScheduledWithdrawal>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == scheduled_withdrawal.recipient @ VaultError::InvalidTokenAccount
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub keeper_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
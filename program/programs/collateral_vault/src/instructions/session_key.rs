@@ -0,0 +1,322 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    CollateralVault, DepositEvent, JournalOp, LockBucket, ProtocolConfig, SessionKey, SessionKeyCreated,
+    SessionKeyRevoked, SubAccount, SubAccountAction, VaultJournal, EVENT_VERSION,
+    SESSION_SCOPE_ALL, SESSION_SCOPE_DEPOSIT, SESSION_SCOPE_LOCK, SESSION_SCOPE_UNLOCK,
+};
+use crate::errors::VaultError;
+use crate::instructions::{emit_balance_event, record_journal};
+
+// changing a session is revoke + create, same as allowances, so the used amount can't be
+// reset without the owner seeing it
+pub fn create_session_key(
+    ctx: Context<CreateSessionKey>,
+    session_key: Pubkey,
+    scope: u8,
+    amount_cap: u64,
+    expires_at: i64,
+) -> Result<()> {
+    require!(
+        scope != 0 && scope & !SESSION_SCOPE_ALL == 0,
+        VaultError::InvalidSessionKey
+    );
+    require!(amount_cap > 0, VaultError::InvalidAmount);
+    require!(session_key != ctx.accounts.owner.key(), VaultError::InvalidSessionKey);
+
+    let clock = Clock::get()?;
+    require!(
+        expires_at > clock.unix_timestamp
+            && expires_at - clock.unix_timestamp <= SessionKey::MAX_DURATION,
+        VaultError::InvalidSessionKey
+    );
+
+    let session = &mut ctx.accounts.session;
+    session.vault = ctx.accounts.vault.key();
+    session.session_key = session_key;
+    session.scope = scope;
+    session.amount_cap = amount_cap;
+    session.amount_used = 0;
+    session.expires_at = expires_at;
+    session.created_at = clock.unix_timestamp;
+    session.bump = ctx.bumps.session;
+
+    emit!(SessionKeyCreated {
+        version: EVENT_VERSION,
+        vault: session.vault,
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        session_key,
+        scope,
+        amount_cap,
+        expires_at,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Created session key {} with scope {:#05b}, cap {}, expiring at {}",
+        session_key,
+        scope,
+        amount_cap,
+        expires_at
+    );
+
+    Ok(())
+}
+
+// also how the owner reclaims the rent once a session has expired
+pub fn revoke_session_key(ctx: Context<RevokeSessionKey>, session_key: Pubkey) -> Result<()> {
+    let clock = Clock::get()?;
+
+    emit!(SessionKeyRevoked {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        session_key,
+        amount_used: ctx.accounts.session.amount_used,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Revoked session key {}", session_key);
+
+    Ok(())
+}
+
+// deposit signed by the session key. the tokens come from an account the session key can
+// move, usually the owner's own token account with the session key approved as its delegate
+pub fn session_deposit(ctx: Context<SessionDeposit>, amount: u64, reference: Option<[u8; 32]>) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let clock = Clock::get()?;
+    ctx.accounts.session.authorize(SESSION_SCOPE_DEPOSIT, amount, &clock)?;
+
+    ctx.accounts
+        .protocol_config
+        .check_deposit_caps(ctx.accounts.vault.load()?.total_balance, amount)?;
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.source_token_account.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: ctx.accounts.session_signer.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, amount)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.add_deposit(amount)?;
+    ctx.accounts.protocol_config.record_inflow(amount)?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Deposit,
+        amount,
+        ctx.accounts.session_signer.key(),
+        &clock,
+    )?;
+
+    emit!(DepositEvent {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
+        user: ctx.accounts.session_signer.key(),
+        amount,
+        new_balance: vault.total_balance,
+        timestamp: clock.unix_timestamp,
+        reference,
+    });
+
+    msg!("Session deposited {} to vault. New balance: {}", amount, vault.total_balance);
+
+    Ok(())
+}
+
+// session-signed fund_sub_account
+pub fn session_fund_sub_account(
+    ctx: Context<SessionManageSubAccount>,
+    _sub_account_id: u16,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let clock = Clock::get()?;
+    ctx.accounts.session.authorize(SESSION_SCOPE_LOCK, amount, &clock)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let sub_account = &mut ctx.accounts.sub_account;
+
+    vault.lock_into(LockBucket::SubAccounts, amount)?;
+    sub_account.fund(amount)?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Lock,
+        amount,
+        sub_account.key(),
+        &clock,
+    )?;
+
+    let event_seq = vault.next_event_seq()?;
+    emit_balance_event(ctx.accounts.vault.key(), event_seq, sub_account, SubAccountAction::Fund, amount)
+}
+
+// session-signed defund_sub_account
+pub fn session_defund_sub_account(
+    ctx: Context<SessionManageSubAccount>,
+    _sub_account_id: u16,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let clock = Clock::get()?;
+    ctx.accounts.session.authorize(SESSION_SCOPE_UNLOCK, amount, &clock)?;
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let sub_account = &mut ctx.accounts.sub_account;
+
+    sub_account.defund(amount)?;
+    vault.unlock_from(LockBucket::SubAccounts, amount)?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Unlock,
+        amount,
+        sub_account.key(),
+        &clock,
+    )?;
+
+    let event_seq = vault.next_event_seq()?;
+    emit_balance_event(ctx.accounts.vault.key(), event_seq, sub_account, SubAccountAction::Defund, amount)
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSessionKey<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = SessionKey::LEN,
+        seeds = [b"session_key", vault.key().as_ref(), session_key.as_ref()],
+        bump
+    )]
+    pub session: Account<'info, SessionKey>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct RevokeSessionKey<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // no freeze check, revoking only ever takes access away
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"session_key", vault.key().as_ref(), session_key.as_ref()],
+        bump = session.bump,
+    )]
+    pub session: Account<'info, SessionKey>,
+}
+
+#[derive(Accounts)]
+pub struct SessionDeposit<'info> {
+    pub session_signer: Signer<'info>,
+
+    // the seeds tie the session to this vault and this signer
+    #[account(
+        mut,
+        seeds = [b"session_key", vault.key().as_ref(), session_signer.key().as_ref()],
+        bump = session.bump,
+    )]
+    pub session: Account<'info, SessionKey>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(mut)]
+    pub source_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(sub_account_id: u16)]
+pub struct SessionManageSubAccount<'info> {
+    pub session_signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"session_key", vault.key().as_ref(), session_signer.key().as_ref()],
+        bump = session.bump,
+    )]
+    pub session: Account<'info, SessionKey>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"sub_account", vault.key().as_ref(), &sub_account_id.to_le_bytes()],
+        bump = sub_account.bump,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
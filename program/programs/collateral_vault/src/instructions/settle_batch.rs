@@ -0,0 +1,295 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    AuthorizedProgram, CollateralVault, JournalOp, ProgramRegistry, ProtocolConfig, SettlementEvent,
+    TransferEvent, VaultJournal, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+const MAX_SETTLEMENT_VAULTS: usize = 16;
+const MAX_SETTLEMENT_LEGS: usize = 32;
+
+// indices point into the participant list built from remaining_accounts, which is laid out as
+// [vault_0, vault_token_account_0, vault_1, vault_token_account_1, ...]. a vault that has a
+// journal is followed by its journal, e.g. [vault_0, vault_token_account_0, journal_0, vault_1, ...]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SettlementLeg {
+    pub from_vault: u8,
+    pub to_vault: u8,
+    pub amount: u64,
+}
+
+pub fn settle_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SettleBatch<'info>>,
+    legs: Vec<SettlementLeg>,
+) -> Result<()> {
+    let caller_program = ctx.accounts.caller_program.key();
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_TRANSFER)?;
+
+    require!(
+        !legs.is_empty() && legs.len() <= MAX_SETTLEMENT_LEGS,
+        VaultError::BatchLimitExceeded
+    );
+
+    let remaining = ctx.remaining_accounts;
+
+    // the loaders write straight into account data so nothing needs exiting
+    let mut vaults: Vec<Participant<'info>> = Vec::new();
+    let mut cursor = 0usize;
+    while cursor < remaining.len() {
+        require!(cursor + 2 <= remaining.len(), VaultError::InvalidBatchOperation);
+        require!(vaults.len() < MAX_SETTLEMENT_VAULTS, VaultError::BatchLimitExceeded);
+
+        let vault_info = &remaining[cursor];
+        let token_info = &remaining[cursor + 1];
+        cursor += 2;
+
+        require!(
+            vault_info.is_writable && token_info.is_writable,
+            VaultError::InvalidBatchOperation
+        );
+        require!(
+            !vaults.iter().any(|p| p.vault.key() == vault_info.key()),
+            VaultError::InvalidBatchOperation
+        );
+
+        let loader = AccountLoader::<CollateralVault>::try_from(vault_info)?;
+        let (owner, bump, has_journal) = {
+            let vault = loader.load()?;
+
+            // remaining accounts don't get seed constraints, so re-derive the PDA by hand
+            let expected = Pubkey::create_program_address(
+                &[b"vault", vault.owner.as_ref(), &[vault.bump]],
+                &crate::ID,
+            )
+            .map_err(|_| error!(VaultError::InvalidAuthority))?;
+            require!(expected == vault_info.key(), VaultError::InvalidAuthority);
+            require!(
+                vault.token_account == token_info.key(),
+                VaultError::InvalidTokenAccount
+            );
+            (vault.owner, vault.bump, vault.has_journal())
+        };
+
+        let journal = if has_journal {
+            let journal_info = remaining.get(cursor).ok_or(error!(VaultError::JournalRequired))?;
+            cursor += 1;
+            require!(journal_info.is_writable, VaultError::InvalidBatchOperation);
+
+            let journal = AccountLoader::<VaultJournal>::try_from(journal_info)?;
+            let expected = Pubkey::create_program_address(
+                &[b"journal", vault_info.key.as_ref(), &[journal.load()?.bump]],
+                &crate::ID,
+            )
+            .map_err(|_| error!(VaultError::InvalidAuthority))?;
+            require!(expected == journal_info.key(), VaultError::InvalidAuthority);
+            Some(journal)
+        } else {
+            None
+        };
+
+        vaults.push(Participant {
+            vault: loader,
+            token_account: token_info,
+            journal,
+            owner,
+            bump,
+        });
+    }
+
+    let vault_count = vaults.len();
+    require!(vault_count > 0, VaultError::InvalidBatchOperation);
+
+    let mut net: Vec<i128> = vec![0; vault_count];
+    let mut total_debits = 0u64;
+
+    for leg in legs.iter() {
+        let from = leg.from_vault as usize;
+        let to = leg.to_vault as usize;
+
+        require!(leg.amount > 0, VaultError::InvalidAmount);
+        require!(
+            from < vault_count && to < vault_count && from != to,
+            VaultError::InvalidBatchOperation
+        );
+
+        net[from] -= leg.amount as i128;
+        net[to] += leg.amount as i128;
+
+        total_debits = total_debits.checked_add(leg.amount)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+    }
+
+    // frozen vaults are neither debited nor paid into, same as deposit_for. the deltas sum to
+    // zero so the protocol TVL doesn't move, but each creditor still has to fit under the vault cap
+    for (participant, delta) in vaults.iter().zip(net.iter()) {
+        let mut vault = participant.vault.load_mut()?;
+        require!(!vault.is_frozen(), VaultError::VaultFrozen);
+        if *delta < 0 {
+            vault.sub_withdrawal((-*delta) as u64)?;
+        } else if *delta > 0 {
+            ctx.accounts
+                .protocol_config
+                .check_vault_cap(vault.total_balance, *delta as u64)?;
+            vault.add_deposit(*delta as u64)?;
+        }
+    }
+
+    let balances_before = token_balances(&vaults)?;
+
+    // pairing debtors with creditors greedily keeps it to at most vault_count - 1 transfers
+    let mut remaining_net = net.clone();
+    let mut debtor = 0usize;
+    let mut creditor = 0usize;
+    let mut transfer_count = 0u8;
+
+    loop {
+        while debtor < vault_count && remaining_net[debtor] >= 0 {
+            debtor += 1;
+        }
+        while creditor < vault_count && remaining_net[creditor] <= 0 {
+            creditor += 1;
+        }
+        if debtor >= vault_count || creditor >= vault_count {
+            break;
+        }
+
+        let amount = (-remaining_net[debtor]).min(remaining_net[creditor]);
+        remaining_net[debtor] += amount;
+        remaining_net[creditor] -= amount;
+
+        let debtor_vault = &vaults[debtor];
+        let seeds = &[
+            b"vault",
+            debtor_vault.owner.as_ref(),
+            &[debtor_vault.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: debtor_vault.token_account.clone(),
+            to: vaults[creditor].token_account.clone(),
+            authority: debtor_vault.vault.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount as u64)?;
+
+        transfer_count = transfer_count.saturating_add(1);
+    }
+
+    // the ledger deltas and the netted transfers are computed separately, so hold one against
+    // the other: every participant's token account has to have moved by exactly its delta.
+    // a token account shared by two vaults or a netting bug shows up here
+    let balances_after = token_balances(&vaults)?;
+    for (i, delta) in net.iter().enumerate() {
+        let moved = balances_after[i] as i128 - balances_before[i] as i128;
+        require!(moved == *delta, VaultError::SettlementNotConserved);
+    }
+
+    let clock = Clock::get()?;
+    for leg in legs.iter() {
+        let from = &vaults[leg.from_vault as usize];
+        let to = &vaults[leg.to_vault as usize];
+
+        let from_event_seq = {
+            let mut from_vault = from.vault.load_mut()?;
+            record_journal(
+                &from_vault,
+                from.journal.as_ref(),
+                JournalOp::TransferOut,
+                leg.amount,
+                to.vault.key(),
+                &clock,
+            )?;
+            from_vault.next_event_seq()?
+        };
+        let to_event_seq = {
+            let mut to_vault = to.vault.load_mut()?;
+            record_journal(
+                &to_vault,
+                to.journal.as_ref(),
+                JournalOp::TransferIn,
+                leg.amount,
+                from.vault.key(),
+                &clock,
+            )?;
+            to_vault.next_event_seq()?
+        };
+
+        emit!(TransferEvent {
+            version: EVENT_VERSION,
+            from_vault: from.vault.key(),
+            from_event_seq,
+            to_vault: to.vault.key(),
+            to_event_seq,
+            from: from.owner,
+            to: to.owner,
+            amount: leg.amount,
+            timestamp: clock.unix_timestamp,
+        });
+    }
+
+    emit!(SettlementEvent {
+        version: EVENT_VERSION,
+        caller_program,
+        vault_count: vault_count as u8,
+        leg_count: legs.len() as u8,
+        transfer_count,
+        total_volume: total_debits,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Settled {} legs across {} vaults with {} transfers, volume: {}",
+        legs.len(),
+        vault_count,
+        transfer_count,
+        total_debits
+    );
+
+    Ok(())
+}
+
+struct Participant<'info> {
+    vault: AccountLoader<'info, CollateralVault>,
+    token_account: &'info AccountInfo<'info>,
+    journal: Option<AccountLoader<'info, VaultJournal>>,
+    owner: Pubkey,
+    bump: u8,
+}
+
+fn token_balances<'info>(vaults: &[Participant<'info>]) -> Result<Vec<u64>> {
+    vaults
+        .iter()
+        .map(|p| Ok(Account::<TokenAccount>::try_from(p.token_account)?.amount))
+        .collect()
+}
+
+#[derive(Accounts)]
+pub struct SettleBatch<'info> {
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -0,0 +1,459 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    AuthorizedProgram, PoolAssetsUpdated, PoolDepositEvent, PoolPosition, PoolPositionOpened,
+    PoolWithdrawEvent, ProgramRegistry, ProtocolConfig, SharePool, SharePoolInitialized,
+    EVENT_VERSION,
+};
+use crate::errors::VaultError;
+
+pub fn initialize_share_pool(ctx: Context<InitializeSharePool>, pool_id: u64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let clock = Clock::get()?;
+
+    pool.admin = ctx.accounts.admin.key();
+    pool.pool_id = pool_id;
+    pool.mint = ctx.accounts.mint.key();
+    pool.token_account = ctx.accounts.pool_token_account.key();
+    pool.total_assets = 0;
+    pool.total_shares = 0;
+    pool.created_at = clock.unix_timestamp;
+    pool.bump = ctx.bumps.pool;
+
+    emit!(SharePoolInitialized {
+        version: EVENT_VERSION,
+        pool: pool.key(),
+        pool_id,
+        admin: pool.admin,
+        mint: pool.mint,
+        token_account: pool.token_account,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Share pool {} initialized for mint {}", pool_id, pool.mint);
+
+    Ok(())
+}
+
+pub fn open_pool_position(ctx: Context<OpenPoolPosition>) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    position.pool = ctx.accounts.pool.key();
+    position.owner = ctx.accounts.owner.key();
+    position.shares = 0;
+    position.bump = ctx.bumps.position;
+
+    let clock = Clock::get()?;
+    emit!(PoolPositionOpened {
+        version: EVENT_VERSION,
+        pool: position.pool,
+        owner: position.owner,
+        position: position.key(),
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+// min_shares protects the depositor if the share price moves between preview and execution
+pub fn pool_deposit(ctx: Context<PoolDeposit>, assets: u64, min_shares: u64) -> Result<()> {
+    require!(assets > 0, VaultError::InvalidAmount);
+
+    ctx.accounts.protocol_config.check_global_cap(assets)?;
+
+    let pool = &mut ctx.accounts.pool;
+    let shares = pool.preview_deposit(assets)?;
+    require!(shares > 0 && shares >= min_shares, VaultError::SlippageExceeded);
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.user_token_account.to_account_info(),
+        to: ctx.accounts.pool_token_account.to_account_info(),
+        authority: ctx.accounts.owner.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, assets)?;
+
+    pool.add_liquidity(assets, shares)?;
+    let position = &mut ctx.accounts.position;
+    position.shares = position.shares
+        .checked_add(shares)
+        .ok_or(error!(VaultError::NumericalOverflow))?;
+    ctx.accounts.protocol_config.record_inflow(assets)?;
+
+    let clock = Clock::get()?;
+    emit!(PoolDepositEvent {
+        version: EVENT_VERSION,
+        pool: pool.key(),
+        owner: position.owner,
+        assets,
+        shares,
+        total_assets: pool.total_assets,
+        total_shares: pool.total_shares,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Deposited {} into pool {} for {} shares", assets, pool.pool_id, shares);
+
+    Ok(())
+}
+
+// exact assets out, burns however many shares that costs (rounded up)
+pub fn pool_withdraw(ctx: Context<PoolWithdraw>, assets: u64, max_shares: u64) -> Result<()> {
+    require!(assets > 0, VaultError::InvalidAmount);
+
+    let shares = ctx.accounts.pool.preview_withdraw(assets)?;
+    require!(shares <= max_shares, VaultError::SlippageExceeded);
+
+    burn_and_pay(ctx, assets, shares)
+}
+
+// exact shares in, pays out whatever they are worth (rounded down)
+pub fn pool_redeem(ctx: Context<PoolWithdraw>, shares: u64, min_assets: u64) -> Result<()> {
+    require!(shares > 0, VaultError::InvalidAmount);
+
+    let assets = ctx.accounts.pool.preview_redeem(shares)?;
+    require!(assets > 0 && assets >= min_assets, VaultError::SlippageExceeded);
+
+    burn_and_pay(ctx, assets, shares)
+}
+
+fn burn_and_pay(ctx: Context<PoolWithdraw>, assets: u64, shares: u64) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    require!(position.shares >= shares, VaultError::InsufficientShares);
+
+    let pool = &mut ctx.accounts.pool;
+    pool.remove_liquidity(assets, shares)?;
+    position.shares -= shares;
+
+    let pool_id_bytes = pool.pool_id.to_le_bytes();
+    let seeds = &[
+        b"share_pool",
+        pool_id_bytes.as_ref(),
+        &[pool.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.pool_token_account.to_account_info(),
+        to: ctx.accounts.user_token_account.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, assets)?;
+
+    ctx.accounts.protocol_config.record_outflow(assets);
+
+    let clock = Clock::get()?;
+    emit!(PoolWithdrawEvent {
+        version: EVENT_VERSION,
+        pool: pool.key(),
+        owner: position.owner,
+        assets,
+        shares,
+        total_assets: pool.total_assets,
+        total_shares: pool.total_shares,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Withdrew {} from pool {} for {} shares", assets, pool.pool_id, shares);
+
+    Ok(())
+}
+
+// yield has to arrive as real tokens, the share price only moves once they're in the pool
+pub fn report_pool_yield(ctx: Context<ReportPoolYield>, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.source_token_account.to_account_info(),
+        to: ctx.accounts.pool_token_account.to_account_info(),
+        authority: ctx.accounts.payer.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, amount)?;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.add_liquidity(amount, 0)?;
+    ctx.accounts.protocol_config.record_inflow(amount)?;
+
+    emit_assets_updated(pool, amount, false)
+}
+
+// an authorized program (strategy, liquidation engine) takes a realised loss out of the pool,
+// every shareholder takes the hit pro rata through the lower share price. the program has to
+// sign through its caller authority PDA and the tokens can only go to the protocol treasury,
+// naming a registered program id isn't enough to move anything
+pub fn report_pool_loss(ctx: Context<ReportPoolLoss>, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_TRANSFER)?;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.remove_liquidity(amount, 0)?;
+
+    let pool_id_bytes = pool.pool_id.to_le_bytes();
+    let seeds = &[
+        b"share_pool",
+        pool_id_bytes.as_ref(),
+        &[pool.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.pool_token_account.to_account_info(),
+        to: ctx.accounts.destination_token_account.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    ctx.accounts.protocol_config.record_outflow(amount);
+
+    emit_assets_updated(pool, amount, true)
+}
+
+fn emit_assets_updated(pool: &Account<SharePool>, amount: u64, is_loss: bool) -> Result<()> {
+    let clock = Clock::get()?;
+    emit!(PoolAssetsUpdated {
+        version: EVENT_VERSION,
+        pool: pool.key(),
+        amount,
+        is_loss,
+        total_assets: pool.total_assets,
+        total_shares: pool.total_shares,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Pool {} {} {}. Total assets: {}, shares: {}",
+        pool.pool_id,
+        if is_loss { "lost" } else { "earned" },
+        amount,
+        pool.total_assets,
+        pool.total_shares
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct InitializeSharePool<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == protocol_config.admin @ VaultError::InvalidAuthority
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = SharePool::LEN,
+        seeds = [b"share_pool".as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, SharePool>,
+
+    // same ATA-owned-by-PDA setup as the per-user vaults
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPoolPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"share_pool".as_ref(), &pool.pool_id.to_le_bytes()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, SharePool>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = PoolPosition::LEN,
+        seeds = [b"pool_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, PoolPosition>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PoolDeposit<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"share_pool".as_ref(), &pool.pool_id.to_le_bytes()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, SharePool>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+    )]
+    pub position: Account<'info, PoolPosition>,
+
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct PoolWithdraw<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"share_pool".as_ref(), &pool.pool_id.to_le_bytes()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, SharePool>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+    )]
+    pub position: Account<'info, PoolPosition>,
+
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReportPoolYield<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"share_pool".as_ref(), &pool.pool_id.to_le_bytes()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, SharePool>,
+
+    #[account(mut)]
+    pub source_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReportPoolLoss<'info> {
+    #[account(
+        mut,
+        seeds = [b"share_pool".as_ref(), &pool.pool_id.to_le_bytes()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, SharePool>,
+
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.key() == protocol_config.treasury @ VaultError::InvalidTokenAccount
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
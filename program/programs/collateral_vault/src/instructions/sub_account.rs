@@ -0,0 +1,499 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    AuthorizedProgram, CollateralVault, JournalOp, LockBucket, ProgramExposure, ProgramRegistry, SubAccount,
+    SubAccountAction, SubAccountBalanceEvent, SubAccountClosed, SubAccountCreated, SubAccountTransferEvent,
+    TransferEvent, VaultJournal, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+pub fn create_sub_account(
+    ctx: Context<CreateSubAccount>,
+    sub_account_id: u16,
+    name: String,
+) -> Result<()> {
+    require!(
+        !name.is_empty() && name.len() <= SubAccount::MAX_NAME_LEN,
+        VaultError::InvalidSubAccountName
+    );
+
+    let clock = Clock::get()?;
+    let sub_account = &mut ctx.accounts.sub_account;
+    sub_account.vault = ctx.accounts.vault.key();
+    sub_account.sub_account_id = sub_account_id;
+    sub_account.name = name.clone();
+    sub_account.available_balance = 0;
+    sub_account.locked_balance = 0;
+    sub_account.created_at = clock.unix_timestamp;
+    sub_account.bump = ctx.bumps.sub_account;
+
+    emit!(SubAccountCreated {
+        version: EVENT_VERSION,
+        vault: sub_account.vault,
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        sub_account_id,
+        name: name.clone(),
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Created sub-account {} ({})", sub_account_id, name);
+
+    Ok(())
+}
+
+// moving funds into a bucket reserves them in the vault's sub-account bucket
+pub fn fund_sub_account(ctx: Context<ManageSubAccount>, _sub_account_id: u16, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let clock = Clock::get()?;
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let sub_account = &mut ctx.accounts.sub_account;
+
+    vault.lock_into(LockBucket::SubAccounts, amount)?;
+    sub_account.fund(amount)?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Lock,
+        amount,
+        sub_account.key(),
+        &clock,
+    )?;
+
+    let event_seq = vault.next_event_seq()?;
+    emit_balance_event(ctx.accounts.vault.key(), event_seq, sub_account, SubAccountAction::Fund, amount)
+}
+
+// only the bucket's available part can go back, locked margin stays put
+pub fn defund_sub_account(ctx: Context<ManageSubAccount>, _sub_account_id: u16, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let clock = Clock::get()?;
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let sub_account = &mut ctx.accounts.sub_account;
+
+    sub_account.defund(amount)?;
+    vault.unlock_from(LockBucket::SubAccounts, amount)?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Unlock,
+        amount,
+        sub_account.key(),
+        &clock,
+    )?;
+
+    let event_seq = vault.next_event_seq()?;
+    emit_balance_event(ctx.accounts.vault.key(), event_seq, sub_account, SubAccountAction::Defund, amount)
+}
+
+pub fn close_sub_account(ctx: Context<CloseSubAccount>, sub_account_id: u16) -> Result<()> {
+    require!(
+        ctx.accounts.sub_account.total_balance() == 0,
+        VaultError::SubAccountNotEmpty
+    );
+
+    let clock = Clock::get()?;
+    emit!(SubAccountClosed {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: ctx.accounts.vault.load_mut()?.next_event_seq()?,
+        sub_account_id,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Closed sub-account {}", sub_account_id);
+
+    Ok(())
+}
+
+// authorized programs lock against one bucket, the vault totals don't move because
+// the bucket's funds are already counted as locked at the vault level
+pub fn lock_sub_account(ctx: Context<SubAccountCollateral>, _sub_account_id: u16, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    require!(!ctx.accounts.vault.load()?.is_frozen(), VaultError::VaultFrozen);
+    ctx.accounts
+        .program_registry
+        .record_lock(&mut ctx.accounts.program_exposure, amount)?;
+
+    let sub_account = &mut ctx.accounts.sub_account;
+    sub_account.lock(amount)?;
+
+    let event_seq = ctx.accounts.vault.load_mut()?.next_event_seq()?;
+    emit_balance_event(ctx.accounts.vault.key(), event_seq, sub_account, SubAccountAction::Lock, amount)
+}
+
+pub fn unlock_sub_account(ctx: Context<SubAccountCollateral>, _sub_account_id: u16, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_UNLOCK)?;
+
+    let sub_account = &mut ctx.accounts.sub_account;
+    sub_account.unlock(amount)?;
+    ctx.accounts
+        .program_registry
+        .record_release(&mut ctx.accounts.program_exposure, amount)?;
+
+    let event_seq = ctx.accounts.vault.load_mut()?.next_event_seq()?;
+    emit_balance_event(ctx.accounts.vault.key(), event_seq, sub_account, SubAccountAction::Unlock, amount)
+}
+
+// sub-account version of transfer_collateral: only the source bucket's locked margin can be
+// taken, so liquidating one strategy never reaches into another bucket or the main balance
+pub fn transfer_sub_account_collateral(
+    ctx: Context<TransferSubAccountCollateral>,
+    _from_sub_account_id: u16,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_TRANSFER)?;
+
+    let from_vault_key = ctx.accounts.from_vault.key();
+    let to_vault_key = ctx.accounts.to_vault.key();
+    let from_sub_account = &mut ctx.accounts.from_sub_account;
+
+    require!(
+        from_vault_key != to_vault_key,
+        VaultError::OperationNotAllowed
+    );
+
+    from_sub_account.seize_locked(amount)?;
+    ctx.accounts
+        .program_registry
+        .record_release(&mut ctx.accounts.program_exposure, amount)?;
+    let (from_owner, bump) = {
+        let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+        from_vault.seize_from(LockBucket::SubAccounts, amount)?;
+        (from_vault.owner, from_vault.bump)
+    };
+
+    let seeds = &[
+        b"vault",
+        from_owner.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.from_token_account.to_account_info(),
+        to: ctx.accounts.to_token_account.to_account_info(),
+        authority: ctx.accounts.from_vault.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    let mut to_vault = ctx.accounts.to_vault.load_mut()?;
+    to_vault.add_deposit(amount)?;
+
+    let clock = Clock::get()?;
+    record_journal(
+        &from_vault,
+        ctx.accounts.from_journal.as_ref(),
+        JournalOp::Seize,
+        amount,
+        to_vault_key,
+        &clock,
+    )?;
+    record_journal(
+        &to_vault,
+        ctx.accounts.to_journal.as_ref(),
+        JournalOp::TransferIn,
+        amount,
+        from_vault_key,
+        &clock,
+    )?;
+
+    // landing in a bucket means reserving it on the receiving side too
+    let to_sub_account_id = match ctx.accounts.to_sub_account.as_mut() {
+        Some(to_sub_account) => {
+            require!(
+                to_sub_account.vault == to_vault_key,
+                VaultError::InvalidAuthority
+            );
+            to_vault.lock_into(LockBucket::SubAccounts, amount)?;
+            to_sub_account.fund(amount)?;
+            record_journal(
+                &to_vault,
+                ctx.accounts.to_journal.as_ref(),
+                JournalOp::Lock,
+                amount,
+                to_sub_account.key(),
+                &clock,
+            )?;
+            Some(to_sub_account.sub_account_id)
+        }
+        None => None,
+    };
+
+    emit!(TransferEvent {
+        version: EVENT_VERSION,
+        from_vault: from_vault_key,
+        from_event_seq: from_vault.next_event_seq()?,
+        to_vault: to_vault_key,
+        to_event_seq: to_vault.next_event_seq()?,
+        from: from_owner,
+        to: to_vault.owner,
+        amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(SubAccountTransferEvent {
+        version: EVENT_VERSION,
+        from_vault: from_vault_key,
+        from_event_seq: from_vault.next_event_seq()?,
+        from_sub_account_id: from_sub_account.sub_account_id,
+        to_vault: to_vault_key,
+        to_event_seq: to_vault.next_event_seq()?,
+        to_sub_account_id,
+        amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Transferred {} from sub-account {} of {} to {}",
+        amount,
+        from_sub_account.sub_account_id,
+        from_owner,
+        to_vault.owner
+    );
+
+    Ok(())
+}
+
+pub fn emit_balance_event(
+    vault: Pubkey,
+    event_seq: u64,
+    sub_account: &SubAccount,
+    action: SubAccountAction,
+    amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    emit!(SubAccountBalanceEvent {
+        version: EVENT_VERSION,
+        vault,
+        event_seq,
+        sub_account_id: sub_account.sub_account_id,
+        action,
+        amount,
+        available_balance: sub_account.available_balance,
+        locked_balance: sub_account.locked_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Sub-account {} {:?} {}. Available: {}, Locked: {}",
+        sub_account.sub_account_id,
+        action,
+        amount,
+        sub_account.available_balance,
+        sub_account.locked_balance
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(sub_account_id: u16)]
+pub struct CreateSubAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = SubAccount::LEN,
+        seeds = [b"sub_account", vault.key().as_ref(), &sub_account_id.to_le_bytes()],
+        bump
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(sub_account_id: u16)]
+pub struct ManageSubAccount<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"sub_account", vault.key().as_ref(), &sub_account_id.to_le_bytes()],
+        bump = sub_account.bump,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(sub_account_id: u16)]
+pub struct CloseSubAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"sub_account", vault.key().as_ref(), &sub_account_id.to_le_bytes()],
+        bump = sub_account.bump,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(sub_account_id: u16)]
+pub struct SubAccountCollateral<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"sub_account", vault.key().as_ref(), &sub_account_id.to_le_bytes()],
+        bump = sub_account.bump,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+}
+
+#[derive(Accounts)]
+#[instruction(from_sub_account_id: u16)]
+pub struct TransferSubAccountCollateral<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", from_vault.load()?.owner.as_ref()],
+        bump = from_vault.load()?.bump,
+        constraint = !from_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub from_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"sub_account", from_vault.key().as_ref(), &from_sub_account_id.to_le_bytes()],
+        bump = from_sub_account.bump,
+    )]
+    pub from_sub_account: Account<'info, SubAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", to_vault.load()?.owner.as_ref()],
+        bump = to_vault.load()?.bump,
+        // frozen vaults can't be paid into, same as deposit_for
+        constraint = !to_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub to_vault: AccountLoader<'info, CollateralVault>,
+
+    // optional, without it the funds go to the receiving vault's main available balance
+    #[account(mut)]
+    pub to_sub_account: Option<Account<'info, SubAccount>>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.key() == from_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.key() == to_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), from_vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", from_vault.key().as_ref()],
+        bump = from_journal.load()?.bump,
+    )]
+    pub from_journal: Option<AccountLoader<'info, VaultJournal>>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", to_vault.key().as_ref()],
+        bump = to_journal.load()?.bump,
+    )]
+    pub to_journal: Option<AccountLoader<'info, VaultJournal>>,
+}
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::state::{
+    BalanceSyncedEvent, CollateralVault, JournalOp, ProtocolConfig, VaultJournal, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+// permissionless, tokens sent straight to the vault ATA never go through deposit so the
+// book balance doesn't see them. anything above the book balance gets credited as a donation
+pub fn sync_balance(ctx: Context<SyncBalance>) -> Result<()> {
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    let token_balance = ctx.accounts.vault_token_account.amount;
+    let book_balance = vault.total_balance;
+
+    // book above real means tokens are missing (e.g. yield credited without funding),
+    // crediting anything here would just hide that
+    require!(
+        token_balance >= book_balance,
+        VaultError::BookBalanceExceedsActual
+    );
+
+    let donation = token_balance - book_balance;
+    require!(donation > 0, VaultError::BalanceAlreadySynced);
+
+    vault.add_donation(donation)?;
+
+    // the tokens are already sitting in the vault, so caps aren't checked, only TVL is kept honest
+    ctx.accounts.protocol_config.record_inflow(donation)?;
+
+    // nobody on the other side is known, the tokens just showed up in the vault ATA
+    let clock = Clock::get()?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Donation,
+        donation,
+        Pubkey::default(),
+        &clock,
+    )?;
+    emit!(BalanceSyncedEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        owner: vault.owner,
+        donation,
+        previous_balance: book_balance,
+        new_balance: vault.total_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Synced vault balance, credited {} as donation. New balance: {}",
+        donation,
+        vault.total_balance
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SyncBalance<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
@@ -0,0 +1,322 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{
+    AuthorizedProgram, CollateralVault, JournalOp, LockBucket, LockEvent, LockRecord, LockRecordCreated,
+    LockRecordReleased, ProgramExposure, ProgramRegistry, UnlockEvent, VaultJournal, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+// same as lock_collateral but leaves a LockRecord behind so the owner has a way out
+// if the position manager that created it goes away
+pub fn lock_collateral_with_expiry(
+    ctx: Context<LockCollateralWithExpiry>,
+    lock_id: u64,
+    amount: u64,
+    expires_at: Option<i64>,
+    reason_code: u8,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let caller_program = ctx.accounts.caller_program.key();
+
+    let clock = Clock::get()?;
+    if let Some(expires_at) = expires_at {
+        require!(expires_at > clock.unix_timestamp, VaultError::InvalidLockExpiry);
+    }
+
+    ctx.accounts
+        .program_registry
+        .record_lock(&mut ctx.accounts.program_exposure, amount)?;
+
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
+
+    vault.lock_into(LockBucket::LockRecords, amount)?;
+    record_journal(&vault, ctx.accounts.journal.as_ref(), JournalOp::Lock, amount, caller_program, &clock)?;
+
+    let record = &mut ctx.accounts.lock_record;
+    record.vault = vault_key;
+    record.caller_program = caller_program;
+    record.lock_id = lock_id;
+    record.amount = amount;
+    record.outstanding = amount;
+    record.reason_code = reason_code;
+    record.expires_at = expires_at;
+    record.created_at = clock.unix_timestamp;
+    record.bump = ctx.bumps.lock_record;
+
+    emit!(LockEvent {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        user: vault.owner,
+        amount,
+        locked_balance: vault.locked_balance,
+        available_balance: vault.available_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(LockRecordCreated {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        caller_program,
+        lock_id,
+        amount,
+        reason_code,
+        expires_at,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!("Locked {} under lock id {}, expires at: {:?}", amount, lock_id, expires_at);
+
+    Ok(())
+}
+
+// normal exit path, the program that created the lock releases it
+pub fn release_collateral_lock(ctx: Context<ReleaseCollateralLock>, lock_id: u64) -> Result<()> {
+    let caller_program = ctx.accounts.caller_program.key();
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_UNLOCK)?;
+    require!(
+        ctx.accounts.lock_record.caller_program == caller_program,
+        VaultError::UnauthorizedProgram
+    );
+
+    release_record(
+        &ctx.accounts.vault,
+        ctx.accounts.journal.as_ref(),
+        &mut ctx.accounts.program_registry,
+        &mut ctx.accounts.program_exposure,
+        &ctx.accounts.lock_record,
+        lock_id,
+        false,
+    )
+}
+
+pub fn reclaim_expired_lock(ctx: Context<ReclaimExpiredLock>, lock_id: u64) -> Result<()> {
+    let clock = Clock::get()?;
+    require!(
+        ctx.accounts.lock_record.is_expired(&clock),
+        VaultError::LockNotExpired
+    );
+
+    let accounts = &mut *ctx.accounts;
+    release_record(
+        &accounts.vault,
+        accounts.journal.as_ref(),
+        &mut accounts.program_registry,
+        &mut accounts.program_exposure,
+        &accounts.lock_record,
+        lock_id,
+        true,
+    )
+}
+
+fn release_record(
+    vault_loader: &AccountLoader<CollateralVault>,
+    journal: Option<&AccountLoader<VaultJournal>>,
+    registry: &mut Account<ProgramRegistry>,
+    exposure: &mut Account<ProgramExposure>,
+    record: &LockRecord,
+    lock_id: u64,
+    reclaimed_by_owner: bool,
+) -> Result<()> {
+    let mut vault = vault_loader.load_mut()?;
+    let clock = Clock::get()?;
+
+    // whatever partial unlocks and liquidations against the record left over. the vault keeps
+    // it in the lock record bucket, so anything short of that is an error rather than a clamp
+    let amount = record.outstanding;
+    if amount > 0 {
+        vault.unlock_from(LockBucket::LockRecords, amount)?;
+        record_journal(&vault, journal, JournalOp::Unlock, amount, record.caller_program, &clock)?;
+    }
+    registry.record_release(exposure, amount)?;
+
+    emit!(UnlockEvent {
+        version: EVENT_VERSION,
+        vault: vault_loader.key(),
+        event_seq: vault.next_event_seq()?,
+        user: vault.owner,
+        amount,
+        locked_balance: vault.locked_balance,
+        available_balance: vault.available_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    emit!(LockRecordReleased {
+        version: EVENT_VERSION,
+        vault: vault_loader.key(),
+        event_seq: vault.next_event_seq()?,
+        lock_id,
+        amount,
+        reclaimed_by_owner,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Released lock {} ({}). Locked: {}, Available: {}",
+        lock_id,
+        amount,
+        vault.locked_balance,
+        vault.available_balance
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
+pub struct LockCollateralWithExpiry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = LockRecord::LEN,
+        seeds = [b"lock", vault.key().as_ref(), &lock_id.to_le_bytes()],
+        bump
+    )]
+    pub lock_record: Account<'info, LockRecord>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
+pub struct ReleaseCollateralLock<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    // rent goes back to the vault owner since they are the one whose funds were locked
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"lock", vault.key().as_ref(), &lock_id.to_le_bytes()],
+        bump = lock_record.bump,
+    )]
+    pub lock_record: Account<'info, LockRecord>,
+
+    /// CHECK: Only receives the record rent, must match the vault owner
+    #[account(mut, address = vault.load()?.owner @ VaultError::InvalidAuthority)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
+pub struct ReclaimExpiredLock<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"lock", vault.key().as_ref(), &lock_id.to_le_bytes()],
+        bump = lock_record.bump,
+    )]
+    pub lock_record: Account<'info, LockRecord>,
+
+    // the creating program's registry, so its exposure goes down with the reclaim. it's still
+    // there: deregister_program refuses while the record's amount counts against the program
+    #[account(
+        mut,
+        seeds = [b"program_registry", lock_record.caller_program.as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Account<'info, ProgramRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", lock_record.caller_program.as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
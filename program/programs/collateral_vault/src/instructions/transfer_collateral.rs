@@ -1,42 +1,42 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::state::{CollateralVault, VaultAuthority, TransferEvent};
+use crate::state::{
+    AuthorizedProgram, CollateralVault, JournalOp, ProgramExposure, ProgramRegistry, TransferEvent,
+    VaultJournal, EVENT_VERSION,
+};
 use crate::errors::VaultError;
+use crate::instructions::record_journal;
 
 pub fn transfer_collateral(ctx: Context<TransferCollateral>, amount: u64) -> Result<()> {
     require!(amount > 0, VaultError::InvalidAmount);
 
-    let caller_program = ctx.accounts.caller_program.key();
-    let authority = &ctx.accounts.vault_authority;
-    
-    require!(
-        authority.is_authorized(&caller_program),
-        VaultError::UnauthorizedProgram
-    );
-
-    let from_vault = &mut ctx.accounts.from_vault;
-    let to_vault = &mut ctx.accounts.to_vault;
-
-    require!(
-        from_vault.total_balance >= amount,
-        VaultError::InsufficientBalance
-    );
+    ctx.accounts.program_registry.check_permission(AuthorizedProgram::PERMISSION_TRANSFER)?;
+
+    // only collateral the calling program locked in this vault itself can be moved, never the
+    // owner's available funds or another program's locks
+    ctx.accounts
+        .program_registry
+        .record_release(&mut ctx.accounts.program_exposure, amount)?;
+    let (from_owner, bump) = {
+        let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+        from_vault.seize_locked(amount)?;
+        (from_vault.owner, from_vault.bump)
+    };
 
     // transferring between two vault PDAs - this is for things like liquidations
     // where collateral needs to move from liquidated user to liquidator
-    let from_owner = from_vault.owner;
     let seeds = &[
         b"vault",
         from_owner.as_ref(),
-        &[from_vault.bump],
+        &[bump],
     ];
     let signer = &[&seeds[..]];
 
     let cpi_accounts = Transfer {
         from: ctx.accounts.from_token_account.to_account_info(),
         to: ctx.accounts.to_token_account.to_account_info(),
-        authority: from_vault.to_account_info(),
+        authority: ctx.accounts.from_vault.to_account_info(),
     };
     
     let cpi_program = ctx.accounts.token_program.to_account_info();
@@ -44,11 +44,36 @@ pub fn transfer_collateral(ctx: Context<TransferCollateral>, amount: u64) -> Res
     
     token::transfer(cpi_ctx, amount)?;
 
-    from_vault.sub_withdrawal(amount)?;
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    let mut to_vault = ctx.accounts.to_vault.load_mut()?;
     to_vault.add_deposit(amount)?;
 
     let clock = Clock::get()?;
+    let from_vault_key = ctx.accounts.from_vault.key();
+    let to_vault_key = ctx.accounts.to_vault.key();
+    record_journal(
+        &from_vault,
+        ctx.accounts.from_journal.as_ref(),
+        JournalOp::TransferOut,
+        amount,
+        to_vault_key,
+        &clock,
+    )?;
+    record_journal(
+        &to_vault,
+        ctx.accounts.to_journal.as_ref(),
+        JournalOp::TransferIn,
+        amount,
+        from_vault_key,
+        &clock,
+    )?;
+
     emit!(TransferEvent {
+        version: EVENT_VERSION,
+        from_vault: from_vault_key,
+        from_event_seq: from_vault.next_event_seq()?,
+        to_vault: to_vault_key,
+        to_event_seq: to_vault.next_event_seq()?,
         from: from_vault.owner,
         to: to_vault.owner,
         amount,
@@ -69,39 +94,69 @@ pub fn transfer_collateral(ctx: Context<TransferCollateral>, amount: u64) -> Res
 pub struct TransferCollateral<'info> {
     #[account(
         mut,
-        seeds = [b"vault", from_vault.owner.as_ref()],
-        bump = from_vault.bump,
+        seeds = [b"vault", from_vault.load()?.owner.as_ref()],
+        bump = from_vault.load()?.bump,
+        constraint = !from_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub from_vault: Account<'info, CollateralVault>,
+    pub from_vault: AccountLoader<'info, CollateralVault>,
 
     #[account(
         mut,
-        seeds = [b"vault", to_vault.owner.as_ref()],
-        bump = to_vault.bump,
+        seeds = [b"vault", to_vault.load()?.owner.as_ref()],
+        bump = to_vault.load()?.bump,
+        // frozen vaults can't be paid into, same as deposit_for
+        constraint = !to_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub to_vault: Account<'info, CollateralVault>,
+    pub to_vault: AccountLoader<'info, CollateralVault>,
 
     #[account(
         mut,
-        constraint = from_token_account.key() == from_vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = from_token_account.key() == from_vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub from_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = to_token_account.key() == to_vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = to_token_account.key() == to_vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub to_token_account: Account<'info, TokenAccount>,
 
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
+
     #[account(
-        seeds = [b"vault_authority"],
-        bump = vault_authority.bump,
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
     )]
-    pub vault_authority: Account<'info, VaultAuthority>,
+    pub program_registry: Account<'info, ProgramRegistry>,
 
-    /// CHECK: Verified against vault_authority.authorized_programs
-    pub caller_program: UncheckedAccount<'info>,
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), from_vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
 
     pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", from_vault.key().as_ref()],
+        bump = from_journal.load()?.bump,
+    )]
+    pub from_journal: Option<AccountLoader<'info, VaultJournal>>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", to_vault.key().as_ref()],
+        bump = to_journal.load()?.bump,
+    )]
+    pub to_journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
@@ -1,24 +1,46 @@
 use anchor_lang::prelude::*;
 
-use crate::state::{CollateralVault, VaultAuthority, UnlockEvent};
+use crate::state::{
+    AuthorizedProgram, CollateralVault, JournalOp, LockBucket, LockRecord, ProgramExposure,
+    ProgramRegistry, UnlockEvent, VaultJournal, EVENT_VERSION,
+};
 use crate::errors::VaultError;
+use crate::instructions::record_journal;
 
 pub fn unlock_collateral(ctx: Context<UnlockCollateral>, amount: u64) -> Result<()> {
     require!(amount > 0, VaultError::InvalidAmount);
 
-    let caller_program = ctx.accounts.caller_program.key();
-    let authority = &ctx.accounts.vault_authority;
-    
-    require!(
-        authority.is_authorized(&caller_program),
-        VaultError::UnauthorizedProgram
-    );
+    let registry = &mut ctx.accounts.program_registry;
+
+    registry.check_permission(AuthorizedProgram::PERMISSION_UNLOCK)?;
+    // a program can only unlock what it locked in this vault, whichever bucket it sits in
+    registry.record_release(&mut ctx.accounts.program_exposure, amount)?;
 
-    let vault = &mut ctx.accounts.vault;
-    vault.unlock(amount)?;
+    // without a record only the vault's plain program locks can come back, anything held in a
+    // LockBucket leaves through its own instructions
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    match ctx.accounts.lock_record.as_mut() {
+        Some(lock_record) => {
+            lock_record.take(amount)?;
+            vault.unlock_from(LockBucket::LockRecords, amount)?;
+        }
+        None => vault.unlock(amount)?,
+    }
 
     let clock = Clock::get()?;
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Unlock,
+        amount,
+        ctx.accounts.caller_program.key(),
+        &clock,
+    )?;
+
     emit!(UnlockEvent {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
         user: vault.owner,
         amount,
         locked_balance: vault.locked_balance,
@@ -40,18 +62,47 @@ pub fn unlock_collateral(ctx: Context<UnlockCollateral>, amount: u64) -> Result<
 pub struct UnlockCollateral<'info> {
     #[account(
         mut,
-        seeds = [b"vault", vault.owner.as_ref()],
-        bump = vault.bump,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: UncheckedAccount<'info>,
 
     #[account(
-        seeds = [b"vault_authority"],
-        bump = vault_authority.bump,
+        mut,
+        seeds = [b"program_registry", caller_program.key().as_ref()],
+        bump = program_registry.bump,
     )]
-    pub vault_authority: Account<'info, VaultAuthority>,
+    pub program_registry: Account<'info, ProgramRegistry>,
 
-    /// CHECK: Verified against vault_authority.authorized_programs
-    pub caller_program: UncheckedAccount<'info>,
+    #[account(
+        constraint = caller_authority.key() == program_registry.caller_authority() @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", caller_program.key().as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Account<'info, ProgramExposure>,
+
+    // unlocks part of this record instead of the plain locks, release_collateral_lock still
+    // closes it and frees whatever is left
+    #[account(
+        mut,
+        constraint = lock_record.vault == vault.key() @ VaultError::InvalidAuthority,
+        constraint = lock_record.caller_program == caller_program.key() @ VaultError::UnauthorizedProgram,
+    )]
+    pub lock_record: Option<Account<'info, LockRecord>>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
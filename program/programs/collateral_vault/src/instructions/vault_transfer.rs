@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::state::{
+    CollateralVault, JournalOp, ProtocolConfig, VaultJournal, VaultTransferEvent, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::{record_journal, settle_vesting_schedules};
+
+// owner-initiated version of transfer_collateral, goes through the same policies as a withdrawal
+// when a timelock is configured the owner has to request_withdrawal with the destination vault
+// (or its owner) as recipient first, and this instruction then settles that pending request
+pub fn transfer_to_vault<'info>(
+    ctx: Context<'_, '_, 'info, 'info, TransferToVault<'info>>,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+
+    let from_vault_key = ctx.accounts.from_vault.key();
+    let to_vault_key = ctx.accounts.to_vault.key();
+    let clock = Clock::get()?;
+
+    require!(
+        from_vault_key != to_vault_key,
+        VaultError::OperationNotAllowed
+    );
+
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    let to_vault = ctx.accounts.to_vault.load()?;
+    settle_vesting_schedules(
+        &mut from_vault,
+        &from_vault_key,
+        ctx.accounts.from_journal.as_ref(),
+        ctx.remaining_accounts,
+        &clock,
+    )?;
+
+    // whitelist entries are wallet addresses, but accept the vault PDA as well
+    if from_vault.whitelist_enabled != 0 {
+        require!(
+            from_vault.is_withdrawal_allowed(&to_vault.owner)
+                || from_vault.is_withdrawal_allowed(&to_vault_key),
+            VaultError::RecipientNotWhitelisted
+        );
+    }
+
+    if from_vault.has_rate_limit() {
+        from_vault.check_and_update_rate_limit(amount, &clock)?;
+    }
+
+    ctx.accounts
+        .protocol_config
+        .check_vault_cap(to_vault.total_balance, amount)?;
+
+    if from_vault.withdrawal_timelock > 0 {
+        let (pending_amount, recipient) = from_vault.execute_pending_withdrawal(&clock)?;
+        require!(pending_amount == amount, VaultError::InvalidAmount);
+        require!(
+            recipient == to_vault_key || recipient == to_vault.owner,
+            VaultError::InvalidAuthority
+        );
+    } else {
+        require!(
+            from_vault.available_balance >= amount,
+            VaultError::InsufficientAvailableBalance
+        );
+        from_vault.sub_withdrawal(amount)?;
+    }
+
+    let bump = from_vault.bump;
+    drop(from_vault);
+    drop(to_vault);
+
+    let owner_key = ctx.accounts.owner.key();
+    let seeds = &[
+        b"vault",
+        owner_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.from_token_account.to_account_info(),
+        to: ctx.accounts.to_token_account.to_account_info(),
+        authority: ctx.accounts.from_vault.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)?;
+
+    let mut from_vault = ctx.accounts.from_vault.load_mut()?;
+    let mut to_vault = ctx.accounts.to_vault.load_mut()?;
+    to_vault.add_deposit(amount)?;
+    record_journal(
+        &from_vault,
+        ctx.accounts.from_journal.as_ref(),
+        JournalOp::TransferOut,
+        amount,
+        to_vault_key,
+        &clock,
+    )?;
+    record_journal(
+        &to_vault,
+        ctx.accounts.to_journal.as_ref(),
+        JournalOp::TransferIn,
+        amount,
+        from_vault_key,
+        &clock,
+    )?;
+
+    emit!(VaultTransferEvent {
+        version: EVENT_VERSION,
+        from_vault: from_vault_key,
+        from_event_seq: from_vault.next_event_seq()?,
+        to_vault: to_vault_key,
+        to_event_seq: to_vault.next_event_seq()?,
+        from: from_vault.owner,
+        to: to_vault.owner,
+        amount,
+        from_balance: from_vault.total_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Sent {} from {} to {}",
+        amount,
+        from_vault.owner,
+        to_vault.owner
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct TransferToVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", owner.key().as_ref()],
+        bump = from_vault.load()?.bump,
+        has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !from_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub from_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", to_vault.load()?.owner.as_ref()],
+        bump = to_vault.load()?.bump,
+        // frozen vaults can't be paid into, same as deposit_for
+        constraint = !to_vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub to_vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.key() == from_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.key() == to_vault.load()?.token_account @ VaultError::InvalidTokenAccount
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", from_vault.key().as_ref()],
+        bump = from_journal.load()?.bump,
+    )]
+    pub from_journal: Option<AccountLoader<'info, VaultJournal>>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", to_vault.key().as_ref()],
+        bump = to_journal.load()?.bump,
+    )]
+    pub to_journal: Option<AccountLoader<'info, VaultJournal>>,
+}
@@ -0,0 +1,290 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{
+    CollateralVault, JournalOp, LockBucket, ProgramExposure, ProgramRegistry, VaultJournal,
+    VestingKind, VestingReleased, VestingSchedule, VestingScheduleCreated, EVENT_VERSION,
+};
+use crate::errors::VaultError;
+use crate::instructions::record_journal;
+
+// the vested part is never pushed anywhere on its own, it gets moved back to available
+// whenever someone calls release_vested or passes the schedule into any instruction that
+// takes funds out of available (see settle_vesting_schedules)
+#[allow(clippy::too_many_arguments)]
+pub fn create_vesting_schedule(
+    ctx: Context<CreateVestingSchedule>,
+    schedule_id: u64,
+    kind: VestingKind,
+    amount: u64,
+    start_ts: i64,
+    cliff_ts: i64,
+    end_ts: i64,
+) -> Result<()> {
+    require!(amount > 0, VaultError::InvalidAmount);
+    require!(
+        start_ts <= cliff_ts
+            && cliff_ts <= end_ts
+            && (kind == VestingKind::Cliff || start_ts < end_ts),
+        VaultError::InvalidVestingSchedule
+    );
+
+    let creator = ctx.accounts.creator.key();
+
+    // owners can lock up their own funds, anyone else has to come through an authorized program,
+    // and then the schedule counts against that program's exposure until it vests
+    let vault_key = ctx.accounts.vault.key();
+    let owner = ctx.accounts.vault.load()?.owner;
+
+    let (caller_program, exposure_held) = if creator != owner {
+        let accounts = &mut *ctx.accounts;
+        let (Some(caller_program), Some(registry), Some(_), Some(exposure)) = (
+            accounts.caller_program.as_ref(),
+            accounts.program_registry.as_mut(),
+            accounts.caller_authority.as_ref(),
+            accounts.program_exposure.as_mut(),
+        ) else {
+            return err!(VaultError::UnauthorizedProgram);
+        };
+
+        registry.record_lock(exposure, amount)?;
+        (caller_program.key(), amount)
+    } else {
+        (Pubkey::default(), 0)
+    };
+
+    let mut vault = ctx.accounts.vault.load_mut()?;
+    vault.lock_into(LockBucket::Vesting, amount)?;
+
+    let clock = Clock::get()?;
+    record_journal(&vault, ctx.accounts.journal.as_ref(), JournalOp::Lock, amount, creator, &clock)?;
+
+    let schedule = &mut ctx.accounts.vesting_schedule;
+    schedule.vault = vault_key;
+    schedule.creator = creator;
+    schedule.caller_program = caller_program;
+    schedule.schedule_id = schedule_id;
+    schedule.kind = kind;
+    schedule.total_amount = amount;
+    schedule.released_amount = 0;
+    schedule.start_ts = start_ts;
+    schedule.cliff_ts = cliff_ts;
+    schedule.end_ts = end_ts;
+    schedule.exposure_held = exposure_held;
+    schedule.bump = ctx.bumps.vesting_schedule;
+
+    emit!(VestingScheduleCreated {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        creator,
+        schedule_id,
+        kind,
+        total_amount: amount,
+        start_ts,
+        cliff_ts,
+        end_ts,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Vesting schedule {} created for {} ({:?}), ends at {}",
+        schedule_id,
+        amount,
+        kind,
+        end_ts
+    );
+
+    Ok(())
+}
+
+// permissionless, it can only ever move funds from locked back to available. vesting settled by
+// other instructions doesn't touch the creating program's exposure, passing its registry and
+// exposure here catches that up
+pub fn release_vested(ctx: Context<ReleaseVested>, _schedule_id: u64) -> Result<()> {
+    let clock = Clock::get()?;
+    let released = settle_vesting(
+        &mut *ctx.accounts.vault.load_mut()?,
+        &ctx.accounts.vault.key(),
+        ctx.accounts.journal.as_ref(),
+        &mut ctx.accounts.vesting_schedule,
+        &clock,
+    )?;
+
+    let accounts = &mut *ctx.accounts;
+    let exposure_released = match (accounts.program_registry.as_mut(), accounts.program_exposure.as_mut()) {
+        (Some(registry), Some(exposure)) => {
+            let amount = accounts.vesting_schedule.take_exposure_to_release();
+            registry.record_release(exposure, amount)?;
+            amount
+        }
+        _ => 0,
+    };
+
+    require!(released > 0 || exposure_released > 0, VaultError::NothingToRelease);
+
+    Ok(())
+}
+
+pub fn settle_vesting(
+    vault: &mut CollateralVault,
+    vault_key: &Pubkey,
+    journal: Option<&AccountLoader<VaultJournal>>,
+    schedule: &mut VestingSchedule,
+    clock: &Clock,
+) -> Result<u64> {
+    let releasable = schedule.take_releasable(clock.unix_timestamp)?;
+    if releasable == 0 {
+        return Ok(0);
+    }
+
+    // only this function takes from the vesting bucket, so everything releasable is still in it
+    let amount = releasable;
+    vault.unlock_from(LockBucket::Vesting, amount)?;
+    record_journal(vault, journal, JournalOp::Unlock, amount, schedule.creator, clock)?;
+
+    emit!(VestingReleased {
+        version: EVENT_VERSION,
+        vault: *vault_key,
+        event_seq: vault.next_event_seq()?,
+        schedule_id: schedule.schedule_id,
+        amount,
+        total_released: schedule.released_amount,
+        timestamp: clock.unix_timestamp,
+    });
+
+    msg!(
+        "Released {} vested from schedule {}. Available: {}",
+        amount,
+        schedule.schedule_id,
+        vault.available_balance
+    );
+
+    Ok(amount)
+}
+
+// every instruction that pays out of available_balance calls this first, so schedules passed
+// in remaining_accounts count towards the balance it checks
+pub fn settle_vesting_schedules<'info>(
+    vault: &mut CollateralVault,
+    vault_key: &Pubkey,
+    journal: Option<&AccountLoader<VaultJournal>>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    for info in remaining_accounts.iter() {
+        require!(info.is_writable, VaultError::InvalidVestingSchedule);
+
+        let mut schedule = Account::<VestingSchedule>::try_from(info)?;
+        require!(schedule.vault == *vault_key, VaultError::InvalidVestingSchedule);
+
+        if schedule.is_fully_released() {
+            continue;
+        }
+
+        settle_vesting(vault, vault_key, journal, &mut schedule, clock)?;
+        schedule.exit(&crate::ID)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CreateVestingSchedule<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = VestingSchedule::LEN,
+        seeds = [b"vesting", vault.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump
+    )]
+    pub vesting_schedule: Account<'info, VestingSchedule>,
+
+    // the program accounts are only needed when the creator is not the vault owner
+    /// CHECK: Only used to derive program_registry, which exists only for registered programs
+    pub caller_program: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [b"program_registry", caller_program.as_ref().ok_or(VaultError::UnauthorizedProgram)?.key.as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Option<Account<'info, ProgramRegistry>>,
+
+    #[account(
+        constraint = Some(caller_authority.key()) == program_registry.as_ref().map(|r| r.caller_authority())
+            @ VaultError::UnauthorizedProgram
+    )]
+    pub caller_authority: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"program_exposure",
+            caller_program.as_ref().ok_or(VaultError::UnauthorizedProgram)?.key.as_ref(),
+            vault.key().as_ref(),
+        ],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Option<Account<'info, ProgramExposure>>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct ReleaseVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"vesting", vault.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump = vesting_schedule.bump,
+    )]
+    pub vesting_schedule: Account<'info, VestingSchedule>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
+
+    // only for schedules a program created, to hand back its exposure for what has vested
+    #[account(
+        mut,
+        seeds = [b"program_registry", vesting_schedule.caller_program.as_ref()],
+        bump = program_registry.bump,
+    )]
+    pub program_registry: Option<Account<'info, ProgramRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"program_exposure", vesting_schedule.caller_program.as_ref(), vault.key().as_ref()],
+        bump = program_exposure.bump,
+    )]
+    pub program_exposure: Option<Account<'info, ProgramExposure>>,
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{CollateralVault, RateLimitView, VaultSummary, WithdrawableView, VIEW_VERSION};
+
+// these only read the vault and hand the result back through set_return_data
+// (anchor does that for the returned value), so they work over CPI and simulateTransaction
+
+pub fn get_vault_summary(ctx: Context<ViewVault>) -> Result<VaultSummary> {
+    let vault_key = ctx.accounts.vault.key();
+    let vault = ctx.accounts.vault.load()?;
+
+    Ok(VaultSummary {
+        version: VIEW_VERSION,
+        vault: vault_key,
+        owner: vault.owner,
+        total_balance: vault.total_balance,
+        locked_balance: vault.locked_balance,
+        available_balance: vault.available_balance,
+        total_deposited: vault.total_deposited,
+        total_withdrawn: vault.total_withdrawn,
+        total_yield_earned: vault.total_yield_earned,
+        frozen: vault.is_frozen(),
+        emergency_mode: vault.emergency_mode != 0,
+        whitelist_enabled: vault.whitelist_enabled != 0,
+        withdrawal_timelock: vault.withdrawal_timelock,
+        has_pending_withdrawal: vault.pending().is_some(),
+        created_at: vault.created_at,
+        event_seq: vault.event_seq,
+    })
+}
+
+pub fn get_withdrawable(ctx: Context<ViewVault>) -> Result<WithdrawableView> {
+    let vault_key = ctx.accounts.vault.key();
+    let vault = ctx.accounts.vault.load()?;
+    let clock = Clock::get()?;
+
+    let withdrawable = if vault.is_frozen() { 0 } else { vault.available_balance };
+    let withdrawable_rate_limited = withdrawable.min(vault.remaining_rate_limit(&clock));
+
+    let (pending_amount, pending_executable) = match vault.pending() {
+        Some(pending) => (
+            pending.amount,
+            !vault.is_frozen()
+                && (vault.emergency_mode != 0 || clock.unix_timestamp >= pending.executable_at),
+        ),
+        None => (0, false),
+    };
+
+    Ok(WithdrawableView {
+        version: VIEW_VERSION,
+        vault: vault_key,
+        withdrawable,
+        withdrawable_rate_limited,
+        pending_amount,
+        pending_executable,
+        frozen: vault.is_frozen(),
+    })
+}
+
+pub fn get_remaining_rate_limit(ctx: Context<ViewVault>) -> Result<RateLimitView> {
+    let vault_key = ctx.accounts.vault.key();
+    let vault = ctx.accounts.vault.load()?;
+    let clock = Clock::get()?;
+
+    let limited = vault.has_rate_limit();
+    let window_end = vault.rate_limit_window_start.saturating_add(vault.rate_limit_window);
+    let window_open = limited && clock.unix_timestamp < window_end;
+
+    Ok(RateLimitView {
+        version: VIEW_VERSION,
+        vault: vault_key,
+        limited,
+        limit_amount: if limited { vault.rate_limit_amount } else { u64::MAX },
+        window: vault.rate_limit_window,
+        withdrawn_in_window: if window_open { vault.rate_limit_withdrawn } else { 0 },
+        remaining: vault.remaining_rate_limit(&clock),
+        resets_at: if window_open { window_end } else { 0 },
+    })
+}
+
+#[derive(Accounts)]
+pub struct ViewVault<'info> {
+    #[account(
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+}
@@ -1,33 +1,52 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-use crate::state::{CollateralVault, WithdrawEvent};
+use crate::state::{CollateralVault, JournalOp, ProtocolConfig, VaultJournal, WithdrawEvent, EVENT_VERSION};
 use crate::errors::VaultError;
-
-pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+use crate::instructions::{record_journal, settle_vesting_schedules};
+
+// any vesting schedules for this vault can be passed in remaining_accounts
+// so whatever has vested is available for this withdrawal
+pub fn withdraw<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+    amount: u64,
+) -> Result<()> {
     require!(amount > 0, VaultError::InvalidAmount);
 
-    let vault = &mut ctx.accounts.vault;
+    let clock = Clock::get()?;
 
-    // this prevents users from pulling funds that are backing active positions
-    require!(
-        vault.available_balance >= amount,
-        VaultError::InsufficientAvailableBalance
-    );
+    let bump = {
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        let vault_key = ctx.accounts.vault.key();
+        settle_vesting_schedules(
+            &mut vault,
+            &vault_key,
+            ctx.accounts.journal.as_ref(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+
+        // this prevents users from pulling funds that are backing active positions
+        require!(
+            vault.available_balance >= amount,
+            VaultError::InsufficientAvailableBalance
+        );
+        vault.bump
+    };
 
     // need PDA seeds to sign the CPI since vault owns the token account
     let owner_key = ctx.accounts.owner.key();
     let seeds = &[
         b"vault",
         owner_key.as_ref(),
-        &[vault.bump],
+        &[bump],
     ];
     let signer = &[&seeds[..]];
 
     let cpi_accounts = Transfer {
         from: ctx.accounts.vault_token_account.to_account_info(),
         to: ctx.accounts.user_token_account.to_account_info(),
-        authority: vault.to_account_info(),
+        authority: ctx.accounts.vault.to_account_info(),
     };
     
     let cpi_program = ctx.accounts.token_program.to_account_info();
@@ -35,10 +54,22 @@ pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
     
     token::transfer(cpi_ctx, amount)?;
 
+    let mut vault = ctx.accounts.vault.load_mut()?;
     vault.sub_withdrawal(amount)?;
+    ctx.accounts.protocol_config.record_outflow(amount);
+    record_journal(
+        &vault,
+        ctx.accounts.journal.as_ref(),
+        JournalOp::Withdraw,
+        amount,
+        ctx.accounts.user_token_account.owner,
+        &clock,
+    )?;
 
-    let clock = Clock::get()?;
     emit!(WithdrawEvent {
+        version: EVENT_VERSION,
+        vault: ctx.accounts.vault.key(),
+        event_seq: vault.next_event_seq()?,
         user: ctx.accounts.owner.key(),
         amount,
         new_balance: vault.total_balance,
@@ -58,23 +89,38 @@ pub struct Withdraw<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = vault_token_account.key() == vault.token_account @ VaultError::InvalidTokenAccount
+        constraint = vault_token_account.key() == vault.load()?.token_account @ VaultError::InvalidTokenAccount
     )]
     pub vault_token_account: Account<'info, TokenAccount>,
 
     /// CHECK: Verified via has_one constraint
     pub owner: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
     pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
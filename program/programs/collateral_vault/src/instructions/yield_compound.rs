@@ -1,13 +1,17 @@
 use anchor_lang::prelude::*;
 
-use crate::state::{CollateralVault, YieldEarned};
+use crate::state::{
+    CollateralVault, JournalOp, ProtocolConfig, VaultJournal, YieldConfigured, YieldEarned, EVENT_VERSION,
+};
 use crate::errors::VaultError;
+use crate::instructions::record_journal;
 
 pub fn compound_yield(ctx: Context<CompoundYield>) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
-    require!(vault.yield_enabled, VaultError::YieldNotEnabled);
+    require!(vault.yield_enabled != 0, VaultError::YieldNotEnabled);
 
     let time_elapsed = clock.unix_timestamp - vault.last_yield_compound;
     let annual_rate = 10000000;
@@ -23,11 +27,22 @@ pub fn compound_yield(ctx: Context<CompoundYield>) -> Result<()> {
 
         if yield_amount > 0 {
             vault.add_yield(yield_amount)?;
+            ctx.accounts.protocol_config.record_inflow(yield_amount)?;
+            record_journal(
+                &vault,
+                ctx.accounts.journal.as_ref(),
+                JournalOp::Yield,
+                yield_amount,
+                Pubkey::default(),
+                &clock,
+            )?;
             vault.last_yield_compound = clock.unix_timestamp;
             vault.last_update = clock.unix_timestamp;
 
             emit!(YieldEarned {
-                vault: vault.key(),
+                version: EVENT_VERSION,
+                vault: vault_key,
+                event_seq: vault.next_event_seq()?,
                 amount: yield_amount,
                 total_yield: vault.total_yield_earned,
                 timestamp: clock.unix_timestamp,
@@ -41,10 +56,11 @@ pub fn compound_yield(ctx: Context<CompoundYield>) -> Result<()> {
 }
 
 pub fn auto_compound(ctx: Context<AutoCompound>) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
-    require!(vault.yield_enabled, VaultError::YieldNotEnabled);
+    require!(vault.yield_enabled != 0, VaultError::YieldNotEnabled);
 
     let min_compound_interval = 10i64;
     let time_since_last = clock.unix_timestamp - vault.last_yield_compound;
@@ -67,11 +83,22 @@ pub fn auto_compound(ctx: Context<AutoCompound>) -> Result<()> {
 
         if yield_amount > 0 {
             vault.add_yield(yield_amount)?;
+            ctx.accounts.protocol_config.record_inflow(yield_amount)?;
+            record_journal(
+                &vault,
+                ctx.accounts.journal.as_ref(),
+                JournalOp::Yield,
+                yield_amount,
+                Pubkey::default(),
+                &clock,
+            )?;
             vault.last_yield_compound = clock.unix_timestamp;
             vault.last_update = clock.unix_timestamp;
 
             emit!(YieldEarned {
-                vault: vault.key(),
+                version: EVENT_VERSION,
+                vault: vault_key,
+                event_seq: vault.next_event_seq()?,
                 amount: yield_amount,
                 total_yield: vault.total_yield_earned,
                 timestamp: clock.unix_timestamp,
@@ -85,10 +112,11 @@ pub fn auto_compound(ctx: Context<AutoCompound>) -> Result<()> {
 }
 
 pub fn configure_yield(ctx: Context<ConfigureYield>, enabled: bool) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
+    let vault_key = ctx.accounts.vault.key();
+    let mut vault = ctx.accounts.vault.load_mut()?;
     let clock = Clock::get()?;
 
-    vault.yield_enabled = enabled;
+    vault.yield_enabled = enabled as u8;
     
     if enabled {
         vault.last_yield_compound = clock.unix_timestamp;
@@ -97,6 +125,14 @@ pub fn configure_yield(ctx: Context<ConfigureYield>, enabled: bool) -> Result<()
         msg!("Yield generation disabled for vault");
     }
 
+    emit!(YieldConfigured {
+        version: EVENT_VERSION,
+        vault: vault_key,
+        event_seq: vault.next_event_seq()?,
+        enabled,
+        timestamp: clock.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -108,13 +144,28 @@ pub struct CompoundYield<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
+    )]
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
 #[derive(Accounts)]
@@ -124,10 +175,24 @@ pub struct AutoCompound<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", vault.owner.as_ref()],
-        bump = vault.bump,
+        seeds = [b"vault", vault.load()?.owner.as_ref()],
+        bump = vault.load()?.bump,
+    )]
+    pub vault: AccountLoader<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"journal", vault.key().as_ref()],
+        bump = journal.load()?.bump,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub journal: Option<AccountLoader<'info, VaultJournal>>,
 }
 
 #[derive(Accounts)]
@@ -138,10 +203,11 @@ pub struct ConfigureYield<'info> {
     #[account(
         mut,
         seeds = [b"vault", owner.key().as_ref()],
-        bump = vault.bump,
+        bump = vault.load()?.bump,
         has_one = owner @ VaultError::InvalidAuthority,
+        constraint = !vault.load()?.is_frozen() @ VaultError::VaultFrozen,
     )]
-    pub vault: Account<'info, CollateralVault>,
+    pub vault: AccountLoader<'info, CollateralVault>,
 
     /// CHECK: Verified through has_one constraint
     pub owner: UncheckedAccount<'info>,
@@ -5,6 +5,7 @@ pub mod instructions;
 pub mod state;
 
 use instructions::*;
+use state::{RateLimitView, VaultSummary, VestingKind, WithdrawableView, WithdrawalPermit};
 // devnet program public key, so fine for committing to github
 declare_id!("J4AH5hKsnigMxdcGoLAffr7XxKVLHw22y6RG3qEsi9Dd");
 
@@ -16,11 +17,23 @@ pub mod collateral_vault {
         instructions::initialize_vault(ctx)
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        instructions::deposit(ctx, amount)
+    pub fn deposit(ctx: Context<Deposit>, amount: u64, reference: Option<[u8; 32]>) -> Result<()> {
+        instructions::deposit(ctx, amount, reference)
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+    pub fn deposit_for(
+        ctx: Context<DepositFor>,
+        amount: u64,
+        memo: Option<String>,
+        reference: Option<[u8; 32]>,
+    ) -> Result<()> {
+        instructions::deposit_for(ctx, amount, memo, reference)
+    }
+
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         instructions::withdraw(ctx, amount)
     }
 
@@ -32,6 +45,52 @@ pub mod collateral_vault {
         instructions::unlock_collateral(ctx, amount)
     }
 
+    pub fn lock_collateral_with_expiry(
+        ctx: Context<LockCollateralWithExpiry>,
+        lock_id: u64,
+        amount: u64,
+        expires_at: Option<i64>,
+        reason_code: u8,
+    ) -> Result<()> {
+        instructions::lock_collateral_with_expiry(ctx, lock_id, amount, expires_at, reason_code)
+    }
+
+    pub fn release_collateral_lock(
+        ctx: Context<ReleaseCollateralLock>,
+        lock_id: u64,
+    ) -> Result<()> {
+        instructions::release_collateral_lock(ctx, lock_id)
+    }
+
+    pub fn reclaim_expired_lock(ctx: Context<ReclaimExpiredLock>, lock_id: u64) -> Result<()> {
+        instructions::reclaim_expired_lock(ctx, lock_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_vesting_schedule(
+        ctx: Context<CreateVestingSchedule>,
+        schedule_id: u64,
+        kind: VestingKind,
+        amount: u64,
+        start_ts: i64,
+        cliff_ts: i64,
+        end_ts: i64,
+    ) -> Result<()> {
+        instructions::create_vesting_schedule(
+            ctx,
+            schedule_id,
+            kind,
+            amount,
+            start_ts,
+            cliff_ts,
+            end_ts,
+        )
+    }
+
+    pub fn release_vested(ctx: Context<ReleaseVested>, schedule_id: u64) -> Result<()> {
+        instructions::release_vested(ctx, schedule_id)
+    }
+
     pub fn transfer_collateral(
         ctx: Context<TransferCollateral>,
         amount: u64,
@@ -39,6 +98,24 @@ pub mod collateral_vault {
         instructions::transfer_collateral(ctx, amount)
     }
 
+    pub fn settle_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleBatch<'info>>,
+        legs: Vec<SettlementLeg>,
+    ) -> Result<()> {
+        instructions::settle_batch(ctx, legs)
+    }
+
+    pub fn transfer_to_vault<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TransferToVault<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::transfer_to_vault(ctx, amount)
+    }
+
+    pub fn liquidate(ctx: Context<Liquidate>, amount: u64) -> Result<()> {
+        instructions::liquidate(ctx, amount)
+    }
+
     pub fn initialize_authority(
         ctx: Context<InitializeAuthority>,
         authorized_programs: Vec<Pubkey>,
@@ -60,14 +137,93 @@ pub mod collateral_vault {
         instructions::remove_authorized_program(ctx, program)
     }
 
+    pub fn register_program(ctx: Context<RegisterProgram>, program: Pubkey) -> Result<()> {
+        instructions::register_program(ctx, program)
+    }
+
+    pub fn migrate_authorized_program(
+        ctx: Context<MigrateAuthorizedProgram>,
+        program: Pubkey,
+    ) -> Result<()> {
+        instructions::migrate_authorized_program(ctx, program)
+    }
+
+    pub fn open_program_exposure(ctx: Context<OpenProgramExposure>) -> Result<()> {
+        instructions::open_program_exposure(ctx)
+    }
+
+    pub fn deregister_program(ctx: Context<DeregisterProgram>, program: Pubkey) -> Result<()> {
+        instructions::deregister_program(ctx, program)
+    }
+
+    pub fn configure_program_limits(
+        ctx: Context<ConfigureProgramLimits>,
+        program: Pubkey,
+        enabled: bool,
+        permissions: u8,
+        max_lock_per_vault: u64,
+        global_exposure_cap: u64,
+    ) -> Result<()> {
+        instructions::configure_program_limits(
+            ctx,
+            program,
+            enabled,
+            permissions,
+            max_lock_per_vault,
+            global_exposure_cap,
+        )
+    }
+
+    pub fn initialize_protocol_config(
+        ctx: Context<InitializeProtocolConfig>,
+        max_vault_balance: u64,
+        global_deposit_cap: u64,
+    ) -> Result<()> {
+        instructions::initialize_protocol_config(ctx, max_vault_balance, global_deposit_cap)
+    }
+
+    pub fn update_deposit_caps(
+        ctx: Context<UpdateProtocolConfig>,
+        max_vault_balance: u64,
+        global_deposit_cap: u64,
+    ) -> Result<()> {
+        instructions::update_deposit_caps(ctx, max_vault_balance, global_deposit_cap)
+    }
+
+    pub fn configure_liquidation(
+        ctx: Context<UpdateProtocolConfig>,
+        treasury: Pubkey,
+        liquidation_bonus_bps: u16,
+        protocol_fee_bps: u16,
+        close_factor_bps: u16,
+    ) -> Result<()> {
+        instructions::configure_liquidation(
+            ctx,
+            treasury,
+            liquidation_bonus_bps,
+            protocol_fee_bps,
+            close_factor_bps,
+        )
+    }
+
     pub fn batch_deposit(ctx: Context<BatchDeposit>, amounts: Vec<u64>) -> Result<()> {
         instructions::batch_deposit(ctx, amounts)
     }
 
-    pub fn batch_withdraw(ctx: Context<BatchWithdraw>, amounts: Vec<u64>) -> Result<()> {
+    pub fn batch_withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchWithdraw<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
         instructions::batch_withdraw(ctx, amounts)
     }
 
+    pub fn batch_execute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchExecute<'info>>,
+        operations: Vec<BatchOperation>,
+    ) -> Result<()> {
+        instructions::batch_execute(ctx, operations)
+    }
+
     pub fn compound_yield(ctx: Context<CompoundYield>) -> Result<()> {
         instructions::compound_yield(ctx)
     }
@@ -124,8 +280,8 @@ pub mod collateral_vault {
         instructions::toggle_emergency_mode(ctx, enabled)
     }
 
-    pub fn request_withdrawal(
-        ctx: Context<RequestWithdrawal>,
+    pub fn request_withdrawal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RequestWithdrawal<'info>>,
         amount: u64,
         recipient: Pubkey,
     ) -> Result<()> {
@@ -136,8 +292,250 @@ pub mod collateral_vault {
         instructions::cancel_withdrawal(ctx)
     }
 
-    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+    pub fn execute_withdrawal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteWithdrawal<'info>>,
+    ) -> Result<()> {
         instructions::execute_withdrawal(ctx)
     }
+
+    pub fn sync_balance(ctx: Context<SyncBalance>) -> Result<()> {
+        instructions::sync_balance(ctx)
+    }
+
+    pub fn grant_allowance(
+        ctx: Context<GrantAllowance>,
+        spender: Pubkey,
+        amount_per_period: u64,
+        period: i64,
+    ) -> Result<()> {
+        instructions::grant_allowance(ctx, spender, amount_per_period, period)
+    }
+
+    pub fn revoke_allowance(ctx: Context<RevokeAllowance>, spender: Pubkey) -> Result<()> {
+        instructions::revoke_allowance(ctx, spender)
+    }
+
+    pub fn pull_allowance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PullAllowance<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::pull_allowance(ctx, amount)
+    }
+
+    pub fn create_hashlock_escrow(
+        ctx: Context<CreateHashlockEscrow>,
+        escrow_id: u64,
+        amount: u64,
+        hashlock: [u8; 32],
+        deadline: i64,
+    ) -> Result<()> {
+        instructions::create_hashlock_escrow(ctx, escrow_id, amount, hashlock, deadline)
+    }
+
+    pub fn claim_hashlock_escrow(
+        ctx: Context<ClaimHashlockEscrow>,
+        escrow_id: u64,
+        preimage: Vec<u8>,
+    ) -> Result<()> {
+        instructions::claim_hashlock_escrow(ctx, escrow_id, preimage)
+    }
+
+    pub fn refund_hashlock_escrow(ctx: Context<RefundHashlockEscrow>, escrow_id: u64) -> Result<()> {
+        instructions::refund_hashlock_escrow(ctx, escrow_id)
+    }
+
+    pub fn create_sub_account(
+        ctx: Context<CreateSubAccount>,
+        sub_account_id: u16,
+        name: String,
+    ) -> Result<()> {
+        instructions::create_sub_account(ctx, sub_account_id, name)
+    }
+
+    pub fn fund_sub_account(ctx: Context<ManageSubAccount>, sub_account_id: u16, amount: u64) -> Result<()> {
+        instructions::fund_sub_account(ctx, sub_account_id, amount)
+    }
+
+    pub fn defund_sub_account(ctx: Context<ManageSubAccount>, sub_account_id: u16, amount: u64) -> Result<()> {
+        instructions::defund_sub_account(ctx, sub_account_id, amount)
+    }
+
+    pub fn close_sub_account(ctx: Context<CloseSubAccount>, sub_account_id: u16) -> Result<()> {
+        instructions::close_sub_account(ctx, sub_account_id)
+    }
+
+    pub fn lock_sub_account(ctx: Context<SubAccountCollateral>, sub_account_id: u16, amount: u64) -> Result<()> {
+        instructions::lock_sub_account(ctx, sub_account_id, amount)
+    }
+
+    pub fn unlock_sub_account(ctx: Context<SubAccountCollateral>, sub_account_id: u16, amount: u64) -> Result<()> {
+        instructions::unlock_sub_account(ctx, sub_account_id, amount)
+    }
+
+    pub fn transfer_sub_account_collateral(
+        ctx: Context<TransferSubAccountCollateral>,
+        from_sub_account_id: u16,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::transfer_sub_account_collateral(ctx, from_sub_account_id, amount)
+    }
+
+    pub fn initialize_share_pool(ctx: Context<InitializeSharePool>, pool_id: u64) -> Result<()> {
+        instructions::initialize_share_pool(ctx, pool_id)
+    }
+
+    pub fn open_pool_position(ctx: Context<OpenPoolPosition>) -> Result<()> {
+        instructions::open_pool_position(ctx)
+    }
+
+    pub fn pool_deposit(ctx: Context<PoolDeposit>, assets: u64, min_shares: u64) -> Result<()> {
+        instructions::pool_deposit(ctx, assets, min_shares)
+    }
+
+    pub fn pool_withdraw(ctx: Context<PoolWithdraw>, assets: u64, max_shares: u64) -> Result<()> {
+        instructions::pool_withdraw(ctx, assets, max_shares)
+    }
+
+    pub fn pool_redeem(ctx: Context<PoolWithdraw>, shares: u64, min_assets: u64) -> Result<()> {
+        instructions::pool_redeem(ctx, shares, min_assets)
+    }
+
+    pub fn report_pool_yield(ctx: Context<ReportPoolYield>, amount: u64) -> Result<()> {
+        instructions::report_pool_yield(ctx, amount)
+    }
+
+    pub fn report_pool_loss(ctx: Context<ReportPoolLoss>, amount: u64) -> Result<()> {
+        instructions::report_pool_loss(ctx, amount)
+    }
+
+    pub fn initialize_insurance_fund(ctx: Context<InitializeInsuranceFund>, fee_share_bps: u16) -> Result<()> {
+        instructions::initialize_insurance_fund(ctx, fee_share_bps)
+    }
+
+    pub fn configure_insurance_fund(ctx: Context<ConfigureInsuranceFund>, fee_share_bps: u16) -> Result<()> {
+        instructions::configure_insurance_fund(ctx, fee_share_bps)
+    }
+
+    pub fn contribute_insurance_fund(ctx: Context<ContributeInsuranceFund>, amount: u64) -> Result<()> {
+        instructions::contribute_insurance_fund(ctx, amount)
+    }
+
+    pub fn cover_shortfall(ctx: Context<CoverShortfall>, amount: u64, reason_code: u8) -> Result<()> {
+        instructions::cover_shortfall(ctx, amount, reason_code)
+    }
+
+    pub fn freeze_vault(ctx: Context<FreezeVault>, reason_code: u8) -> Result<()> {
+        instructions::freeze_vault(ctx, reason_code)
+    }
+
+    pub fn unfreeze_vault(ctx: Context<FreezeVault>) -> Result<()> {
+        instructions::unfreeze_vault(ctx)
+    }
+
+    pub fn get_vault_summary(ctx: Context<ViewVault>) -> Result<VaultSummary> {
+        instructions::get_vault_summary(ctx)
+    }
+
+    pub fn get_withdrawable(ctx: Context<ViewVault>) -> Result<WithdrawableView> {
+        instructions::get_withdrawable(ctx)
+    }
+
+    pub fn get_remaining_rate_limit(ctx: Context<ViewVault>) -> Result<RateLimitView> {
+        instructions::get_remaining_rate_limit(ctx)
+    }
+
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        instructions::migrate_vault(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_scheduled_withdrawal(
+        ctx: Context<CreateScheduledWithdrawal>,
+        schedule_id: u64,
+        recipient: Pubkey,
+        amount: u64,
+        interval: i64,
+        first_execution_at: i64,
+        max_executions: u32,
+        keeper_tip: u64,
+    ) -> Result<()> {
+        instructions::create_scheduled_withdrawal(
+            ctx,
+            schedule_id,
+            recipient,
+            amount,
+            interval,
+            first_execution_at,
+            max_executions,
+            keeper_tip,
+        )
+    }
+
+    pub fn cancel_scheduled_withdrawal(
+        ctx: Context<CancelScheduledWithdrawal>,
+        schedule_id: u64,
+    ) -> Result<()> {
+        instructions::cancel_scheduled_withdrawal(ctx, schedule_id)
+    }
+
+    pub fn execute_scheduled_withdrawal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteScheduledWithdrawal<'info>>,
+        schedule_id: u64,
+    ) -> Result<()> {
+        instructions::execute_scheduled_withdrawal(ctx, schedule_id)
+    }
+
+    pub fn initialize_journal(ctx: Context<InitializeJournal>) -> Result<()> {
+        instructions::initialize_journal(ctx)
+    }
+
+    pub fn close_journal(ctx: Context<CloseJournal>) -> Result<()> {
+        instructions::close_journal(ctx)
+    }
+
+    pub fn withdraw_with_permit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawWithPermit<'info>>,
+        permit: WithdrawalPermit,
+    ) -> Result<()> {
+        instructions::withdraw_with_permit(ctx, permit)
+    }
+
+    pub fn create_session_key(
+        ctx: Context<CreateSessionKey>,
+        session_key: Pubkey,
+        scope: u8,
+        amount_cap: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        instructions::create_session_key(ctx, session_key, scope, amount_cap, expires_at)
+    }
+
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>, session_key: Pubkey) -> Result<()> {
+        instructions::revoke_session_key(ctx, session_key)
+    }
+
+    pub fn session_deposit(
+        ctx: Context<SessionDeposit>,
+        amount: u64,
+        reference: Option<[u8; 32]>,
+    ) -> Result<()> {
+        instructions::session_deposit(ctx, amount, reference)
+    }
+
+    pub fn session_fund_sub_account(
+        ctx: Context<SessionManageSubAccount>,
+        sub_account_id: u16,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::session_fund_sub_account(ctx, sub_account_id, amount)
+    }
+
+    pub fn session_defund_sub_account(
+        ctx: Context<SessionManageSubAccount>,
+        sub_account_id: u16,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::session_defund_sub_account(ctx, sub_account_id, amount)
+    }
 }
 
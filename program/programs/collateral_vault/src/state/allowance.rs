@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+
+// recurring pull permission an owner grants to one spender (subscription program, biller, ...)
+// the period counter works like rate_limit_withdrawn on the vault, but per spender
+#[account]
+pub struct Allowance {
+    pub vault: Pubkey,
+    pub spender: Pubkey,
+    pub amount_per_period: u64,
+    pub period: i64,
+    pub period_start: i64,
+    pub pulled_in_period: u64,
+    pub total_pulled: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Allowance {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    pub fn check_and_update(&mut self, amount: u64, clock: &Clock) -> Result<()> {
+        // periods are anchored to the grant time so they don't drift with pull timing
+        let period_end = self.period_start
+            .checked_add(self.period)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        if clock.unix_timestamp >= period_end {
+            let elapsed_periods = (clock.unix_timestamp - self.period_start) / self.period;
+            self.period_start = elapsed_periods
+                .checked_mul(self.period)
+                .and_then(|elapsed| self.period_start.checked_add(elapsed))
+                .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+            self.pulled_in_period = 0;
+        }
+
+        let new_total = self.pulled_in_period
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        require!(
+            new_total <= self.amount_per_period,
+            crate::errors::VaultError::AllowanceExceeded
+        );
+
+        self.pulled_in_period = new_total;
+        self.total_pulled = self.total_pulled
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct AllowanceGranted {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub spender: Pubkey,
+    pub amount_per_period: u64,
+    pub period: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AllowanceRevoked {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub spender: Pubkey,
+    pub total_pulled: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AllowancePulled {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub spender: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub remaining_in_period: u64,
+    pub period_start: i64,
+    pub timestamp: i64,
+}
@@ -1,5 +1,147 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::VaultError;
+
+// a registered program proves it's the one calling by signing (invoke_signed) for the PDA it
+// derives from this seed and its own program id, nobody else can produce that signature
+pub const CALLER_AUTHORITY_SEED: &[u8] = b"vault_caller";
+
+// per-integration limits, so one compromised program can't drain every vault
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuthorizedProgram {
+    pub program_id: Pubkey,
+    pub enabled: bool,
+    pub permissions: u8,
+    // cap on what this program may have locked in any one vault, u64::MAX = no cap
+    pub max_lock_per_vault: u64,
+    // cap on everything this program currently has locked across all vaults
+    pub global_exposure_cap: u64,
+    pub current_exposure: u64,
+}
+
+impl AuthorizedProgram {
+    pub const LEN: usize = 32 + 1 + 1 + 8 + 8 + 8;
+
+    pub const PERMISSION_LOCK: u8 = 1 << 0;
+    pub const PERMISSION_UNLOCK: u8 = 1 << 1;
+    pub const PERMISSION_TRANSFER: u8 = 1 << 2;
+    pub const PERMISSION_ALL: u8 =
+        Self::PERMISSION_LOCK | Self::PERMISSION_UNLOCK | Self::PERMISSION_TRANSFER;
+
+    // what register_program starts a program with, which matches the old behaviour
+    // of an unrestricted list entry
+    pub fn unrestricted(program_id: Pubkey) -> Self {
+        Self {
+            program_id,
+            enabled: true,
+            permissions: Self::PERMISSION_ALL,
+            max_lock_per_vault: u64::MAX,
+            global_exposure_cap: u64::MAX,
+            current_exposure: 0,
+        }
+    }
+
+    pub fn has_permission(&self, permission: u8) -> bool {
+        self.enabled && self.permissions & permission == permission
+    }
+
+    // vault_exposure_after is this program's own locked amount in the vault including this
+    // lock, locks by other programs in the same vault don't count against it
+    pub fn record_lock(&mut self, amount: u64, vault_exposure_after: u64) -> Result<()> {
+        require!(
+            vault_exposure_after <= self.max_lock_per_vault,
+            VaultError::ProgramExposureCapExceeded
+        );
+
+        let exposure = self.current_exposure
+            .checked_add(amount)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+        require!(
+            exposure <= self.global_exposure_cap,
+            VaultError::ProgramExposureCapExceeded
+        );
+
+        self.current_exposure = exposure;
+        Ok(())
+    }
+
+    // saturating because locks placed before limits existed were never counted
+    pub fn record_release(&mut self, amount: u64) {
+        self.current_exposure = self.current_exposure.saturating_sub(amount);
+    }
+}
+
+// one account per integration, seeded by its program id, so authorization is an account
+// derivation instead of a scan over a list and there's no cap on how many can be registered
+#[account]
+pub struct ProgramRegistry {
+    pub limits: AuthorizedProgram,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl ProgramRegistry {
+    pub const LEN: usize = 8 + AuthorizedProgram::LEN + 8 + 1;
+
+    pub fn check_permission(&self, permission: u8) -> Result<()> {
+        require!(self.limits.enabled, VaultError::UnauthorizedProgram);
+        require!(
+            self.limits.has_permission(permission),
+            VaultError::ProgramPermissionDenied
+        );
+        Ok(())
+    }
+
+    // permission check plus exposure bookkeeping for the program doing the lock, every path
+    // that locks on behalf of a program goes through here
+    pub fn record_lock(&mut self, exposure: &mut ProgramExposure, amount: u64) -> Result<()> {
+        self.check_permission(AuthorizedProgram::PERMISSION_LOCK)?;
+
+        let vault_exposure_after = exposure.locked
+            .checked_add(amount)
+            .ok_or(error!(VaultError::NumericalOverflow))?;
+        self.limits.record_lock(amount, vault_exposure_after)?;
+
+        exposure.locked = vault_exposure_after;
+        Ok(())
+    }
+
+    pub fn record_release(&mut self, exposure: &mut ProgramExposure, amount: u64) -> Result<()> {
+        exposure.release(amount)?;
+        self.limits.record_release(amount);
+        Ok(())
+    }
+
+    pub fn caller_authority(&self) -> Pubkey {
+        Pubkey::find_program_address(&[CALLER_AUTHORITY_SEED], &self.limits.program_id).0
+    }
+}
+
+// what one registered program currently has locked in one vault, which is what
+// max_lock_per_vault caps. opened once per program and vault with open_program_exposure
+#[account]
+pub struct ProgramExposure {
+    pub program_id: Pubkey,
+    pub vault: Pubkey,
+    pub locked: u64,
+    pub bump: u8,
+}
+
+impl ProgramExposure {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+
+    // checked, a program can only hand back what it locked in this vault itself
+    pub fn release(&mut self, amount: u64) -> Result<()> {
+        self.locked = self.locked
+            .checked_sub(amount)
+            .ok_or(error!(VaultError::ProgramExposureExceeded))?;
+        Ok(())
+    }
+}
+
+// holds the admin for the program registry. authorized_programs keeps its original layout so
+// deployed accounts still load, but nothing reads it for authorization anymore: entries sit
+// there until migrate_authorized_program moves them into ProgramRegistry accounts
 #[account]
 pub struct VaultAuthority {
     pub authorized_programs: Vec<Pubkey>,
@@ -9,7 +151,7 @@ pub struct VaultAuthority {
 
 impl VaultAuthority {
     pub const MAX_AUTHORIZED: usize = 10;
-    
+
     pub const LEN: usize = 8 + 4 + (32 * Self::MAX_AUTHORIZED) + 32 + 1;
 
     pub fn is_authorized(&self, program: &Pubkey) -> bool {
@@ -19,14 +161,14 @@ impl VaultAuthority {
     pub fn add_program(&mut self, program: Pubkey) -> Result<()> {
         require!(
             !self.is_authorized(&program),
-            crate::errors::VaultError::ProgramAlreadyAuthorized
+            VaultError::ProgramAlreadyAuthorized
         );
-        
+
         require!(
             self.authorized_programs.len() < Self::MAX_AUTHORIZED,
-            crate::errors::VaultError::MaxAuthorizedProgramsReached
+            VaultError::MaxAuthorizedProgramsReached
         );
-        
+
         self.authorized_programs.push(program);
         Ok(())
     }
@@ -35,10 +177,49 @@ impl VaultAuthority {
         let pos = self.authorized_programs
             .iter()
             .position(|p| p == program)
-            .ok_or(error!(crate::errors::VaultError::ProgramNotAuthorized))?;
-        
+            .ok_or(error!(VaultError::ProgramNotAuthorized))?;
+
         self.authorized_programs.remove(pos);
         Ok(())
     }
 }
 
+#[event]
+pub struct ProgramRegistered {
+    pub version: u8,
+    pub program_id: Pubkey,
+    pub registry: Pubkey,
+    pub migrated: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramExposureOpened {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub program_id: Pubkey,
+    pub exposure: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramDeregistered {
+    pub version: u8,
+    pub program_id: Pubkey,
+    pub registry: Pubkey,
+    pub current_exposure: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramLimitsConfigured {
+    pub version: u8,
+    pub program_id: Pubkey,
+    pub enabled: bool,
+    pub permissions: u8,
+    pub max_lock_per_vault: u64,
+    pub global_exposure_cap: u64,
+    pub current_exposure: u64,
+    pub timestamp: i64,
+}
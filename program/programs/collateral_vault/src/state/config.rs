@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+
+// every deposit, withdrawal, transfer and liquidation path takes this account, so none of them
+// work until it exists. deploy order is initialize_authority, then initialize_protocol_config
+// by the same admin, then vaults
+#[account]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    // u64::MAX means no cap, same convention as rate_limit_amount on the vault
+    pub max_vault_balance: u64,
+    pub global_deposit_cap: u64,
+    // aggregate of every vault's total_balance, kept in sync by the deposit/withdraw/yield paths
+    pub total_value_locked: u64,
+    pub bump: u8,
+
+    // token account that receives the protocol cut of liquidations
+    pub treasury: Pubkey,
+    pub liquidation_bonus_bps: u16,
+    pub protocol_fee_bps: u16,
+    pub close_factor_bps: u16,
+
+    // set once initialize_insurance_fund has run, from then on every liquidation has to pay it
+    pub has_insurance_fund: bool,
+}
+
+impl ProtocolConfig {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 1 + 32 + 2 + 2 + 2 + 1;
+
+    pub const BPS_DENOMINATOR: u64 = 10_000;
+    pub const DEFAULT_LIQUIDATION_BONUS_BPS: u16 = 500;
+    pub const DEFAULT_CLOSE_FACTOR_BPS: u16 = 5_000;
+
+    pub fn check_deposit_caps(&self, vault_balance: u64, amount: u64) -> Result<()> {
+        self.check_vault_cap(vault_balance, amount)?;
+        self.check_global_cap(amount)
+    }
+
+    // share pools aren't a single user's vault, so they only count against the global cap
+    pub fn check_global_cap(&self, amount: u64) -> Result<()> {
+        let new_tvl = self.total_value_locked
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        require!(
+            new_tvl <= self.global_deposit_cap,
+            crate::errors::VaultError::GlobalDepositCapExceeded
+        );
+
+        Ok(())
+    }
+
+    // vault-to-vault moves don't change TVL, so only the per-vault cap applies to them
+    pub fn check_vault_cap(&self, vault_balance: u64, amount: u64) -> Result<()> {
+        let new_vault_balance = vault_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        require!(
+            new_vault_balance <= self.max_vault_balance,
+            crate::errors::VaultError::VaultDepositCapExceeded
+        );
+
+        Ok(())
+    }
+
+    pub fn record_inflow(&mut self, amount: u64) -> Result<()> {
+        self.total_value_locked = self.total_value_locked
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+        Ok(())
+    }
+
+    // saturating because vaults funded before the config existed were never counted
+    pub fn record_outflow(&mut self, amount: u64) {
+        self.total_value_locked = self.total_value_locked.saturating_sub(amount);
+    }
+
+    pub fn apply_bps(amount: u64, bps: u16) -> Result<u64> {
+        let result = (amount as u128)
+            .checked_mul(bps as u128)
+            .and_then(|v| v.checked_div(Self::BPS_DENOMINATOR as u128))
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+        Ok(result as u64)
+    }
+
+    // the bonus part of a seized amount. seized is base * (1 + bonus), so this holds whether
+    // or not the close factor cut the seizure short
+    pub fn bonus_portion(seized: u64, bonus_bps: u16) -> Result<u64> {
+        let result = (seized as u128)
+            .checked_mul(bonus_bps as u128)
+            .and_then(|v| v.checked_div(Self::BPS_DENOMINATOR as u128 + bonus_bps as u128))
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+        Ok(result as u64)
+    }
+}
+
+#[event]
+pub struct LiquidationConfigUpdated {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub liquidation_bonus_bps: u16,
+    pub protocol_fee_bps: u16,
+    pub close_factor_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositCapsUpdated {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub max_vault_balance: u64,
+    pub global_deposit_cap: u64,
+    pub timestamp: i64,
+}
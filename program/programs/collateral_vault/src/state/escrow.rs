@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+// funds stay in the sender's vault as locked balance until the escrow resolves,
+// so nothing moves between token accounts until the preimage shows up
+#[account]
+pub struct HashlockEscrow {
+    pub from_vault: Pubkey,
+    pub to_vault: Pubkey,
+    pub escrow_id: u64,
+    pub amount: u64,
+    // sha256 of the secret the receiver has to reveal
+    pub hashlock: [u8; 32],
+    pub deadline: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl HashlockEscrow {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 32 + 8 + 8 + 1;
+
+    pub fn is_expired(&self, clock: &Clock) -> bool {
+        clock.unix_timestamp >= self.deadline
+    }
+
+    pub fn matches_preimage(&self, preimage: &[u8]) -> bool {
+        solana_sha256_hasher::hash(preimage).to_bytes() == self.hashlock
+    }
+}
+
+#[event]
+pub struct HashlockEscrowCreated {
+    pub version: u8,
+    pub from_vault: Pubkey,
+    pub from_event_seq: u64,
+    pub to_vault: Pubkey,
+    pub escrow_id: u64,
+    pub amount: u64,
+    pub hashlock: [u8; 32],
+    pub deadline: i64,
+    pub timestamp: i64,
+}
+
+// the preimage is published on purpose, for swaps the other leg is unlocked with the same secret
+#[event]
+pub struct HashlockEscrowClaimed {
+    pub version: u8,
+    pub from_vault: Pubkey,
+    pub from_event_seq: u64,
+    pub to_vault: Pubkey,
+    pub to_event_seq: u64,
+    pub escrow_id: u64,
+    pub amount: u64,
+    pub preimage: Vec<u8>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HashlockEscrowRefunded {
+    pub version: u8,
+    pub from_vault: Pubkey,
+    pub from_event_seq: u64,
+    pub escrow_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
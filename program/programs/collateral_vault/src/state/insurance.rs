@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+
+// protocol-wide backstop for bad debt. it gets a cut of every liquidation's protocol fee
+// plus whatever is sent to it directly, and only the protocol admin can draw from it
+#[account]
+pub struct InsuranceFund {
+    pub token_account: Pubkey,
+    // share of the liquidation protocol fee routed here instead of the treasury
+    pub fee_share_bps: u16,
+    pub total_contributed: u64,
+    pub total_paid_out: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl InsuranceFund {
+    pub const LEN: usize = 8 + 32 + 2 + 8 + 8 + 8 + 1;
+
+    pub fn record_contribution(&mut self, amount: u64) -> Result<()> {
+        self.total_contributed = self.total_contributed
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    pub fn record_payout(&mut self, amount: u64) -> Result<()> {
+        self.total_paid_out = self.total_paid_out
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsuranceSource {
+    LiquidationFee,
+    Direct,
+}
+
+#[event]
+pub struct InsuranceFundContribution {
+    pub version: u8,
+    pub source: InsuranceSource,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub fund_balance: u64,
+    pub total_contributed: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ShortfallCovered {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub reason_code: u8,
+    pub fund_balance: u64,
+    pub total_paid_out: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceFundInitialized {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub token_account: Pubkey,
+    pub fee_share_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceFundConfigured {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub fee_share_bps: u16,
+    pub timestamp: i64,
+}
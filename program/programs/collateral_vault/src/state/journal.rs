@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+
+pub const JOURNAL_CAPACITY: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JournalOp {
+    Deposit,
+    Withdraw,
+    Lock,
+    Unlock,
+    ExecuteWithdrawal,
+    ScheduledWithdrawal,
+    PermitWithdrawal,
+    TransferIn,
+    TransferOut,
+    Seize,
+    Donation,
+    Yield,
+}
+
+impl JournalOp {
+    // stored as op + 1 so an unwritten slot (all zeroes) never decodes as an operation
+    pub fn to_u8(self) -> u8 {
+        self as u8 + 1
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(JournalOp::Deposit),
+            2 => Some(JournalOp::Withdraw),
+            3 => Some(JournalOp::Lock),
+            4 => Some(JournalOp::Unlock),
+            5 => Some(JournalOp::ExecuteWithdrawal),
+            6 => Some(JournalOp::ScheduledWithdrawal),
+            7 => Some(JournalOp::PermitWithdrawal),
+            8 => Some(JournalOp::TransferIn),
+            9 => Some(JournalOp::TransferOut),
+            10 => Some(JournalOp::Seize),
+            11 => Some(JournalOp::Donation),
+            12 => Some(JournalOp::Yield),
+            _ => None,
+        }
+    }
+}
+
+#[zero_copy]
+#[derive(Debug, Default)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub slot: u64,
+    pub amount: u64,
+    // whoever was on the other side: depositor, recipient, the other vault or the calling program
+    pub counterparty: Pubkey,
+    pub op: u8,
+    pub _padding: [u8; 7],
+}
+
+// optional ring buffer of the vault's last JOURNAL_CAPACITY operations. logs get pruned by
+// most RPC nodes, this lets anyone rebuild recent history from a single account read
+#[account(zero_copy)]
+#[derive(Debug)]
+pub struct VaultJournal {
+    pub vault: Pubkey,
+    // sequence number the next entry gets, also the total number ever written
+    pub next_seq: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub entries: [JournalEntry; JOURNAL_CAPACITY],
+}
+
+impl VaultJournal {
+    pub const LEN: usize = 8 + std::mem::size_of::<VaultJournal>();
+
+    pub fn record(&mut self, op: JournalOp, amount: u64, counterparty: Pubkey, slot: u64) -> Result<()> {
+        let seq = self.next_seq;
+        self.entries[(seq % JOURNAL_CAPACITY as u64) as usize] = JournalEntry {
+            seq,
+            slot,
+            amount,
+            counterparty,
+            op: op.to_u8(),
+            _padding: [0; 7],
+        };
+
+        self.next_seq = seq
+            .checked_add(1)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    // oldest first. once the buffer has wrapped the oldest entry sits where the next one goes
+    pub fn entries_in_order(&self) -> Vec<JournalEntry> {
+        let count = (self.next_seq as usize).min(JOURNAL_CAPACITY);
+        let start = if self.next_seq as usize > JOURNAL_CAPACITY {
+            (self.next_seq % JOURNAL_CAPACITY as u64) as usize
+        } else {
+            0
+        };
+
+        (0..count)
+            .map(|i| self.entries[(start + i) % JOURNAL_CAPACITY])
+            .collect()
+    }
+}
+
+#[event]
+pub struct JournalInitialized {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub journal: Pubkey,
+    pub capacity: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct JournalClosed {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub journal: Pubkey,
+    // how many entries were ever recorded, the ring only kept the last JOURNAL_CAPACITY
+    pub entries_recorded: u64,
+    pub timestamp: i64,
+}
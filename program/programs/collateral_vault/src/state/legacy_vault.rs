@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{CollateralVault, PendingWithdrawal};
+
+// the borsh layout CollateralVault had before it went zero-copy. nothing reads it except
+// migrate_vault, which decodes an old account with it and rewrites it in the fixed layout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacyCollateralVault {
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub total_balance: u64,
+    pub locked_balance: u64,
+    pub available_balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub created_at: i64,
+    pub bump: u8,
+
+    pub multisig_threshold: u8,
+    pub authorized_signers: Vec<Pubkey>,
+    pub delegated_users: Vec<Pubkey>,
+    pub withdrawal_timelock: i64,
+    pub pending_withdrawal: Option<LegacyPendingWithdrawal>,
+    pub emergency_mode: bool,
+    pub yield_enabled: bool,
+    pub total_yield_earned: u64,
+    pub last_yield_compound: i64,
+    pub whitelist_enabled: bool,
+    pub withdrawal_whitelist: Vec<Pubkey>,
+    pub rate_limit_amount: u64,
+    pub rate_limit_window: i64,
+    pub rate_limit_window_start: i64,
+    pub rate_limit_withdrawn: u64,
+    pub last_update: i64,
+
+    // appended by the freeze feature without resizing existing accounts, so they're only there
+    // when the vecs left enough slack. decode reads them separately instead of through borsh
+    #[borsh_skip]
+    pub frozen: bool,
+    #[borsh_skip]
+    pub freeze_reason: u8,
+    #[borsh_skip]
+    pub frozen_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacyPendingWithdrawal {
+    pub amount: u64,
+    pub requested_at: i64,
+    pub executable_at: i64,
+    pub recipient: Pubkey,
+}
+
+impl LegacyCollateralVault {
+    // sha256("account:CollateralVault")[..8], what anchor derived for the borsh account
+    pub const DISCRIMINATOR: [u8; 8] = [19, 189, 95, 155, 100, 9, 159, 145];
+
+    // bool + reason + timestamp
+    const FREEZE_TAIL_LEN: usize = 1 + 1 + 8;
+
+    // `data` is the account data after the discriminator. a baseline vault with full vecs ends
+    // right after last_update, one that was never frozen has zeroed slack there, both of which
+    // come out as not frozen
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut rest = data;
+        let mut legacy = Self::deserialize(&mut rest)
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+
+        if rest.len() >= Self::FREEZE_TAIL_LEN {
+            let (frozen, freeze_reason, frozen_at) = <(bool, u8, i64)>::deserialize(&mut rest)
+                .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+            legacy.frozen = frozen;
+            legacy.freeze_reason = freeze_reason;
+            legacy.frozen_at = frozen_at;
+        }
+
+        Ok(legacy)
+    }
+
+    pub fn write_into(&self, vault: &mut CollateralVault) -> Result<()> {
+        vault.owner = self.owner;
+        vault.token_account = self.token_account;
+        vault.total_balance = self.total_balance;
+        vault.locked_balance = self.locked_balance;
+        vault.available_balance = self.available_balance;
+        vault.total_deposited = self.total_deposited;
+        vault.total_withdrawn = self.total_withdrawn;
+        vault.created_at = self.created_at;
+        vault.bump = self.bump;
+
+        vault.multisig_threshold = self.multisig_threshold;
+        vault.set_signers(&self.authorized_signers)?;
+        for user in self.delegated_users.iter() {
+            vault.add_delegated_user(*user)?;
+        }
+        vault.withdrawal_timelock = self.withdrawal_timelock;
+        if let Some(pending) = self.pending_withdrawal.as_ref() {
+            vault.pending_withdrawal = PendingWithdrawal {
+                amount: pending.amount,
+                requested_at: pending.requested_at,
+                executable_at: pending.executable_at,
+                recipient: pending.recipient,
+            };
+            vault.has_pending_withdrawal = 1;
+        }
+        vault.emergency_mode = self.emergency_mode as u8;
+        vault.yield_enabled = self.yield_enabled as u8;
+        vault.total_yield_earned = self.total_yield_earned;
+        vault.last_yield_compound = self.last_yield_compound;
+        vault.whitelist_enabled = self.whitelist_enabled as u8;
+        for address in self.withdrawal_whitelist.iter() {
+            vault.add_to_whitelist(*address)?;
+        }
+        vault.rate_limit_amount = self.rate_limit_amount;
+        vault.rate_limit_window = self.rate_limit_window;
+        vault.rate_limit_window_start = self.rate_limit_window_start;
+        vault.rate_limit_withdrawn = self.rate_limit_withdrawn;
+        vault.last_update = self.last_update;
+
+        vault.frozen = self.frozen as u8;
+        vault.freeze_reason = self.freeze_reason;
+        vault.frozen_at = self.frozen_at;
+
+        Ok(())
+    }
+}
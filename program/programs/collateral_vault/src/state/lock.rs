@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+// one record per lock so the vault account layout doesn't have to change
+// lock_id is picked by the calling program, it only needs to be unique per vault
+#[account]
+pub struct LockRecord {
+    pub vault: Pubkey,
+    pub caller_program: Pubkey,
+    pub lock_id: u64,
+    pub amount: u64,
+    // what's still locked under this record, partial unlocks and liquidations take it down
+    pub outstanding: u64,
+    pub reason_code: u8,
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl LockRecord {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1 + (1 + 8) + 8 + 1;
+
+    pub fn take(&mut self, amount: u64) -> Result<()> {
+        self.outstanding = self.outstanding
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientLockedBalance))?;
+        Ok(())
+    }
+
+    pub fn is_expired(&self, clock: &Clock) -> bool {
+        match self.expires_at {
+            Some(expires_at) => clock.unix_timestamp >= expires_at,
+            None => false,
+        }
+    }
+}
+
+#[event]
+pub struct LockRecordCreated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub caller_program: Pubkey,
+    pub lock_id: u64,
+    pub amount: u64,
+    pub reason_code: u8,
+    pub expires_at: Option<i64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockRecordReleased {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub lock_id: u64,
+    pub amount: u64,
+    pub reclaimed_by_owner: bool,
+    pub timestamp: i64,
+}
@@ -1,6 +1,33 @@
 pub mod vault;
 pub mod authority;
+pub mod config;
+pub mod lock;
+pub mod vesting;
+pub mod allowance;
+pub mod escrow;
+pub mod sub_account;
+pub mod pool;
+pub mod insurance;
+pub mod views;
+pub mod legacy_vault;
+pub mod scheduled_withdrawal;
+pub mod journal;
+pub mod permit;
+pub mod session_key;
 
 pub use vault::*;
 pub use authority::*;
-
+pub use config::*;
+pub use lock::*;
+pub use vesting::*;
+pub use allowance::*;
+pub use escrow::*;
+pub use sub_account::*;
+pub use pool::*;
+pub use insurance::*;
+pub use views::*;
+pub use legacy_vault::*;
+pub use scheduled_withdrawal::*;
+pub use journal::*;
+pub use permit::*;
+pub use session_key::*;
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+// prefixed to every permit before signing so the owner's signature can't be replayed
+// as some other message that happens to share the same bytes
+pub const PERMIT_DOMAIN: &[u8] = b"collateral_vault:withdraw_permit:v1";
+
+// signed off chain by the vault owner, then submitted by anyone (a relayer) together with
+// an ed25519 program instruction over `message()`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WithdrawalPermit {
+    pub vault: Pubkey,
+    pub amount: u64,
+    // wallet that has to own the destination token account
+    pub recipient: Pubkey,
+    // has to match the vault's permit_nonce at submission
+    pub nonce: u64,
+    pub expires_at: i64,
+}
+
+impl WithdrawalPermit {
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = PERMIT_DOMAIN.to_vec();
+        message.extend_from_slice(self.vault.as_ref());
+        message.extend_from_slice(&self.amount.to_le_bytes());
+        message.extend_from_slice(self.recipient.as_ref());
+        message.extend_from_slice(&self.nonce.to_le_bytes());
+        message.extend_from_slice(&self.expires_at.to_le_bytes());
+        message
+    }
+}
+
+#[event]
+pub struct WithdrawalPermitUsed {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub relayer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+    pub timestamp: i64,
+}
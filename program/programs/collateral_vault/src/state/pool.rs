@@ -0,0 +1,169 @@
+use anchor_lang::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+// pooled alternative to CollateralVault: depositors hold shares of total_assets instead of a
+// balance of their own, so yield and losses move the share price for everyone at once
+#[account]
+pub struct SharePool {
+    pub admin: Pubkey,
+    pub pool_id: u64,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl SharePool {
+    pub const LEN: usize = 8 + 32 + 8 + 32 + 32 + 8 + 8 + 8 + 1;
+
+    // the +1 on both sides is a virtual share/asset, it keeps the first depositor from
+    // inflating the share price with a donation and rounding later depositors down to zero
+    pub fn convert_to_shares(&self, assets: u64, rounding: Rounding) -> Result<u64> {
+        mul_div(
+            assets,
+            (self.total_shares as u128) + 1,
+            (self.total_assets as u128) + 1,
+            rounding,
+        )
+    }
+
+    pub fn convert_to_assets(&self, shares: u64, rounding: Rounding) -> Result<u64> {
+        mul_div(
+            shares,
+            (self.total_assets as u128) + 1,
+            (self.total_shares as u128) + 1,
+            rounding,
+        )
+    }
+
+    // previews always round in the pool's favour, same as ERC-4626
+    pub fn preview_deposit(&self, assets: u64) -> Result<u64> {
+        self.convert_to_shares(assets, Rounding::Down)
+    }
+
+    pub fn preview_mint(&self, shares: u64) -> Result<u64> {
+        self.convert_to_assets(shares, Rounding::Up)
+    }
+
+    pub fn preview_withdraw(&self, assets: u64) -> Result<u64> {
+        self.convert_to_shares(assets, Rounding::Up)
+    }
+
+    pub fn preview_redeem(&self, shares: u64) -> Result<u64> {
+        self.convert_to_assets(shares, Rounding::Down)
+    }
+
+    pub fn add_liquidity(&mut self, assets: u64, shares: u64) -> Result<()> {
+        self.total_assets = self.total_assets
+            .checked_add(assets)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        self.total_shares = self.total_shares
+            .checked_add(shares)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    pub fn remove_liquidity(&mut self, assets: u64, shares: u64) -> Result<()> {
+        self.total_assets = self.total_assets
+            .checked_sub(assets)
+            .ok_or(error!(crate::errors::VaultError::InsufficientBalance))?;
+
+        self.total_shares = self.total_shares
+            .checked_sub(shares)
+            .ok_or(error!(crate::errors::VaultError::InsufficientShares))?;
+
+        Ok(())
+    }
+}
+
+fn mul_div(value: u64, numerator: u128, denominator: u128, rounding: Rounding) -> Result<u64> {
+    let product = (value as u128)
+        .checked_mul(numerator)
+        .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+    let result = match rounding {
+        Rounding::Down => product / denominator,
+        Rounding::Up => product
+            .checked_add(denominator - 1)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?
+            / denominator,
+    };
+
+    u64::try_from(result).map_err(|_| error!(crate::errors::VaultError::NumericalOverflow))
+}
+
+#[account]
+pub struct PoolPosition {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub bump: u8,
+}
+
+impl PoolPosition {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+#[event]
+pub struct SharePoolInitialized {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub pool_id: u64,
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolPositionOpened {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub position: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolDepositEvent {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub assets: u64,
+    pub shares: u64,
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolWithdrawEvent {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub assets: u64,
+    pub shares: u64,
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolAssetsUpdated {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub is_loss: bool,
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+// recurring payout from a vault to a whitelisted wallet (payroll, rent, ...). the owner signs
+// once when creating it, after that any keeper can crank each run once it's due
+#[account]
+pub struct ScheduledWithdrawal {
+    pub vault: Pubkey,
+    pub schedule_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub interval: i64,
+    pub next_execution_at: i64,
+    // 0 means it keeps running until cancelled
+    pub max_executions: u32,
+    pub executions: u32,
+    // paid from the vault to whoever cranks a run, on top of amount
+    pub keeper_tip: u64,
+    pub total_paid: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl ScheduledWithdrawal {
+    pub const LEN: usize = 8 + 32 + 8 + 32 + 8 + 8 + 8 + 4 + 4 + 8 + 8 + 8 + 1;
+
+    pub fn is_finished(&self) -> bool {
+        self.max_executions > 0 && self.executions >= self.max_executions
+    }
+
+    // runs stay on the original cadence, so a keeper that shows up late can crank the
+    // missed ones back to back instead of the schedule drifting
+    pub fn record_execution(&mut self) -> Result<()> {
+        self.executions = self.executions
+            .checked_add(1)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        self.total_paid = self.total_paid
+            .checked_add(self.amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        self.next_execution_at = self.next_execution_at
+            .checked_add(self.interval)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct ScheduledWithdrawalCreated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub schedule_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub interval: i64,
+    pub first_execution_at: i64,
+    pub max_executions: u32,
+    pub keeper_tip: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ScheduledWithdrawalExecuted {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub schedule_id: u64,
+    pub recipient: Pubkey,
+    pub keeper: Pubkey,
+    pub amount: u64,
+    pub keeper_tip: u64,
+    pub executions: u32,
+    pub next_execution_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ScheduledWithdrawalCancelled {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub schedule_id: u64,
+    pub executions: u32,
+    pub total_paid: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+// what a session key may sign, as a bitmask so one session can cover several operations
+pub const SESSION_SCOPE_DEPOSIT: u8 = 1 << 0;
+// funding a sub-account, which locks vault collateral into that bucket
+pub const SESSION_SCOPE_LOCK: u8 = 1 << 1;
+// defunding a sub-account back to the vault's available balance
+pub const SESSION_SCOPE_UNLOCK: u8 = 1 << 2;
+pub const SESSION_SCOPE_ALL: u8 = SESSION_SCOPE_DEPOSIT | SESSION_SCOPE_LOCK | SESSION_SCOPE_UNLOCK;
+
+// a delegate with an expiry, a scope and a cap. trading frontends keep the key in the browser
+// and sign routine instructions with it instead of prompting the owner's wallet every time.
+// nothing a session key can do moves funds out of the vault
+#[account]
+pub struct SessionKey {
+    pub vault: Pubkey,
+    pub session_key: Pubkey,
+    pub scope: u8,
+    // total across every operation the session signs, not per operation
+    pub amount_cap: u64,
+    pub amount_used: u64,
+    pub expires_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl SessionKey {
+    pub const LEN: usize = 8 + 32 + 32 + 1 + 8 + 8 + 8 + 8 + 1;
+
+    // keeps a leaked browser key from being useful for long
+    pub const MAX_DURATION: i64 = 7 * 24 * 60 * 60;
+
+    pub fn authorize(&mut self, scope: u8, amount: u64, clock: &Clock) -> Result<()> {
+        require!(
+            clock.unix_timestamp < self.expires_at,
+            crate::errors::VaultError::SessionExpired
+        );
+        require!(
+            self.scope & scope == scope,
+            crate::errors::VaultError::SessionScopeNotAllowed
+        );
+
+        let new_total = self.amount_used
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        require!(
+            new_total <= self.amount_cap,
+            crate::errors::VaultError::SessionCapExceeded
+        );
+
+        self.amount_used = new_total;
+        Ok(())
+    }
+}
+
+#[event]
+pub struct SessionKeyCreated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub session_key: Pubkey,
+    pub scope: u8,
+    pub amount_cap: u64,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SessionKeyRevoked {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub session_key: Pubkey,
+    pub amount_used: u64,
+    pub timestamp: i64,
+}
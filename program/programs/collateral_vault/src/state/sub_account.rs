@@ -0,0 +1,127 @@
+use anchor_lang::prelude::*;
+
+// an isolated bucket inside a vault. everything a bucket holds (available and locked) is
+// counted in the parent vault's locked_balance and again in its sub_account_balance, so the
+// vault's own withdraw, unlock and liquidation paths can't reach it
+#[account]
+pub struct SubAccount {
+    pub vault: Pubkey,
+    pub sub_account_id: u16,
+    pub name: String,
+    pub available_balance: u64,
+    pub locked_balance: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl SubAccount {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const LEN: usize = 8 + 32 + 2 + (4 + Self::MAX_NAME_LEN) + 8 + 8 + 8 + 1;
+
+    pub fn total_balance(&self) -> u64 {
+        self.available_balance.saturating_add(self.locked_balance)
+    }
+
+    pub fn fund(&mut self, amount: u64) -> Result<()> {
+        self.available_balance = self.available_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    pub fn defund(&mut self, amount: u64) -> Result<()> {
+        self.available_balance = self.available_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientSubAccountBalance))?;
+
+        Ok(())
+    }
+
+    pub fn lock(&mut self, amount: u64) -> Result<()> {
+        self.available_balance = self.available_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientSubAccountBalance))?;
+
+        self.locked_balance = self.locked_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, amount: u64) -> Result<()> {
+        self.locked_balance = self.locked_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientSubAccountBalance))?;
+
+        self.available_balance = self.available_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    pub fn seize_locked(&mut self, amount: u64) -> Result<()> {
+        self.locked_balance = self.locked_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientSubAccountBalance))?;
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct SubAccountCreated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub sub_account_id: u16,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+// covers fund/defund (owner) and lock/unlock (authorized programs), action says which
+#[event]
+pub struct SubAccountBalanceEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub sub_account_id: u16,
+    pub action: SubAccountAction,
+    pub amount: u64,
+    pub available_balance: u64,
+    pub locked_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubAccountClosed {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub sub_account_id: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubAccountTransferEvent {
+    pub version: u8,
+    pub from_vault: Pubkey,
+    pub from_event_seq: u64,
+    pub from_sub_account_id: u16,
+    pub to_vault: Pubkey,
+    pub to_event_seq: u64,
+    // None when the funds landed in the receiving vault's main balance
+    pub to_sub_account_id: Option<u16>,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubAccountAction {
+    Fund,
+    Defund,
+    Lock,
+    Unlock,
+}
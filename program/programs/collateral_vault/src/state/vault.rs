@@ -1,36 +1,84 @@
 use anchor_lang::prelude::*;
 
-#[account]
+// fixed layout read in place instead of borsh-decoding the whole account on every
+// instruction. every field sits at a stable offset, so off-chain readers can index into the
+// raw data. the vecs became fixed arrays with a count, the bools are u8 (0/1) and the
+// option is a flag plus a zeroed struct. new fields have to be carved out of `_reserved`.
+//
+// the discriminator differs from the old borsh account (same name) so the loader can't
+// mistake one for the other; migrate_vault converts old accounts in place
+#[account(zero_copy, discriminator = b"vault_v2")]
 pub struct CollateralVault {
-    pub owner: Pubkey,
-    pub token_account: Pubkey,
     pub total_balance: u64,
     pub locked_balance: u64,
     pub available_balance: u64,
     pub total_deposited: u64,
     pub total_withdrawn: u64,
     pub created_at: i64,
-    pub bump: u8,
-
-    pub multisig_threshold: u8,
-    pub authorized_signers: Vec<Pubkey>,
-    pub delegated_users: Vec<Pubkey>,
     pub withdrawal_timelock: i64,
-    pub pending_withdrawal: Option<PendingWithdrawal>,
-    pub emergency_mode: bool,
-    pub yield_enabled: bool,
     pub total_yield_earned: u64,
     pub last_yield_compound: i64,
-    pub whitelist_enabled: bool,
-    pub withdrawal_whitelist: Vec<Pubkey>,
     pub rate_limit_amount: u64,
     pub rate_limit_window: i64,
     pub rate_limit_window_start: i64,
     pub rate_limit_withdrawn: u64,
     pub last_update: i64,
+    pub frozen_at: i64,
+    // only meaningful while has_pending_withdrawal is set
+    pub pending_withdrawal: PendingWithdrawal,
+
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub authorized_signers: [Pubkey; 10],
+    pub delegated_users: [Pubkey; 5],
+    pub withdrawal_whitelist: [Pubkey; 20],
+
+    pub bump: u8,
+    pub multisig_threshold: u8,
+    pub signer_count: u8,
+    pub delegate_count: u8,
+    pub whitelist_count: u8,
+    pub has_pending_withdrawal: u8,
+    pub emergency_mode: u8,
+    pub yield_enabled: u8,
+    pub whitelist_enabled: u8,
+    // set by the protocol admin (e.g. on a regulator's request)
+    pub frozen: u8,
+    pub freeze_reason: u8,
+    pub _padding: [u8; 5],
+    // bumped for every event emitted about this vault, so indexers can spot missed events
+    pub event_seq: u64,
+    // next nonce a signed withdrawal permit has to carry, so each permit can only be used once
+    pub permit_nonce: u64,
+    // the part of locked_balance held by open LockRecords, only the record it belongs to can
+    // release or seize it
+    pub lock_record_balance: u64,
+    // everything funded into sub-accounts, available or locked inside the sub-account
+    pub sub_account_balance: u64,
+    // held by open hashlock escrows, only their claim or refund moves it
+    pub escrow_balance: u64,
+    // still unvested under vesting schedules, only settle_vesting releases it
+    pub vesting_balance: u64,
+    // set while the vault has a journal, every instruction that moves funds then has to be
+    // given it so the journal can't be skipped
+    pub has_journal: u8,
+    pub _journal_padding: [u8; 7],
+    pub _reserved: [u8; 72],
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+// parts of locked_balance that belong to something more specific than a plain program lock.
+// each one is only moved through its own instructions, plain unlock and seize_locked stop at
+// whatever is left over (free_locked)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockBucket {
+    LockRecords,
+    SubAccounts,
+    Escrow,
+    Vesting,
+}
+
+#[zero_copy]
+#[derive(Debug, Default)]
 pub struct PendingWithdrawal {
     pub amount: u64,
     pub requested_at: i64,
@@ -39,8 +87,52 @@ pub struct PendingWithdrawal {
 }
 
 impl CollateralVault {
-    // account size calculation includes max vec lengths to prevent realloc issues
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 4 + (32 * 10) + 4 + (32 * 5) + 8 + 1 + (8 + 8 + 8 + 32) + 1 + 1 + 8 + 8 + 1 + 4 + (32 * 20) + 8 + 8 + 8 + 8 + 8;
+    pub const LEN: usize = 8 + std::mem::size_of::<CollateralVault>();
+
+    pub const MAX_SIGNERS: usize = 10;
+    pub const MAX_DELEGATES: usize = 5;
+    pub const MAX_WHITELIST: usize = 20;
+
+    pub fn signers(&self) -> &[Pubkey] {
+        &self.authorized_signers[..self.signer_count as usize]
+    }
+
+    pub fn delegates(&self) -> &[Pubkey] {
+        &self.delegated_users[..self.delegate_count as usize]
+    }
+
+    pub fn whitelist(&self) -> &[Pubkey] {
+        &self.withdrawal_whitelist[..self.whitelist_count as usize]
+    }
+
+    pub fn pending(&self) -> Option<&PendingWithdrawal> {
+        if self.has_pending_withdrawal != 0 {
+            Some(&self.pending_withdrawal)
+        } else {
+            None
+        }
+    }
+
+    pub fn clear_pending(&mut self) {
+        self.has_pending_withdrawal = 0;
+        self.pending_withdrawal = PendingWithdrawal::default();
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen != 0
+    }
+
+    pub fn has_journal(&self) -> bool {
+        self.has_journal != 0
+    }
+
+    // the first event a vault emits is 1, so 0 on an account just means nothing was emitted yet
+    pub fn next_event_seq(&mut self) -> Result<u64> {
+        self.event_seq = self.event_seq
+            .checked_add(1)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+        Ok(self.event_seq)
+    }
 
     pub fn add_deposit(&mut self, amount: u64) -> Result<()> {
         self.total_balance = self.total_balance
@@ -74,6 +166,32 @@ impl CollateralVault {
         Ok(())
     }
 
+    // balance moves without touching total_deposited/total_withdrawn, for callers that book
+    // the lifetime totals themselves (batch_execute only counts the net amount)
+    pub fn credit_available(&mut self, amount: u64) -> Result<()> {
+        self.total_balance = self.total_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        self.available_balance = self.available_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    pub fn debit_available(&mut self, amount: u64) -> Result<()> {
+        self.total_balance = self.total_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientBalance))?;
+
+        self.available_balance = self.available_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientAvailableBalance))?;
+
+        Ok(())
+    }
+
     pub fn lock(&mut self, amount: u64) -> Result<()> {
         require!(
             self.available_balance >= amount,
@@ -91,7 +209,67 @@ impl CollateralVault {
         Ok(())
     }
 
+    // locked funds outside every bucket, what a program locked with plain lock_collateral
+    pub fn free_locked(&self) -> u64 {
+        [
+            self.lock_record_balance,
+            self.sub_account_balance,
+            self.escrow_balance,
+            self.vesting_balance,
+        ]
+            .iter()
+            .fold(self.locked_balance, |free, held| free.saturating_sub(*held))
+    }
+
+    fn bucket_mut(&mut self, bucket: LockBucket) -> &mut u64 {
+        match bucket {
+            LockBucket::LockRecords => &mut self.lock_record_balance,
+            LockBucket::SubAccounts => &mut self.sub_account_balance,
+            LockBucket::Escrow => &mut self.escrow_balance,
+            LockBucket::Vesting => &mut self.vesting_balance,
+        }
+    }
+
+    pub fn lock_into(&mut self, bucket: LockBucket, amount: u64) -> Result<()> {
+        self.lock(amount)?;
+
+        let balance = self.bucket_mut(bucket);
+        *balance = balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
+    fn take_from(&mut self, bucket: LockBucket, amount: u64) -> Result<()> {
+        let balance = self.bucket_mut(bucket);
+        *balance = balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientLockedBalance))?;
+
+        Ok(())
+    }
+
+    pub fn unlock_from(&mut self, bucket: LockBucket, amount: u64) -> Result<()> {
+        self.take_from(bucket, amount)?;
+        self.release_locked(amount)
+    }
+
+    pub fn seize_from(&mut self, bucket: LockBucket, amount: u64) -> Result<()> {
+        self.take_from(bucket, amount)?;
+        self.remove_locked(amount)
+    }
+
     pub fn unlock(&mut self, amount: u64) -> Result<()> {
+        require!(
+            amount <= self.free_locked(),
+            crate::errors::VaultError::InsufficientLockedBalance
+        );
+
+        self.release_locked(amount)
+    }
+
+    fn release_locked(&mut self, amount: u64) -> Result<()> {
         self.locked_balance = self.locked_balance
             .checked_sub(amount)
             .ok_or(error!(crate::errors::VaultError::InsufficientBalance))?;
@@ -103,18 +281,37 @@ impl CollateralVault {
         Ok(())
     }
 
+    // liquidations only ever touch collateral that was locked against a position
+    pub fn seize_locked(&mut self, amount: u64) -> Result<()> {
+        require!(
+            amount <= self.free_locked(),
+            crate::errors::VaultError::InsufficientLockedBalance
+        );
+
+        self.remove_locked(amount)
+    }
+
+    fn remove_locked(&mut self, amount: u64) -> Result<()> {
+        self.locked_balance = self.locked_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientBalance))?;
+
+        self.total_balance = self.total_balance
+            .checked_sub(amount)
+            .ok_or(error!(crate::errors::VaultError::InsufficientBalance))?;
+
+        self.total_withdrawn = self.total_withdrawn
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
     pub fn initialize_advanced_features(&mut self, clock: &Clock) {
-        self.multisig_threshold = 0;
-        self.authorized_signers = Vec::new();
-        self.delegated_users = Vec::new();
+        // arrays, counts and flags start zeroed from load_init
         self.withdrawal_timelock = 0;
-        self.pending_withdrawal = None;
-        self.emergency_mode = false;
-        self.yield_enabled = false;
         self.total_yield_earned = 0;
         self.last_yield_compound = clock.unix_timestamp;
-        self.whitelist_enabled = false;
-        self.withdrawal_whitelist = Vec::new();
         self.rate_limit_amount = u64::MAX;
         self.rate_limit_window = 86400;
         self.rate_limit_window_start = clock.unix_timestamp;
@@ -123,18 +320,24 @@ impl CollateralVault {
     }
 
     pub fn is_authorized(&self, user: &Pubkey) -> bool {
-        &self.owner == user || self.delegated_users.contains(user)
+        &self.owner == user || self.delegates().contains(user)
     }
 
     pub fn is_withdrawal_allowed(&self, recipient: &Pubkey) -> bool {
-        if !self.whitelist_enabled {
+        if self.whitelist_enabled == 0 {
             return true;
         }
-        self.withdrawal_whitelist.contains(recipient)
+        self.whitelist().contains(recipient)
+    }
+
+    // configure_rate_limit always sets a window, so a zero window is a vault that never had one
+    // configured (vaults created before initialize_vault set the defaults have a zero amount too)
+    pub fn has_rate_limit(&self) -> bool {
+        self.rate_limit_window > 0 && self.rate_limit_amount != u64::MAX
     }
 
     pub fn check_and_update_rate_limit(&mut self, amount: u64, clock: &Clock) -> Result<()> {
-        if clock.unix_timestamp >= self.rate_limit_window_start + self.rate_limit_window {
+        if clock.unix_timestamp >= self.rate_limit_window_start.saturating_add(self.rate_limit_window) {
             self.rate_limit_window_start = clock.unix_timestamp;
             self.rate_limit_withdrawn = 0;
         }
@@ -152,9 +355,22 @@ impl CollateralVault {
         Ok(())
     }
 
+    // read-only counterpart of check_and_update_rate_limit, u64::MAX means no limit
+    pub fn remaining_rate_limit(&self, clock: &Clock) -> u64 {
+        if !self.has_rate_limit() {
+            return u64::MAX;
+        }
+
+        if clock.unix_timestamp >= self.rate_limit_window_start.saturating_add(self.rate_limit_window) {
+            return self.rate_limit_amount;
+        }
+
+        self.rate_limit_amount.saturating_sub(self.rate_limit_withdrawn)
+    }
+
     pub fn request_withdrawal(&mut self, amount: u64, recipient: Pubkey, clock: &Clock) -> Result<()> {
         require!(
-            self.pending_withdrawal.is_none(),
+            self.has_pending_withdrawal == 0,
             crate::errors::VaultError::PendingWithdrawalExists
         );
 
@@ -165,35 +381,40 @@ impl CollateralVault {
 
         let executable_at = clock.unix_timestamp + self.withdrawal_timelock;
         
-        self.pending_withdrawal = Some(PendingWithdrawal {
+        self.pending_withdrawal = PendingWithdrawal {
             amount,
             requested_at: clock.unix_timestamp,
             executable_at,
             recipient,
-        });
+        };
+        self.has_pending_withdrawal = 1;
 
         Ok(())
     }
 
     pub fn execute_pending_withdrawal(&mut self, clock: &Clock) -> Result<(u64, Pubkey)> {
-        let pending = self.pending_withdrawal
-            .as_ref()
+        let (amount, recipient) = self.take_pending_withdrawal(clock)?;
+        self.sub_withdrawal(amount)?;
+
+        Ok((amount, recipient))
+    }
+
+    // consumes an unlocked request without moving any balance, for callers that send the
+    // funds somewhere other than out of the vault (a hashlock escrow locks them instead)
+    pub fn take_pending_withdrawal(&mut self, clock: &Clock) -> Result<(u64, Pubkey)> {
+        let pending = *self.pending()
             .ok_or(error!(crate::errors::VaultError::NoPendingWithdrawal))?;
 
-        if !self.emergency_mode {
+        if self.emergency_mode == 0 {
             require!(
                 clock.unix_timestamp >= pending.executable_at,
                 crate::errors::VaultError::TimeLockNotExpired
             );
         }
 
-        let amount = pending.amount;
-        let recipient = pending.recipient;
+        self.clear_pending();
 
-        self.sub_withdrawal(amount)?;
-        self.pending_withdrawal = None;
-
-        Ok((amount, recipient))
+        Ok((pending.amount, pending.recipient))
     }
 
     pub fn add_yield(&mut self, yield_amount: u64) -> Result<()> {
@@ -212,24 +433,38 @@ impl CollateralVault {
         Ok(())
     }
 
+    // unlike add_deposit this leaves total_deposited alone, the owner never deposited it
+    pub fn add_donation(&mut self, amount: u64) -> Result<()> {
+        self.total_balance = self.total_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        self.available_balance = self.available_balance
+            .checked_add(amount)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(())
+    }
+
     pub fn add_delegated_user(&mut self, user: Pubkey) -> Result<()> {
         require!(
-            !self.delegated_users.contains(&user),
+            !self.delegates().contains(&user),
             crate::errors::VaultError::UserAlreadyDelegated
         );
         
         require!(
-            self.delegated_users.len() < 5,
+            (self.delegate_count as usize) < Self::MAX_DELEGATES,
             crate::errors::VaultError::MaxDelegatedUsersReached
         );
         
-        self.delegated_users.push(user);
+        self.delegated_users[self.delegate_count as usize] = user;
+        self.delegate_count += 1;
         Ok(())
     }
 
     pub fn remove_delegated_user(&mut self, user: &Pubkey) -> Result<()> {
-        if let Some(pos) = self.delegated_users.iter().position(|x| x == user) {
-            self.delegated_users.remove(pos);
+        if let Some(pos) = self.delegates().iter().position(|x| x == user) {
+            remove_at(&mut self.delegated_users, &mut self.delegate_count, pos);
             Ok(())
         } else {
             Err(error!(crate::errors::VaultError::UserNotDelegated))
@@ -238,45 +473,113 @@ impl CollateralVault {
 
     pub fn add_to_whitelist(&mut self, address: Pubkey) -> Result<()> {
         require!(
-            !self.withdrawal_whitelist.contains(&address),
+            !self.whitelist().contains(&address),
             crate::errors::VaultError::AddressAlreadyWhitelisted
         );
         
         require!(
-            self.withdrawal_whitelist.len() < 20,
+            (self.whitelist_count as usize) < Self::MAX_WHITELIST,
             crate::errors::VaultError::MaxWhitelistReached
         );
         
-        self.withdrawal_whitelist.push(address);
+        self.withdrawal_whitelist[self.whitelist_count as usize] = address;
+        self.whitelist_count += 1;
         Ok(())
     }
 
+    pub fn remove_from_whitelist(&mut self, address: &Pubkey) -> Result<()> {
+        if let Some(pos) = self.whitelist().iter().position(|x| x == address) {
+            remove_at(&mut self.withdrawal_whitelist, &mut self.whitelist_count, pos);
+            Ok(())
+        } else {
+            Err(error!(crate::errors::VaultError::AddressNotWhitelisted))
+        }
+    }
+
     pub fn add_signer(&mut self, signer: Pubkey) -> Result<()> {
         require!(
-            !self.authorized_signers.contains(&signer),
+            !self.signers().contains(&signer),
             crate::errors::VaultError::SignerAlreadyAuthorized
         );
         
         require!(
-            self.authorized_signers.len() < 10,
+            (self.signer_count as usize) < Self::MAX_SIGNERS,
             crate::errors::VaultError::MaxSignersReached
         );
         
-        self.authorized_signers.push(signer);
+        self.authorized_signers[self.signer_count as usize] = signer;
+        self.signer_count += 1;
+        Ok(())
+    }
+
+    pub fn set_signers(&mut self, signers: &[Pubkey]) -> Result<()> {
+        require!(
+            signers.len() <= Self::MAX_SIGNERS,
+            crate::errors::VaultError::MaxSignersReached
+        );
+
+        self.authorized_signers = [Pubkey::default(); 10];
+        self.authorized_signers[..signers.len()].copy_from_slice(signers);
+        self.signer_count = signers.len() as u8;
         Ok(())
     }
 }
 
+// keeps the used prefix of a fixed array contiguous, same order a Vec::remove would leave
+fn remove_at<const N: usize>(items: &mut [Pubkey; N], count: &mut u8, pos: usize) {
+    let len = *count as usize;
+    items.copy_within(pos + 1..len, pos);
+    items[len - 1] = Pubkey::default();
+    *count -= 1;
+}
+
+// bumped whenever a field is added to or removed from one of the program's events
+pub const EVENT_VERSION: u8 = 1;
+
+// every event about a single vault starts with version, vault and that vault's event_seq.
+// events touching two vaults carry a seq for each side
+
+#[event]
+pub struct VaultInitialized {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct DepositEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
     pub user: Pubkey,
     pub amount: u64,
     pub new_balance: u64,
     pub timestamp: i64,
+    // opaque tag from the depositor (invoice id, hash, ...) so off-chain accounting can match it
+    pub reference: Option<[u8; 32]>,
+}
+
+#[event]
+pub struct DepositForEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub payer: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
+    pub new_balance: u64,
+    pub memo: Option<String>,
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct WithdrawEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
     pub user: Pubkey,
     pub amount: u64,
     pub new_balance: u64,
@@ -285,6 +588,9 @@ pub struct WithdrawEvent {
 
 #[event]
 pub struct LockEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
     pub user: Pubkey,
     pub amount: u64,
     pub locked_balance: u64,
@@ -294,6 +600,9 @@ pub struct LockEvent {
 
 #[event]
 pub struct UnlockEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
     pub user: Pubkey,
     pub amount: u64,
     pub locked_balance: u64,
@@ -303,15 +612,77 @@ pub struct UnlockEvent {
 
 #[event]
 pub struct TransferEvent {
+    pub version: u8,
+    pub from_vault: Pubkey,
+    pub from_event_seq: u64,
+    pub to_vault: Pubkey,
+    pub to_event_seq: u64,
     pub from: Pubkey,
     pub to: Pubkey,
     pub amount: u64,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct VaultTransferEvent {
+    pub version: u8,
+    pub from_vault: Pubkey,
+    pub from_event_seq: u64,
+    pub to_vault: Pubkey,
+    pub to_event_seq: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub from_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BalanceSyncedEvent {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub owner: Pubkey,
+    pub donation: u64,
+    pub previous_balance: u64,
+    pub new_balance: u64,
+    pub timestamp: i64,
+}
+
+// the per-vault side of a settlement is in the TransferEvents emitted before this one
+#[event]
+pub struct SettlementEvent {
+    pub version: u8,
+    pub caller_program: Pubkey,
+    pub vault_count: u8,
+    pub leg_count: u8,
+    pub transfer_count: u8,
+    pub total_volume: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidationEvent {
+    pub version: u8,
+    pub victim_vault: Pubkey,
+    pub victim_event_seq: u64,
+    pub liquidator_vault: Pubkey,
+    pub liquidator_event_seq: u64,
+    pub victim: Pubkey,
+    pub liquidator: Pubkey,
+    pub seized_amount: u64,
+    pub bonus: u64,
+    pub protocol_fee: u64,
+    pub insurance_share: u64,
+    pub remaining_locked: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct MultiSigConfigured {
+    pub version: u8,
     pub vault: Pubkey,
+    pub event_seq: u64,
     pub threshold: u8,
     pub signers_count: u8,
     pub timestamp: i64,
@@ -319,23 +690,88 @@ pub struct MultiSigConfigured {
 
 #[event]
 pub struct DelegationEvent {
+    pub version: u8,
     pub vault: Pubkey,
+    pub event_seq: u64,
     pub user: Pubkey,
     pub action: String,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct WhitelistUpdated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub address: Pubkey,
+    pub added: bool,
+    pub whitelist_count: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WhitelistToggled {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RateLimitConfigured {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub max_amount: u64,
+    pub time_window: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TimelockConfigured {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub duration: i64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct WithdrawalRequested {
+    pub version: u8,
     pub vault: Pubkey,
+    pub event_seq: u64,
     pub amount: u64,
+    pub recipient: Pubkey,
     pub executable_at: i64,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct WithdrawalCancelled {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct YieldConfigured {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct YieldEarned {
+    pub version: u8,
     pub vault: Pubkey,
+    pub event_seq: u64,
     pub amount: u64,
     pub total_yield: u64,
     pub timestamp: i64,
@@ -343,8 +779,43 @@ pub struct YieldEarned {
 
 #[event]
 pub struct EmergencyModeToggled {
+    pub version: u8,
     pub vault: Pubkey,
+    pub event_seq: u64,
     pub enabled: bool,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct VaultFrozen {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub owner: Pubkey,
+    pub admin: Pubkey,
+    pub reason_code: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultUnfrozen {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub owner: Pubkey,
+    pub admin: Pubkey,
+    pub reason_code: u8,
+    pub frozen_for: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultMigrated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub owner: Pubkey,
+    pub old_size: u32,
+    pub new_size: u32,
+    pub timestamp: i64,
+}
@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VestingKind {
+    // accrues pro-rata from start to end, but nothing can be released before the cliff
+    Linear,
+    // everything releases at once when the cliff is reached
+    Cliff,
+}
+
+#[account]
+pub struct VestingSchedule {
+    pub vault: Pubkey,
+    pub creator: Pubkey,
+    // the registered program that created it, default when the owner did
+    pub caller_program: Pubkey,
+    pub schedule_id: u64,
+    pub kind: VestingKind,
+    pub total_amount: u64,
+    pub released_amount: u64,
+    pub start_ts: i64,
+    pub cliff_ts: i64,
+    pub end_ts: i64,
+    // what's still counted against caller_program's exposure in this vault
+    pub exposure_held: u64,
+    pub bump: u8,
+}
+
+impl VestingSchedule {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        if now < self.cliff_ts {
+            return Ok(0);
+        }
+
+        match self.kind {
+            VestingKind::Cliff => Ok(self.total_amount),
+            VestingKind::Linear => {
+                if now >= self.end_ts {
+                    return Ok(self.total_amount);
+                }
+
+                let elapsed = now
+                    .checked_sub(self.start_ts)
+                    .ok_or(error!(crate::errors::VaultError::NumericalOverflow))? as u128;
+                let duration = self.end_ts
+                    .checked_sub(self.start_ts)
+                    .ok_or(error!(crate::errors::VaultError::NumericalOverflow))? as u128;
+
+                let vested = (self.total_amount as u128)
+                    .checked_mul(elapsed)
+                    .and_then(|v| v.checked_div(duration))
+                    .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+                Ok(vested as u64)
+            }
+        }
+    }
+
+    // returns how much became releasable since the last call and marks it released
+    pub fn take_releasable(&mut self, now: i64) -> Result<u64> {
+        let vested = self.vested_amount(now)?;
+        let releasable = vested.saturating_sub(self.released_amount);
+
+        self.released_amount = self.released_amount
+            .checked_add(releasable)
+            .ok_or(error!(crate::errors::VaultError::NumericalOverflow))?;
+
+        Ok(releasable)
+    }
+
+    // vested funds are back in available, so the program that locked them stops carrying them.
+    // returns how much exposure that frees and stops holding it
+    pub fn take_exposure_to_release(&mut self) -> u64 {
+        let unvested = self.total_amount.saturating_sub(self.released_amount);
+        let release = self.exposure_held.saturating_sub(unvested);
+        self.exposure_held -= release;
+        release
+    }
+
+    pub fn is_fully_released(&self) -> bool {
+        self.released_amount >= self.total_amount
+    }
+}
+
+#[event]
+pub struct VestingScheduleCreated {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub creator: Pubkey,
+    pub schedule_id: u64,
+    pub kind: VestingKind,
+    pub total_amount: u64,
+    pub start_ts: i64,
+    pub cliff_ts: i64,
+    pub end_ts: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestingReleased {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub event_seq: u64,
+    pub schedule_id: u64,
+    pub amount: u64,
+    pub total_released: u64,
+    pub timestamp: i64,
+}
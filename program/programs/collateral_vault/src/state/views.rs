@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+// return data for the view instructions. integrators decode these instead of the vault
+// account, so fields are only ever appended and `version` goes up when they are
+pub const VIEW_VERSION: u8 = 2;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VaultSummary {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub total_balance: u64,
+    pub locked_balance: u64,
+    pub available_balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub total_yield_earned: u64,
+    pub frozen: bool,
+    pub emergency_mode: bool,
+    pub whitelist_enabled: bool,
+    pub withdrawal_timelock: i64,
+    pub has_pending_withdrawal: bool,
+    pub created_at: i64,
+    // added in version 2, seq of the last event the vault emitted
+    pub event_seq: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WithdrawableView {
+    pub version: u8,
+    pub vault: Pubkey,
+    // what `withdraw` would accept right now, not counting vesting that
+    // could be settled by passing the schedules in
+    pub withdrawable: u64,
+    // same, for paths that apply the rate limit (batch withdraw, transfers, allowances)
+    pub withdrawable_rate_limited: u64,
+    pub pending_amount: u64,
+    pub pending_executable: bool,
+    pub frozen: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitView {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub limited: bool,
+    pub limit_amount: u64,
+    pub window: i64,
+    pub withdrawn_in_window: u64,
+    pub remaining: u64,
+    // 0 when the current window has already run out
+    pub resets_at: i64,
+}
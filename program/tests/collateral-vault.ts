@@ -131,7 +131,7 @@ describe("collateral_vault", () => {
       );
 
       const tx = await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           user: owner.publicKey,
           userTokenAccount: ownerTokenAccount,
@@ -149,7 +149,7 @@ describe("collateral_vault", () => {
     it("Fails with invalid amount", async () => {
       try {
         await program.methods
-          .deposit(new anchor.BN(0))
+          .deposit(new anchor.BN(0), null)
           .accounts({
           user: owner.publicKey,
           userTokenAccount: ownerTokenAccount,
@@ -185,7 +185,7 @@ describe("collateral_vault", () => {
       );
 
       await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           user: owner.publicKey,
           userTokenAccount: ownerTokenAccount,
@@ -247,7 +247,7 @@ describe("collateral_vault", () => {
       );
 
       await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           user: owner.publicKey,
           userTokenAccount: ownerTokenAccount,
@@ -294,7 +294,7 @@ describe("collateral_vault", () => {
       );
 
       await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           user: owner.publicKey,
           userTokenAccount: ownerTokenAccount,
@@ -392,7 +392,7 @@ describe("collateral_vault", () => {
 
       const vault = await program.account.collateralVault.fetch(vaultPda);
       expect(vault.multisigThreshold).to.equal(threshold);
-      expect(vault.authorizedSigners.length).to.equal(signers.length);
+      expect(vault.signerCount).to.equal(signers.length);
     });
 
     it("Adds delegate", async () => {
@@ -409,7 +409,9 @@ describe("collateral_vault", () => {
 
       const vault = await program.account.collateralVault.fetch(vaultPda);
       // Check if delegate is in the array by converting to strings for comparison
-      const delegateStrings = vault.delegatedUsers.map((pk: PublicKey) => pk.toString());
+      const delegateStrings = vault.delegatedUsers
+        .slice(0, vault.delegateCount)
+        .map((pk: PublicKey) => pk.toString());
       expect(delegateStrings).to.include(delegate.toString());
     });
 
@@ -458,7 +460,7 @@ describe("collateral_vault", () => {
         .rpc();
 
       const vault = await program.account.collateralVault.fetch(vaultPda);
-      expect(vault.emergencyMode).to.be.true;
+      expect(vault.emergencyMode).to.equal(1);
     });
 
     it("Configures yield", async () => {
@@ -472,7 +474,7 @@ describe("collateral_vault", () => {
         .rpc();
 
       const vault = await program.account.collateralVault.fetch(vaultPda);
-      expect(vault.yieldEnabled).to.be.true;
+      expect(vault.yieldEnabled).to.equal(1);
     });
   });
 
@@ -500,7 +502,7 @@ describe("collateral_vault", () => {
       );
 
       await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           user: owner.publicKey,
           userTokenAccount: ownerTokenAccount,
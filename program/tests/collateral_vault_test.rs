@@ -13,7 +13,12 @@ use std::str::FromStr;
 
 use collateral_vault::{
     self,
-    state::CollateralVault,
+    instructions::{record_journal, BatchDepositItem, BatchOperation, BatchWithdrawItem, SettlementLeg},
+    state::{
+        Allowance, AuthorizedProgram, CollateralVault, InsuranceFund, LegacyCollateralVault,
+        LegacyPendingWithdrawal, LockBucket, ProgramExposure, ProgramRegistry, ProtocolConfig, RateLimitView, ScheduledWithdrawal,
+        SessionKey, SharePool, WithdrawalPermit, SESSION_SCOPE_LOCK, SubAccount, JournalOp, VaultJournal, JOURNAL_CAPACITY, VaultAuthority, VaultSummary, VestingKind, VestingSchedule, WithdrawableView, VIEW_VERSION,
+    },
     errors::VaultError,
 };
 
@@ -65,6 +70,8 @@ async fn test_initialize_vault() -> Result<()> {
 #[tokio::test]
 async fn test_deposit() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000; // 1000 tokens (6 decimals)
@@ -79,9 +86,11 @@ async fn test_deposit() -> Result<()> {
             user_token_account: context.user_token_account,
             vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
             owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
             token_program: anchor_spl::token::ID,
+            journal: None,
         })
-        .args(collateral_vault::instruction::Deposit { amount: deposit_amount })
+        .args(collateral_vault::instruction::Deposit { amount: deposit_amount, reference: None })
         .signer(&context.owner)
         .send()
         .await?;
@@ -100,9 +109,54 @@ async fn test_deposit() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_deposit_with_reference() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 250_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    
+    let mut reference = [0u8; 32];
+    reference[..10].copy_from_slice(b"INV-000123");
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::Deposit {
+            user: context.owner.pubkey(),
+            vault: get_vault_pda(context.owner.pubkey()),
+            user_token_account: context.user_token_account,
+            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+            owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::Deposit { amount: deposit_amount, reference: Some(reference) })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    assert_eq!(vault_account.total_balance, deposit_amount);
+    
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_deposit_invalid_amount() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let result = context
@@ -114,9 +168,11 @@ async fn test_deposit_invalid_amount() -> Result<()> {
             user_token_account: context.user_token_account,
             vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
             owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
             token_program: anchor_spl::token::ID,
+            journal: None,
         })
-        .args(collateral_vault::instruction::Deposit { amount: 0 })
+        .args(collateral_vault::instruction::Deposit { amount: 0, reference: None })
         .signer(&context.owner)
         .send()
         .await;
@@ -129,6 +185,8 @@ async fn test_deposit_invalid_amount() -> Result<()> {
 #[tokio::test]
 async fn test_withdraw() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
@@ -146,7 +204,9 @@ async fn test_withdraw() -> Result<()> {
             user_token_account: context.user_token_account,
             vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
             owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
             token_program: anchor_spl::token::ID,
+            journal: None,
         })
         .args(collateral_vault::instruction::Withdraw { amount: withdraw_amount })
         .signer(&context.owner)
@@ -155,39 +215,2033 @@ async fn test_withdraw() -> Result<()> {
     
     assert!(tx.is_success());
     
-    let vault_account: CollateralVault = context
+    let vault_account: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    assert_eq!(vault_account.total_balance, deposit_amount - withdraw_amount);
+    assert_eq!(vault_account.available_balance, deposit_amount - withdraw_amount);
+    assert_eq!(vault_account.total_withdrawn, withdraw_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_withdraw_insufficient_balance() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::Withdraw {
+            user: context.owner.pubkey(),
+            vault: get_vault_pda(context.owner.pubkey()),
+            user_token_account: context.user_token_account,
+            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+            owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::Withdraw { amount: deposit_amount + 1 })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lock_collateral() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let lock_amount = 500_000_000;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::LockCollateral {
+            user: context.owner.pubkey(),
+            vault: get_vault_pda(context.owner.pubkey()),
+            owner: context.owner.pubkey(),
+            journal: None,
+        })
+        .args(collateral_vault::instruction::LockCollateral { amount: lock_amount })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    assert_eq!(vault_account.locked_balance, lock_amount);
+    assert_eq!(vault_account.available_balance, deposit_amount - lock_amount);
+    assert_eq!(vault_account.total_balance, deposit_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unlock_collateral() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let lock_amount = 500_000_000;
+    lock_collateral(&mut context, lock_amount).await?;
+    
+    let unlock_amount = 300_000_000;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::UnlockCollateral {
+            user: context.owner.pubkey(),
+            vault: get_vault_pda(context.owner.pubkey()),
+            owner: context.owner.pubkey(),
+            journal: None,
+        })
+        .args(collateral_vault::instruction::UnlockCollateral { amount: unlock_amount })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    assert_eq!(vault_account.locked_balance, lock_amount - unlock_amount);
+    assert_eq!(vault_account.available_balance, deposit_amount - lock_amount + unlock_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_collateral() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let recipient = Keypair::new();
+    let recipient_vault_pda = get_vault_pda(recipient.pubkey());
+    
+    // Initialize recipient vault
+    initialize_vault_for_user(&mut context, &recipient).await?;
+    
+    let transfer_amount = 300_000_000;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::TransferCollateral {
+            from_user: context.owner.pubkey(),
+            from_vault: get_vault_pda(context.owner.pubkey()),
+            to_vault: recipient_vault_pda,
+            from_vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+            to_vault_token_account: get_vault_token_account(&recipient_vault_pda, &context.usdt_mint.pubkey()),
+            owner: context.owner.pubkey(),
+            token_program: anchor_spl::token::ID,
+            from_journal: None,
+            to_journal: None,
+        })
+        .args(collateral_vault::instruction::TransferCollateral { amount: transfer_amount })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let from_vault: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    let to_vault: CollateralVault = context
+        .program
+        .account(recipient_vault_pda)
+        .await?;
+    
+    assert_eq!(from_vault.total_balance, deposit_amount - transfer_amount);
+    assert_eq!(to_vault.total_balance, transfer_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fresh_vault_has_no_rate_limit() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_vault(&mut context).await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let recipient = Keypair::new();
+    initialize_vault_for_user(&mut context, &recipient).await?;
+    
+    let from_vault = get_vault_pda(context.owner.pubkey());
+    let to_vault = get_vault_pda(recipient.pubkey());
+    
+    let vault_account: CollateralVault = context.program.account(from_vault).await?;
+    assert_eq!(vault_account.rate_limit_amount, u64::MAX);
+    assert!(!vault_account.has_rate_limit());
+    
+    // nothing was configured, so the outflow isn't capped at zero
+    transfer_to_vault(&mut context, from_vault, to_vault, deposit_amount).await?;
+    
+    let to_vault_account: CollateralVault = context.program.account(to_vault).await?;
+    assert_eq!(to_vault_account.total_balance, deposit_amount);
+    
+    Ok(())
+}
+
+#[test]
+fn test_unconfigured_rate_limit_is_unlimited() {
+    let mut clock = Clock::default();
+    clock.unix_timestamp = 1_000;
+    
+    // vaults created before initialize_vault set the defaults are all zeros
+    let mut vault = <CollateralVault as bytemuck::Zeroable>::zeroed();
+    assert!(!vault.has_rate_limit());
+    assert_eq!(vault.remaining_rate_limit(&clock), u64::MAX);
+    
+    vault.initialize_advanced_features(&clock);
+    assert!(!vault.has_rate_limit());
+    assert_eq!(vault.remaining_rate_limit(&clock), u64::MAX);
+    
+    vault.rate_limit_amount = 500;
+    vault.rate_limit_window = i64::MAX;
+    assert!(vault.has_rate_limit());
+    vault.check_and_update_rate_limit(500, &clock).unwrap();
+    assert!(vault.check_and_update_rate_limit(1, &clock).is_err());
+    assert_eq!(vault.remaining_rate_limit(&clock), 0);
+}
+
+#[tokio::test]
+async fn test_transfer_to_vault_requires_pending_request_under_timelock() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_vault(&mut context).await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    configure_timelock(&mut context, 3600).await?;
+    
+    let recipient = Keypair::new();
+    initialize_vault_for_user(&mut context, &recipient).await?;
+    
+    let from_vault = get_vault_pda(context.owner.pubkey());
+    let to_vault = get_vault_pda(recipient.pubkey());
+    
+    // no request_withdrawal was made, so there is nothing for the timelock to release
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::TransferToVault {
+            owner: context.owner.pubkey(),
+            from_vault,
+            to_vault,
+            from_token_account: get_vault_token_account(&from_vault, &context.usdt_mint.pubkey()),
+            to_token_account: get_vault_token_account(&to_vault, &context.usdt_mint.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            from_journal: None,
+            to_journal: None,
+        })
+        .args(collateral_vault::instruction::TransferToVault { amount: 300_000_000 })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    let to_vault_account: CollateralVault = context.program.account(to_vault).await?;
+    assert_eq!(to_vault_account.total_balance, 0);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hashlock_escrow_creation_follows_withdrawal_policies() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let receiver = Keypair::new();
+    initialize_vault_for_user(&mut context, &receiver).await?;
+    
+    let from_vault = get_vault_pda(context.owner.pubkey());
+    let to_vault = get_vault_pda(receiver.pubkey());
+    let hashlock = solana_sdk::hash::hash(b"otc-settlement-secret").to_bytes();
+    let create = |context: &TestContext, escrow_id: u64| {
+        context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::CreateHashlockEscrow {
+                owner: context.owner.pubkey(),
+                from_vault,
+                to_vault,
+                escrow: get_escrow_pda(&from_vault, escrow_id),
+                system_program: anchor_lang::system_program::ID,
+                from_journal: None,
+            })
+            .args(collateral_vault::instruction::CreateHashlockEscrow {
+                escrow_id,
+                amount: 100_000_000,
+                hashlock,
+                deadline: chrono_now() + 3600,
+            })
+            .signer(&context.owner)
+    };
+    
+    // the receiver isn't on the enabled whitelist
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ConfigureVault {
+            user: context.owner.pubkey(),
+            vault: from_vault,
+            owner: context.owner.pubkey(),
+        })
+        .args(collateral_vault::instruction::ToggleWhitelist { enabled: true })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    assert!(tx.is_success());
+    assert!(create(&context, 1).send().await.is_err());
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ManageWhitelist {
+            user: context.owner.pubkey(),
+            vault: from_vault,
+            owner: context.owner.pubkey(),
+        })
+        .args(collateral_vault::instruction::AddToWhitelist { address: receiver.pubkey() })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    assert!(tx.is_success());
+    assert!(create(&context, 1).send().await?.is_success());
+    
+    // under a timelock the escrow needs an unlocked request for the receiver first
+    configure_timelock(&mut context, 3600).await?;
+    assert!(create(&context, 2).send().await.is_err());
+    
+    let sender_vault: CollateralVault = context.program.account(from_vault).await?;
+    assert_eq!(sender_vault.escrow_balance, 100_000_000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_claim_hashlock_escrow_with_preimage() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let receiver = Keypair::new();
+    initialize_vault_for_user(&mut context, &receiver).await?;
+    
+    let from_vault = get_vault_pda(context.owner.pubkey());
+    let to_vault = get_vault_pda(receiver.pubkey());
+    let escrow_id = 7u64;
+    let escrow_pda = get_escrow_pda(&from_vault, escrow_id);
+    let escrow_amount = 400_000_000;
+    
+    let preimage = b"otc-settlement-secret".to_vec();
+    let hashlock = solana_sdk::hash::hash(&preimage).to_bytes();
+    
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::CreateHashlockEscrow {
+            owner: context.owner.pubkey(),
+            from_vault,
+            to_vault,
+            escrow: escrow_pda,
+            system_program: anchor_lang::system_program::ID,
+            from_journal: None,
+        })
+        .args(collateral_vault::instruction::CreateHashlockEscrow {
+            escrow_id,
+            amount: escrow_amount,
+            hashlock,
+            deadline: chrono_now() + 3600,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    let sender_vault: CollateralVault = context.program.account(from_vault).await?;
+    assert_eq!(sender_vault.locked_balance, escrow_amount);
+    assert_eq!(sender_vault.escrow_balance, escrow_amount);
+    assert_eq!(sender_vault.free_locked(), 0);
+    
+    let claim_accounts = collateral_vault::accounts::ClaimHashlockEscrow {
+        from_vault,
+        to_vault,
+        escrow: escrow_pda,
+        sender: context.owner.pubkey(),
+        from_token_account: get_vault_token_account(&from_vault, &context.usdt_mint.pubkey()),
+        to_token_account: get_vault_token_account(&to_vault, &context.usdt_mint.pubkey()),
+        protocol_config: get_protocol_config_pda(),
+        token_program: anchor_spl::token::ID,
+        from_journal: None,
+        to_journal: None,
+    };
+    
+    let wrong = context
+        .program
+        .request()
+        .accounts(claim_accounts.clone())
+        .args(collateral_vault::instruction::ClaimHashlockEscrow {
+            escrow_id,
+            preimage: b"wrong-secret".to_vec(),
+        })
+        .signer(&receiver)
+        .send()
+        .await;
+    
+    assert!(wrong.is_err());
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(claim_accounts)
+        .args(collateral_vault::instruction::ClaimHashlockEscrow { escrow_id, preimage })
+        .signer(&receiver)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let sender_vault: CollateralVault = context.program.account(from_vault).await?;
+    let receiver_vault: CollateralVault = context.program.account(to_vault).await?;
+    
+    assert_eq!(sender_vault.locked_balance, 0);
+    assert_eq!(sender_vault.total_balance, deposit_amount - escrow_amount);
+    assert_eq!(sender_vault.escrow_balance, 0);
+    assert_eq!(receiver_vault.available_balance, escrow_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sub_account_locked_margin_is_isolated() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let sub_account_id = 1u16;
+    let sub_account_pda = get_sub_account_pda(&vault_pda, sub_account_id);
+    open_program_exposure(&mut context, context.owner.pubkey(), vault_pda).await?;
+    
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::CreateSubAccount {
+            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            sub_account: sub_account_pda,
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::CreateSubAccount {
+            sub_account_id,
+            name: "basis-strategy".to_string(),
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    let manage_accounts = collateral_vault::accounts::ManageSubAccount {
+        owner: context.owner.pubkey(),
+        vault: vault_pda,
+        sub_account: sub_account_pda,
+        journal: None,
+    };
+    
+    context
+        .program
+        .request()
+        .accounts(manage_accounts.clone())
+        .args(collateral_vault::instruction::FundSubAccount { sub_account_id, amount: 400_000_000 })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::SubAccountCollateral {
+            vault: vault_pda,
+            sub_account: sub_account_pda,
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
+        })
+        .args(collateral_vault::instruction::LockSubAccount { sub_account_id, amount: 300_000_000 })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    // only 100 of the bucket is still free, the locked margin can't be pulled back out
+    let result = context
+        .program
+        .request()
+        .accounts(manage_accounts)
+        .args(collateral_vault::instruction::DefundSubAccount { sub_account_id, amount: 200_000_000 })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    let sub_account: SubAccount = context.program.account(sub_account_pda).await?;
+    assert_eq!(sub_account.available_balance, 100_000_000);
+    assert_eq!(sub_account.locked_balance, 300_000_000);
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.locked_balance, 400_000_000);
+    assert_eq!(vault_account.available_balance, 600_000_000);
+    
+    Ok(())
+}
+
+#[test]
+fn test_max_lock_per_vault_counts_only_the_programs_own_locks() {
+    let program = Pubkey::new_unique();
+    let mut registry = ProgramRegistry {
+        limits: AuthorizedProgram {
+            max_lock_per_vault: 500,
+            ..AuthorizedProgram::unrestricted(program)
+        },
+        registered_at: 0,
+        bump: 0,
+    };
+    let mut exposure = ProgramExposure {
+        program_id: program,
+        vault: Pubkey::new_unique(),
+        locked: 0,
+        bump: 0,
+    };
+    
+    registry.record_lock(&mut exposure, 300).unwrap();
+    assert!(registry.record_lock(&mut exposure, 201).is_err());
+    
+    // releasing makes room again, the vault's other locks never enter into it
+    registry.record_release(&mut exposure, 100).unwrap();
+    registry.record_lock(&mut exposure, 300).unwrap();
+    
+    assert_eq!(exposure.locked, 500);
+    
+    // a program can't release more than it locked here, even if the vault holds other locks
+    assert!(registry.record_release(&mut exposure, 501).is_err());
+    assert_eq!(exposure.locked, 500);
+    assert_eq!(registry.limits.current_exposure, 500);
+}
+
+#[test]
+fn test_journal_ring_buffer_wraps_oldest_first() {
+    let mut journal: VaultJournal = bytemuck::Zeroable::zeroed();
+    let depositor = Pubkey::new_unique();
+    
+    assert!(journal.entries_in_order().is_empty());
+    
+    let total = JOURNAL_CAPACITY as u64 + 3;
+    for i in 0..total {
+        journal.record(JournalOp::Deposit, i * 10, depositor, 100 + i).unwrap();
+    }
+    
+    let entries = journal.entries_in_order();
+    assert_eq!(entries.len(), JOURNAL_CAPACITY);
+    assert_eq!(journal.next_seq, total);
+    
+    // the first three were overwritten, everything else comes back in sequence
+    assert_eq!(entries[0].seq, 3);
+    assert_eq!(entries[0].slot, 103);
+    assert_eq!(entries.last().unwrap().seq, total - 1);
+    assert!(entries.windows(2).all(|w| w[1].seq == w[0].seq + 1));
+    assert_eq!(JournalOp::from_u8(entries[0].op), Some(JournalOp::Deposit));
+    assert_eq!(entries[0].counterparty, depositor);
+}
+
+#[test]
+fn test_journal_cannot_be_skipped_once_the_vault_has_one() {
+    let mut vault = <CollateralVault as bytemuck::Zeroable>::zeroed();
+    let clock = Clock::default();
+    
+    record_journal(&vault, None, JournalOp::Deposit, 100, Pubkey::new_unique(), &clock).unwrap();
+    
+    vault.has_journal = 1;
+    let err = record_journal(&vault, None, JournalOp::Deposit, 100, Pubkey::new_unique(), &clock).unwrap_err();
+    assert_eq!(err, VaultError::JournalRequired.into());
+    
+    // every op keeps its stored value, appended ops never shift the ones already on chain
+    assert_eq!(JournalOp::from_u8(JournalOp::PermitWithdrawal.to_u8()), Some(JournalOp::PermitWithdrawal));
+    assert_eq!(JournalOp::from_u8(JournalOp::Yield.to_u8()), Some(JournalOp::Yield));
+    assert_eq!(JournalOp::PermitWithdrawal.to_u8(), 7);
+}
+
+#[test]
+fn test_legacy_vault_converts_to_fixed_layout() {
+    let owner = Pubkey::new_unique();
+    let delegate = Pubkey::new_unique();
+    let whitelisted = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    
+    let legacy = LegacyCollateralVault {
+        owner,
+        token_account: Pubkey::new_unique(),
+        total_balance: 1_000,
+        locked_balance: 400,
+        available_balance: 600,
+        total_deposited: 1_200,
+        total_withdrawn: 200,
+        created_at: 10,
+        bump: 254,
+        multisig_threshold: 0,
+        authorized_signers: vec![],
+        delegated_users: vec![delegate],
+        withdrawal_timelock: 3_600,
+        pending_withdrawal: Some(LegacyPendingWithdrawal {
+            amount: 100,
+            requested_at: 20,
+            executable_at: 3_620,
+            recipient,
+        }),
+        emergency_mode: false,
+        yield_enabled: true,
+        total_yield_earned: 5,
+        last_yield_compound: 30,
+        whitelist_enabled: true,
+        withdrawal_whitelist: vec![whitelisted],
+        rate_limit_amount: u64::MAX,
+        rate_limit_window: 86_400,
+        rate_limit_window_start: 10,
+        rate_limit_withdrawn: 0,
+        last_update: 30,
+        frozen: false,
+        freeze_reason: 0,
+        frozen_at: 0,
+    };
+    
+    let mut vault = <CollateralVault as bytemuck::Zeroable>::zeroed();
+    legacy.write_into(&mut vault).unwrap();
+    
+    assert_eq!(vault.owner, owner);
+    assert_eq!(vault.total_balance, 1_000);
+    assert_eq!(vault.locked_balance + vault.available_balance, vault.total_balance);
+    assert_eq!(vault.delegates(), &[delegate]);
+    assert_eq!(vault.whitelist(), &[whitelisted]);
+    assert!(vault.signers().is_empty());
+    assert!(vault.is_withdrawal_allowed(&whitelisted));
+    assert!(!vault.is_withdrawal_allowed(&recipient));
+    assert_eq!(vault.pending().unwrap().executable_at, 3_620);
+    assert_eq!(vault.yield_enabled, 1);
+    assert!(!vault.is_frozen());
+    
+    // offsets are part of the contract with off-chain readers now
+    assert_eq!(CollateralVault::LEN, 8 + 1504);
+    
+    // a baseline vault ends at last_update, the freeze fields only exist if there was slack
+    let baseline = legacy.try_to_vec().unwrap();
+    assert!(!LegacyCollateralVault::decode(&baseline).unwrap().frozen);
+    
+    let mut with_tail = baseline.clone();
+    with_tail.push(1);
+    with_tail.push(7);
+    with_tail.extend_from_slice(&42i64.to_le_bytes());
+    let frozen = LegacyCollateralVault::decode(&with_tail).unwrap();
+    assert!(frozen.frozen);
+    assert_eq!(frozen.freeze_reason, 7);
+    assert_eq!(frozen.frozen_at, 42);
+}
+
+#[test]
+fn test_lock_record_bucket_is_out_of_reach_of_plain_unlocks() {
+    let mut vault = <CollateralVault as bytemuck::Zeroable>::zeroed();
+    vault.add_deposit(1_000).unwrap();
+    vault.lock(300).unwrap();
+    vault.lock_into(LockBucket::LockRecords, 400).unwrap();
+    
+    assert_eq!(vault.locked_balance, 700);
+    assert_eq!(vault.free_locked(), 300);
+    
+    // plain unlocks and seizures stop at the unrecorded part
+    assert!(vault.unlock(301).is_err());
+    assert!(vault.seize_locked(301).is_err());
+    vault.seize_locked(300).unwrap();
+    
+    // the record's share only moves through the bucket, and never past what it holds
+    assert!(vault.unlock_from(LockBucket::LockRecords, 401).is_err());
+    vault.seize_from(LockBucket::LockRecords, 100).unwrap();
+    vault.unlock_from(LockBucket::LockRecords, 300).unwrap();
+    
+    assert_eq!(vault.locked_balance, 0);
+    assert_eq!(vault.lock_record_balance, 0);
+    assert_eq!(vault.available_balance, 600);
+    assert_eq!(vault.total_balance, 600);
+}
+
+#[test]
+fn test_sub_account_funds_are_out_of_reach_of_vault_unlocks() {
+    let mut vault = <CollateralVault as bytemuck::Zeroable>::zeroed();
+    vault.add_deposit(1_000).unwrap();
+    vault.lock(200).unwrap();
+    vault.lock_into(LockBucket::SubAccounts, 500).unwrap();
+    
+    // a position manager unlocking or liquidating at the vault level only sees its own 200
+    assert_eq!(vault.free_locked(), 200);
+    assert!(vault.unlock(201).is_err());
+    assert!(vault.seize_locked(201).is_err());
+    
+    vault.seize_from(LockBucket::SubAccounts, 100).unwrap();
+    vault.unlock_from(LockBucket::SubAccounts, 400).unwrap();
+    assert_eq!(vault.sub_account_balance, 0);
+    assert_eq!(vault.locked_balance, 200);
+}
+
+#[test]
+fn test_liquidation_bonus_is_split_from_clamped_seizure() {
+    // 5% bonus, the unclamped case recovers the bonus exactly
+    let requested = 1_000 + ProtocolConfig::apply_bps(1_000, 500).unwrap();
+    assert_eq!(ProtocolConfig::bonus_portion(requested, 500).unwrap(), 50);
+
+    // clamped to 420, the bonus shrinks with it instead of eating the whole cut
+    assert_eq!(ProtocolConfig::bonus_portion(420, 500).unwrap(), 20);
+    assert_eq!(ProtocolConfig::bonus_portion(420, 0).unwrap(), 0);
+}
+
+#[test]
+fn test_allowance_period_rollover_rejects_overflow() {
+    let mut allowance = Allowance {
+        vault: Pubkey::new_unique(),
+        spender: Pubkey::new_unique(),
+        amount_per_period: 100,
+        period: i64::MAX,
+        period_start: 10,
+        pulled_in_period: 0,
+        total_pulled: 0,
+        created_at: 10,
+        bump: 255,
+    };
+    let clock = Clock { unix_timestamp: 20, ..Clock::default() };
+    
+    // period_start + period doesn't fit in an i64, that has to fail instead of wrapping
+    assert!(allowance.check_and_update(50, &clock).is_err());
+    assert_eq!(allowance.pulled_in_period, 0);
+    
+    allowance.period = 5;
+    allowance.check_and_update(50, &clock).unwrap();
+    assert_eq!(allowance.period_start, 20);
+    assert_eq!(allowance.pulled_in_period, 50);
+}
+
+#[test]
+fn test_share_pool_previews_round_in_pool_favour() {
+    // 1000 assets backing 900 shares, share price just above 1.11
+    let pool = SharePool {
+        admin: Pubkey::new_unique(),
+        pool_id: 0,
+        mint: Pubkey::new_unique(),
+        token_account: Pubkey::new_unique(),
+        total_assets: 1_000,
+        total_shares: 900,
+        created_at: 0,
+        bump: 255,
+    };
+    
+    // depositing gets fewer shares, withdrawing the same assets burns more
+    let deposit_shares = pool.preview_deposit(100).unwrap();
+    let withdraw_shares = pool.preview_withdraw(100).unwrap();
+    assert_eq!(deposit_shares, 90);
+    assert_eq!(withdraw_shares, 91);
+    
+    // minting costs more assets than redeeming the same shares pays out
+    let mint_assets = pool.preview_mint(90).unwrap();
+    let redeem_assets = pool.preview_redeem(90).unwrap();
+    assert_eq!(mint_assets, 100);
+    assert_eq!(redeem_assets, 99);
+    
+    // a round trip can never come out ahead
+    assert!(pool.preview_redeem(deposit_shares).unwrap() <= 100);
+}
+
+#[tokio::test]
+async fn test_reclaim_lock_before_expiry_fails() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    open_program_exposure(&mut context, context.owner.pubkey(), vault_pda).await?;
+    let lock_id = 1u64;
+    let lock_record = get_lock_record_pda(&vault_pda, lock_id);
+    let expires_at = chrono_now() + 3600;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::LockCollateralWithExpiry {
+            payer: context.owner.pubkey(),
+            vault: vault_pda,
+            lock_record,
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
+            system_program: anchor_lang::system_program::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::LockCollateralWithExpiry {
+            lock_id,
+            amount: 400_000_000,
+            expires_at: Some(expires_at),
+            reason_code: 1,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ReclaimExpiredLock {
+            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            lock_record,
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
+            journal: None,
+        })
+        .args(collateral_vault::instruction::ReclaimExpiredLock { lock_id })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(vault_pda)
+        .await?;
+    
+    assert_eq!(vault_account.locked_balance, 400_000_000);
+    assert_eq!(vault_account.lock_record_balance, 400_000_000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_vesting_schedule() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let schedule_id = 1u64;
+    let vesting_schedule = get_vesting_schedule_pda(&vault_pda, schedule_id);
+    let now = chrono_now();
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::CreateVestingSchedule {
+            creator: context.owner.pubkey(),
+            vault: vault_pda,
+            vesting_schedule,
+            caller_program: None,
+            program_registry: None,
+            caller_authority: None,
+            program_exposure: None,
+            system_program: anchor_lang::system_program::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::CreateVestingSchedule {
+            schedule_id,
+            kind: VestingKind::Linear,
+            amount: 600_000_000,
+            start_ts: now,
+            cliff_ts: now + 86400,
+            end_ts: now + 86400 * 30,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(vault_pda)
+        .await?;
+    
+    assert_eq!(vault_account.locked_balance, 600_000_000);
+    assert_eq!(vault_account.available_balance, 400_000_000);
+    assert_eq!(vault_account.vesting_balance, 600_000_000);
+    
+    // still before the cliff so nothing can be released yet
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ReleaseVested {
+            vault: vault_pda,
+            vesting_schedule,
+            journal: None,
+            program_registry: None,
+            program_exposure: None,
+        })
+        .args(collateral_vault::instruction::ReleaseVested { schedule_id })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_program_vesting_exposure_comes_down_as_it_vests() {
+    let mut schedule = VestingSchedule {
+        vault: Pubkey::new_unique(),
+        creator: Pubkey::new_unique(),
+        caller_program: Pubkey::new_unique(),
+        schedule_id: 1,
+        kind: VestingKind::Linear,
+        total_amount: 1_000,
+        released_amount: 0,
+        start_ts: 0,
+        cliff_ts: 100,
+        end_ts: 1_000,
+        exposure_held: 1_000,
+        bump: 0,
+    };
+    
+    // accrues from start but nothing before the cliff
+    assert_eq!(schedule.take_releasable(99).unwrap(), 0);
+    assert_eq!(schedule.take_exposure_to_release(), 0);
+    assert_eq!(schedule.take_releasable(250).unwrap(), 250);
+    
+    // only the vested part stops counting against the program
+    assert_eq!(schedule.take_exposure_to_release(), 250);
+    assert_eq!(schedule.take_exposure_to_release(), 0);
+    assert_eq!(schedule.take_releasable(1_000).unwrap(), 750);
+    assert_eq!(schedule.take_exposure_to_release(), 750);
+    assert_eq!(schedule.exposure_held, 0);
+}
+
+#[tokio::test]
+async fn test_liquidate_respects_close_factor() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    open_program_exposure(&mut context, context.owner.pubkey(), get_vault_pda(context.owner.pubkey())).await?;
+    lock_collateral(&mut context, deposit_amount).await?;
+    
+    let liquidator = Keypair::new();
+    let liquidator_vault_pda = get_vault_pda(liquidator.pubkey());
+    initialize_vault_for_user(&mut context, &liquidator).await?;
+    
+    let victim_vault_pda = get_vault_pda(context.owner.pubkey());
+    
+    // asking for everything, close factor defaults to 50% so only half can be seized
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::Liquidate {
+            victim_vault: victim_vault_pda,
+            liquidator_vault: liquidator_vault_pda,
+            victim_token_account: get_vault_token_account(&victim_vault_pda, &context.usdt_mint.pubkey()),
+            liquidator_token_account: get_vault_token_account(&liquidator_vault_pda, &context.usdt_mint.pubkey()),
+            treasury_token_account: None,
+            insurance_fund: None,
+            insurance_token_account: None,
+            protocol_config: get_protocol_config_pda(),
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &victim_vault_pda),
+            lock_record: None,
+            token_program: anchor_spl::token::ID,
+            victim_journal: None,
+            liquidator_journal: None,
+        })
+        .args(collateral_vault::instruction::Liquidate { amount: deposit_amount })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let victim_vault: CollateralVault = context
+        .program
+        .account(victim_vault_pda)
+        .await?;
+    
+    let liquidator_vault: CollateralVault = context
+        .program
+        .account(liquidator_vault_pda)
+        .await?;
+    
+    assert_eq!(victim_vault.locked_balance, deposit_amount / 2);
+    assert_eq!(victim_vault.available_balance, 0);
+    assert_eq!(liquidator_vault.total_balance, deposit_amount / 2);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cover_shortfall_credits_vault_from_insurance_fund() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let fund_pda = get_insurance_fund_pda();
+    let fund_token_account = get_vault_token_account(&fund_pda, &context.usdt_mint.pubkey());
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::InitializeInsuranceFund {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            insurance_fund: fund_pda,
+            fund_token_account,
+            mint: context.usdt_mint.pubkey(),
+            token_program: anchor_spl::token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::InitializeInsuranceFund { fee_share_bps: 5_000 })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    // liquidations can't leave the fund out from here on
+    let config: ProtocolConfig = context.program.account(get_protocol_config_pda()).await?;
+    assert!(config.has_insurance_fund);
+    
+    let contribution = 300_000_000;
+    mint_tokens(&mut context, contribution).await?;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ContributeInsuranceFund {
+            contributor: context.owner.pubkey(),
+            insurance_fund: fund_pda,
+            contributor_token_account: context.user_token_account,
+            fund_token_account,
+            token_program: anchor_spl::token::ID,
+        })
+        .args(collateral_vault::instruction::ContributeInsuranceFund { amount: contribution })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let shortfall = 100_000_000;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::CoverShortfall {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            insurance_fund: fund_pda,
+            fund_token_account,
+            vault: vault_pda,
+            vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::CoverShortfall { amount: shortfall, reason_code: 1 })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let fund: InsuranceFund = context
+        .program
+        .account(fund_pda)
+        .await?;
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(vault_pda)
+        .await?;
+    
+    assert_eq!(fund.total_contributed, contribution);
+    assert_eq!(fund.total_paid_out, shortfall);
+    assert_eq!(vault_account.available_balance, shortfall);
+    
+    // the fund only holds what's left of the contribution
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::CoverShortfall {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            insurance_fund: fund_pda,
+            fund_token_account,
+            vault: vault_pda,
+            vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::CoverShortfall { amount: contribution, reason_code: 1 })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cover_shortfall_is_admin_only_and_capped() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    let max_vault_balance = 1_000_000_000;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, max_vault_balance, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let fund_token_account = initialize_insurance_fund(&mut context, 5_000).await?;
+    let contribution = 300_000_000;
+    mint_tokens(&mut context, contribution).await?;
+    contribute_insurance_fund(&mut context, contribution).await?;
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let cover = |admin: Pubkey, amount: u64| {
+        context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::CoverShortfall {
+                admin,
+                protocol_config: get_protocol_config_pda(),
+                insurance_fund: get_insurance_fund_pda(),
+                fund_token_account,
+                vault: vault_pda,
+                vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+                token_program: anchor_spl::token::ID,
+                journal: None,
+            })
+            .args(collateral_vault::instruction::CoverShortfall { amount, reason_code: 1 })
+    };
+    
+    // only the config admin can draw on the fund
+    let stranger = Keypair::new();
+    let result = cover(stranger.pubkey(), 100_000_000).signer(&stranger).send().await;
+    assert!(result.is_err());
+    
+    // a payout can't push the vault past the per-vault cap either
+    mint_tokens(&mut context, max_vault_balance).await?;
+    deposit(&mut context, max_vault_balance).await?;
+    let result = cover(context.owner.pubkey(), 1).signer(&context.owner).send().await;
+    assert!(result.is_err());
+    
+    let fund: InsuranceFund = context.program.account(get_insurance_fund_pda()).await?;
+    assert_eq!(fund.total_paid_out, 0);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_liquidation_fee_is_shared_with_insurance_fund() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let victim_vault_pda = get_vault_pda(context.owner.pubkey());
+    open_program_exposure(&mut context, context.owner.pubkey(), victim_vault_pda).await?;
+    lock_collateral(&mut context, deposit_amount).await?;
+    
+    let liquidator = Keypair::new();
+    let liquidator_vault_pda = get_vault_pda(liquidator.pubkey());
+    initialize_vault_for_user(&mut context, &liquidator).await?;
+    
+    // no bonus, a 10% fee and the fund takes half of it
+    let treasury = context.user_token_account;
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::UpdateProtocolConfig {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+        })
+        .args(collateral_vault::instruction::ConfigureLiquidation {
+            treasury,
+            liquidation_bonus_bps: 0,
+            protocol_fee_bps: 1_000,
+            close_factor_bps: 10_000,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    assert!(tx.is_success());
+    
+    let fund_token_account = initialize_insurance_fund(&mut context, 5_000).await?;
+    
+    let liquidate = |insurance: bool| {
+        context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::Liquidate {
+                victim_vault: victim_vault_pda,
+                liquidator_vault: liquidator_vault_pda,
+                victim_token_account: get_vault_token_account(&victim_vault_pda, &context.usdt_mint.pubkey()),
+                liquidator_token_account: get_vault_token_account(&liquidator_vault_pda, &context.usdt_mint.pubkey()),
+                treasury_token_account: Some(treasury),
+                insurance_fund: insurance.then(get_insurance_fund_pda),
+                insurance_token_account: insurance.then_some(fund_token_account),
+                protocol_config: get_protocol_config_pda(),
+                caller_program: context.owner.pubkey(),
+                program_registry: get_program_registry_pda(&context.owner.pubkey()),
+                caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+                program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &victim_vault_pda),
+                lock_record: None,
+                token_program: anchor_spl::token::ID,
+                victim_journal: None,
+                liquidator_journal: None,
+            })
+            .args(collateral_vault::instruction::Liquidate { amount: 500_000_000 })
+            .signer(&context.owner)
+    };
+    
+    // once the fund exists it can't be left out
+    assert!(liquidate(false).send().await.is_err());
+    assert!(liquidate(true).send().await?.is_success());
+    
+    let fund: InsuranceFund = context.program.account(get_insurance_fund_pda()).await?;
+    let victim_vault: CollateralVault = context.program.account(victim_vault_pda).await?;
+    let liquidator_vault: CollateralVault = context.program.account(liquidator_vault_pda).await?;
+    let config: ProtocolConfig = context.program.account(get_protocol_config_pda()).await?;
+    
+    assert_eq!(victim_vault.locked_balance, 500_000_000);
+    assert_eq!(liquidator_vault.total_balance, 450_000_000);
+    assert_eq!(fund.total_contributed, 25_000_000);
+    
+    // the whole fee left the vaults, the fund's share included
+    assert_eq!(config.total_value_locked, deposit_amount - 50_000_000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_settle_batch_rejects_out_of_range_leg() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_vault(&mut context).await?;
+    
+    let counterparty = Keypair::new();
+    initialize_vault_for_user(&mut context, &counterparty).await?;
+    
+    let vault_a = get_vault_pda(context.owner.pubkey());
+    let vault_b = get_vault_pda(counterparty.pubkey());
+    
+    let legs = vec![
+        SettlementLeg { from_vault: 0, to_vault: 1, amount: 100_000_000 },
+        SettlementLeg { from_vault: 1, to_vault: 2, amount: 50_000_000 },
+    ];
+    
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::SettleBatch {
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+        })
+        .accounts(vec![
+            AccountMeta::new(vault_a, false),
+            AccountMeta::new(get_vault_token_account(&vault_a, &context.usdt_mint.pubkey()), false),
+            AccountMeta::new(vault_b, false),
+            AccountMeta::new(get_vault_token_account(&vault_b, &context.usdt_mint.pubkey()), false),
+        ])
+        .args(collateral_vault::instruction::SettleBatch { legs })
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_settle_batch_rejects_frozen_or_capped_creditor() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    let max_vault_balance = 1_000_000_000;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, max_vault_balance, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    mint_tokens(&mut context, max_vault_balance).await?;
+    deposit(&mut context, max_vault_balance).await?;
+    
+    let counterparty = Keypair::new();
+    initialize_vault_for_user(&mut context, &counterparty).await?;
+    
+    let vault_a = get_vault_pda(context.owner.pubkey());
+    let vault_b = get_vault_pda(counterparty.pubkey());
+    let settle = |context: &TestContext, from_vault: u8, to_vault: u8, amount: u64| {
+        context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::SettleBatch {
+                caller_program: context.owner.pubkey(),
+                program_registry: get_program_registry_pda(&context.owner.pubkey()),
+                caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+                protocol_config: get_protocol_config_pda(),
+                token_program: anchor_spl::token::ID,
+            })
+            .accounts(vec![
+                AccountMeta::new(vault_a, false),
+                AccountMeta::new(get_vault_token_account(&vault_a, &context.usdt_mint.pubkey()), false),
+                AccountMeta::new(vault_b, false),
+                AccountMeta::new(get_vault_token_account(&vault_b, &context.usdt_mint.pubkey()), false),
+            ])
+            .args(collateral_vault::instruction::SettleBatch {
+                legs: vec![SettlementLeg { from_vault, to_vault, amount }],
+            })
+            .signer(&context.owner)
+    };
+    
+    let settled = 400_000_000;
+    assert!(settle(&context, 0, 1, settled).send().await?.is_success());
+    
+    // vault_a is back at the cap, so being paid by the settlement fails like a deposit would
+    mint_tokens(&mut context, settled).await?;
+    deposit(&mut context, settled).await?;
+    let result = settle(&context, 1, 0, 100_000_000).send().await;
+    assert!(result.is_err());
+    
+    // a frozen vault can't be paid into any more than it can be debited
+    freeze_vault(&mut context, vault_b, 1).await?;
+    let result = settle(&context, 0, 1, 100_000_000).send().await;
+    assert!(result.is_err());
+    
+    let counterparty_vault: CollateralVault = context.program.account(vault_b).await?;
+    assert_eq!(counterparty_vault.total_balance, settled);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_deposit() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let amounts = vec![100_000_000, 200_000_000, 300_000_000];
+    let total_amount: u64 = amounts.iter().sum();
+    
+    mint_tokens(&mut context, total_amount).await?;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::BatchDeposit {
+            user: context.owner.pubkey(),
+            vault: get_vault_pda(context.owner.pubkey()),
+            user_token_account: context.user_token_account,
+            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+            owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::BatchDeposit { amounts: amounts.clone() })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    assert_eq!(vault_account.total_balance, total_amount);
+    assert_eq!(vault_account.total_deposited, total_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_initialize_protocol_config() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    
+    let max_vault_balance = 5_000_000_000;
+    let global_deposit_cap = 100_000_000_000;
+    
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, max_vault_balance, global_deposit_cap).await?;
+    
+    let config: ProtocolConfig = context
+        .program
+        .account(get_protocol_config_pda())
+        .await?;
+    
+    assert_eq!(config.admin, context.owner.pubkey());
+    assert_eq!(config.max_vault_balance, max_vault_balance);
+    assert_eq!(config.global_deposit_cap, global_deposit_cap);
+    assert_eq!(config.total_value_locked, 0);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_protocol_config_is_required_and_set_up_by_the_admin() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    
+    // nothing can be deposited until the config exists
+    assert!(deposit(&mut context, deposit_amount).await.is_err());
+    
+    initialize_authority(&mut context, vec![]).await?;
+    
+    // whoever gets there first doesn't become the config admin
+    let stranger = Keypair::new();
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::InitializeProtocolConfig {
+            admin: stranger.pubkey(),
+            vault_authority: get_authority_pda(),
+            protocol_config: get_protocol_config_pda(),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::InitializeProtocolConfig {
+            max_vault_balance: u64::MAX,
+            global_deposit_cap: u64::MAX,
+        })
+        .signer(&stranger)
+        .send()
+        .await;
+    assert!(result.is_err());
+    
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let config: ProtocolConfig = context
+        .program
+        .account(get_protocol_config_pda())
+        .await?;
+    assert_eq!(config.admin, context.owner.pubkey());
+    assert_eq!(config.total_value_locked, deposit_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deposit_exceeds_vault_cap() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, 500_000_000, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    
+    let result = deposit(&mut context, deposit_amount).await;
+    assert!(result.is_err());
+    
+    deposit(&mut context, 500_000_000).await?;
+    
+    let config: ProtocolConfig = context
+        .program
+        .account(get_protocol_config_pda())
+        .await?;
+    
+    assert_eq!(config.total_value_locked, 500_000_000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deposit_for_credits_beneficiary() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let beneficiary = Keypair::new();
+    initialize_vault_for_user(&mut context, &beneficiary).await?;
+    let beneficiary_vault = get_vault_pda(beneficiary.pubkey());
+    
+    let deposit_amount = 400_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    
+    // owner's wallet pays, beneficiary never signs
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::DepositFor {
+            payer: context.owner.pubkey(),
+            beneficiary: beneficiary.pubkey(),
+            vault: beneficiary_vault,
+            payer_token_account: context.user_token_account,
+            vault_token_account: get_vault_token_account(&beneficiary_vault, &context.usdt_mint.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::DepositFor {
+            amount: deposit_amount,
+            memo: Some("invoice-42".to_string()),
+            reference: Some([7u8; 32]),
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context.program.account(beneficiary_vault).await?;
+    assert_eq!(vault_account.total_balance, deposit_amount);
+    assert_eq!(vault_account.available_balance, deposit_amount);
+    
+    let payer_vault: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    assert_eq!(payer_vault.total_balance, 0);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_balance_fails_when_in_sync() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 500_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    
+    // everything came in through deposit, so there's nothing unaccounted to credit
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::SyncBalance {
+            vault: vault_pda,
+            vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            journal: None,
+        })
+        .args(collateral_vault::instruction::SyncBalance {})
+        .signer(&context.owner)
+        .send()
+        .await;
+    
+    assert!(result.is_err());
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.total_balance, deposit_amount);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pull_allowance_respects_period_limit() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let spender = Keypair::new();
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let allowance_pda = get_allowance_pda(&vault_pda, &spender.pubkey());
+    let per_period = 100_000_000;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::GrantAllowance {
+            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            allowance: allowance_pda,
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::GrantAllowance {
+            spender: spender.pubkey(),
+            amount_per_period: per_period,
+            period: 30 * 86400,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let pull = |amount: u64| {
+        context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::PullAllowance {
+                spender: spender.pubkey(),
+                vault: vault_pda,
+                allowance: allowance_pda,
+                vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+                destination_token_account: context.user_token_account,
+                protocol_config: get_protocol_config_pda(),
+                token_program: anchor_spl::token::ID,
+                journal: None,
+            })
+            .args(collateral_vault::instruction::PullAllowance { amount })
+            .signer(&spender)
+            .send()
+    };
+    
+    assert!(pull(60_000_000).await?.is_success());
+    
+    // 60 + 60 is over the 100 per period
+    assert!(pull(60_000_000).await.is_err());
+    
+    let allowance: Allowance = context.program.account(allowance_pda).await?;
+    assert_eq!(allowance.pulled_in_period, 60_000_000);
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.total_balance, deposit_amount - 60_000_000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_withdraw() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let amounts = vec![100_000_000, 200_000_000, 300_000_000];
+    let total_withdraw: u64 = amounts.iter().sum();
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::BatchWithdraw {
+            user: context.owner.pubkey(),
+            vault: get_vault_pda(context.owner.pubkey()),
+            user_token_account: context.user_token_account,
+            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+            owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::BatchWithdraw { amounts: amounts.clone() })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(get_vault_pda(context.owner.pubkey()))
+        .await?;
+    
+    assert_eq!(vault_account.total_balance, deposit_amount - total_withdraw);
+    assert_eq!(vault_account.total_withdrawn, total_withdraw);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_execute_nets_transfers() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    
+    let whitelisted = Pubkey::from_str("11111111111111111111111111111111").unwrap();
+    let operations = vec![
+        BatchOperation::Deposit(BatchDepositItem { amount: 700_000_000 }),
+        BatchOperation::Deposit(BatchDepositItem { amount: 300_000_000 }),
+        BatchOperation::Withdraw(BatchWithdrawItem { amount: 250_000_000 }),
+        BatchOperation::AddToWhitelist { address: whitelisted },
+    ];
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::BatchExecute {
+            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            user_token_account: context.user_token_account,
+            vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            journal: None,
+        })
+        .args(collateral_vault::instruction::BatchExecute { operations })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_account: CollateralVault = context
+        .program
+        .account(vault_pda)
+        .await?;
+    
+    // only the net 750 ever moved, so that's all the lifetime totals record
+    assert_eq!(vault_account.total_balance, 750_000_000);
+    assert_eq!(vault_account.total_deposited, 750_000_000);
+    assert_eq!(vault_account.total_withdrawn, 0);
+    assert_eq!(vault_account.rate_limit_withdrawn, 0);
+    assert!(vault_account.whitelist().contains(&whitelisted));
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_initialize_authority() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::InitializeAuthority {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::InitializeAuthority { authorized_programs: vec![] })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let authority: VaultAuthority = context
+        .program
+        .account(get_authority_pda())
+        .await?;
+    
+    assert_eq!(authority.admin, context.owner.pubkey());
+    assert!(authority.authorized_programs.is_empty());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_add_authorized_program() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    
+    let new_program = Pubkey::from_str("33333333333333333333333333333333").unwrap();
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::UpdateAuthority {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+        })
+        .args(collateral_vault::instruction::AddAuthorizedProgram { program: new_program })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_authorized_program() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    
+    let legacy_program = Pubkey::from_str("33333333333333333333333333333333").unwrap();
+    
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::InitializeAuthority {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::InitializeAuthority {
+            authorized_programs: vec![legacy_program],
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::MigrateAuthorizedProgram {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            program_registry: get_program_registry_pda(&legacy_program),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::MigrateAuthorizedProgram { program: legacy_program })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let authority: VaultAuthority = context
+        .program
+        .account(get_authority_pda())
+        .await?;
+    assert!(authority.authorized_programs.is_empty());
+    
+    let registry: ProgramRegistry = context
+        .program
+        .account(get_program_registry_pda(&legacy_program))
+        .await?;
+    assert_eq!(registry.limits, AuthorizedProgram::unrestricted(legacy_program));
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_register_program() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    
+    let new_program = Pubkey::from_str("33333333333333333333333333333333").unwrap();
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::RegisterProgram {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            program_registry: get_program_registry_pda(&new_program),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::RegisterProgram { program: new_program })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let registry: ProgramRegistry = context
+        .program
+        .account(get_program_registry_pda(&new_program))
+        .await?;
+    
+    assert_eq!(registry.limits, AuthorizedProgram::unrestricted(new_program));
+    
+    // the registry PDA already exists, so a second registration can't init it
+    let result = register_program(&mut context, new_program).await;
+    assert!(result.is_err());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lock_respects_program_exposure_cap() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let exposure_cap = 400_000_000;
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ConfigureProgramLimits {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+        })
+        .args(collateral_vault::instruction::ConfigureProgramLimits {
+            program: context.owner.pubkey(),
+            enabled: true,
+            permissions: AuthorizedProgram::PERMISSION_LOCK | AuthorizedProgram::PERMISSION_UNLOCK,
+            max_lock_per_vault: u64::MAX,
+            global_exposure_cap: exposure_cap,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    open_program_exposure(&mut context, context.owner.pubkey(), vault_pda).await?;
+    let lock = |amount: u64| {
+        context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::LockCollateral {
+                vault: vault_pda,
+                caller_program: context.owner.pubkey(),
+                program_registry: get_program_registry_pda(&context.owner.pubkey()),
+                caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+                program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
+                journal: None,
+            })
+            .args(collateral_vault::instruction::LockCollateral { amount })
+            .signer(&context.owner)
+    };
+    
+    assert!(lock(exposure_cap).send().await?.is_success());
+    
+    // the vault has plenty available but the program is at its cap
+    let result = lock(1).send().await;
+    assert!(result.is_err());
+    
+    let registry: ProgramRegistry = context
         .program
-        .account(get_vault_pda(context.owner.pubkey()))
+        .account(get_program_registry_pda(&context.owner.pubkey()))
         .await?;
+    assert_eq!(registry.limits.current_exposure, exposure_cap);
     
-    assert_eq!(vault_account.total_balance, deposit_amount - withdraw_amount);
-    assert_eq!(vault_account.available_balance, deposit_amount - withdraw_amount);
-    assert_eq!(vault_account.total_withdrawn, withdraw_amount);
-    
-    Ok(())
-}
-
-#[tokio::test]
-async fn test_withdraw_insufficient_balance() -> Result<()> {
-    let mut context = setup_test_context().await?;
-    initialize_vault(&mut context).await?;
+    let exposure: ProgramExposure = context
+        .program
+        .account(get_program_exposure_pda(&context.owner.pubkey(), &vault_pda))
+        .await?;
+    assert_eq!(exposure.locked, exposure_cap);
     
-    let deposit_amount = 1_000_000_000;
-    mint_tokens(&mut context, deposit_amount).await?;
-    deposit(&mut context, deposit_amount).await?;
+    // no transfer permission was granted
+    let other = Keypair::new();
+    let other_vault_pda = get_vault_pda(other.pubkey());
+    initialize_vault_for_user(&mut context, &other).await?;
     
     let result = context
         .program
         .request()
-        .accounts(collateral_vault::accounts::Withdraw {
-            user: context.owner.pubkey(),
-            vault: get_vault_pda(context.owner.pubkey()),
-            user_token_account: context.user_token_account,
-            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
-            owner: context.owner.pubkey(),
+        .accounts(collateral_vault::accounts::TransferCollateral {
+            from_vault: vault_pda,
+            to_vault: other_vault_pda,
+            from_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            to_token_account: get_vault_token_account(&other_vault_pda, &context.usdt_mint.pubkey()),
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
             token_program: anchor_spl::token::ID,
+            from_journal: None,
+            to_journal: None,
         })
-        .args(collateral_vault::instruction::Withdraw { amount: deposit_amount + 1 })
+        .args(collateral_vault::instruction::TransferCollateral { amount: 1 })
         .signer(&context.owner)
         .send()
         .await;
@@ -198,66 +2252,164 @@ async fn test_withdraw_insufficient_balance() -> Result<()> {
 }
 
 #[tokio::test]
-async fn test_lock_collateral() -> Result<()> {
+async fn test_frozen_vault_cannot_be_paid_into() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
+    let lock_amount = 400_000_000;
     mint_tokens(&mut context, deposit_amount).await?;
     deposit(&mut context, deposit_amount).await?;
     
-    let lock_amount = 500_000_000;
+    let frozen = Keypair::new();
+    initialize_vault_for_user(&mut context, &frozen).await?;
     
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let frozen_vault = get_vault_pda(frozen.pubkey());
+    
+    // escrow toward it while it's still open, then freeze it
+    let escrow_id = 1u64;
+    let escrow_pda = get_escrow_pda(&vault_pda, escrow_id);
+    let preimage = b"frozen-target".to_vec();
     let tx = context
         .program
         .request()
-        .accounts(collateral_vault::accounts::LockCollateral {
-            user: context.owner.pubkey(),
-            vault: get_vault_pda(context.owner.pubkey()),
+        .accounts(collateral_vault::accounts::CreateHashlockEscrow {
             owner: context.owner.pubkey(),
+            from_vault: vault_pda,
+            to_vault: frozen_vault,
+            escrow: escrow_pda,
+            system_program: anchor_lang::system_program::ID,
+            from_journal: None,
+        })
+        .args(collateral_vault::instruction::CreateHashlockEscrow {
+            escrow_id,
+            amount: 100_000_000,
+            hashlock: solana_sdk::hash::hash(&preimage).to_bytes(),
+            deadline: chrono_now() + 3600,
         })
-        .args(collateral_vault::instruction::LockCollateral { amount: lock_amount })
         .signer(&context.owner)
         .send()
         .await?;
-    
     assert!(tx.is_success());
     
-    let vault_account: CollateralVault = context
+    open_program_exposure(&mut context, context.owner.pubkey(), vault_pda).await?;
+    lock_collateral(&mut context, lock_amount).await?;
+    freeze_vault(&mut context, frozen_vault, 2).await?;
+    
+    // an owner transfer
+    let result = transfer_to_vault(&mut context, vault_pda, frozen_vault, 100_000_000).await;
+    assert!(result.is_err());
+    
+    // an escrow claim
+    let result = context
         .program
-        .account(get_vault_pda(context.owner.pubkey()))
-        .await?;
+        .request()
+        .accounts(collateral_vault::accounts::ClaimHashlockEscrow {
+            from_vault: vault_pda,
+            to_vault: frozen_vault,
+            escrow: escrow_pda,
+            sender: context.owner.pubkey(),
+            from_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            to_token_account: get_vault_token_account(&frozen_vault, &context.usdt_mint.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            from_journal: None,
+            to_journal: None,
+        })
+        .args(collateral_vault::instruction::ClaimHashlockEscrow { escrow_id, preimage })
+        .signer(&frozen)
+        .send()
+        .await;
+    assert!(result.is_err());
     
-    assert_eq!(vault_account.locked_balance, lock_amount);
-    assert_eq!(vault_account.available_balance, deposit_amount - lock_amount);
-    assert_eq!(vault_account.total_balance, deposit_amount);
+    // liquidation proceeds
+    let result = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::Liquidate {
+            victim_vault: vault_pda,
+            liquidator_vault: frozen_vault,
+            victim_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+            liquidator_token_account: get_vault_token_account(&frozen_vault, &context.usdt_mint.pubkey()),
+            treasury_token_account: None,
+            insurance_fund: None,
+            insurance_token_account: None,
+            protocol_config: get_protocol_config_pda(),
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
+            lock_record: None,
+            token_program: anchor_spl::token::ID,
+            victim_journal: None,
+            liquidator_journal: None,
+        })
+        .args(collateral_vault::instruction::Liquidate { amount: lock_amount })
+        .signer(&context.owner)
+        .send()
+        .await;
+    assert!(result.is_err());
+    
+    let frozen_account: CollateralVault = context.program.account(frozen_vault).await?;
+    assert_eq!(frozen_account.total_balance, 0);
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.locked_balance, lock_amount + 100_000_000);
+    assert_eq!(vault_account.escrow_balance, 100_000_000);
     
     Ok(())
 }
 
 #[tokio::test]
-async fn test_unlock_collateral() -> Result<()> {
+async fn test_frozen_vault_blocks_owner_but_allows_unlock() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
+    let lock_amount = 400_000_000;
     mint_tokens(&mut context, deposit_amount).await?;
     deposit(&mut context, deposit_amount).await?;
-    
-    let lock_amount = 500_000_000;
+    open_program_exposure(&mut context, context.owner.pubkey(), get_vault_pda(context.owner.pubkey())).await?;
     lock_collateral(&mut context, lock_amount).await?;
     
-    let unlock_amount = 300_000_000;
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::FreezeVault {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            vault: vault_pda,
+        })
+        .args(collateral_vault::instruction::FreezeVault { reason_code: 3 })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    let result = withdraw(&mut context, 1).await;
+    assert!(result.is_err());
     
+    // the position manager can still release what it had locked
     let tx = context
         .program
         .request()
         .accounts(collateral_vault::accounts::UnlockCollateral {
-            user: context.owner.pubkey(),
-            vault: get_vault_pda(context.owner.pubkey()),
-            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            caller_program: context.owner.pubkey(),
+            program_registry: get_program_registry_pda(&context.owner.pubkey()),
+            caller_authority: get_caller_authority_pda(&context.owner.pubkey()),
+            program_exposure: get_program_exposure_pda(&context.owner.pubkey(), &vault_pda),
+            lock_record: None,
+            journal: None,
         })
-        .args(collateral_vault::instruction::UnlockCollateral { amount: unlock_amount })
+        .args(collateral_vault::instruction::UnlockCollateral { amount: lock_amount })
         .signer(&context.owner)
         .send()
         .await?;
@@ -266,195 +2418,352 @@ async fn test_unlock_collateral() -> Result<()> {
     
     let vault_account: CollateralVault = context
         .program
-        .account(get_vault_pda(context.owner.pubkey()))
+        .account(vault_pda)
         .await?;
     
-    assert_eq!(vault_account.locked_balance, lock_amount - unlock_amount);
-    assert_eq!(vault_account.available_balance, deposit_amount - lock_amount + unlock_amount);
+    assert!(vault_account.is_frozen());
+    assert_eq!(vault_account.freeze_reason, 3);
+    assert_eq!(vault_account.locked_balance, 0);
+    
+    let tx = context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::FreezeVault {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            vault: vault_pda,
+        })
+        .args(collateral_vault::instruction::UnfreezeVault {})
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    assert!(tx.is_success());
+    
+    withdraw(&mut context, deposit_amount).await?;
     
     Ok(())
 }
 
 #[tokio::test]
-async fn test_transfer_collateral() -> Result<()> {
+async fn test_view_instructions_return_versioned_data() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![context.owner.pubkey()]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
+    let lock_amount = 250_000_000;
     mint_tokens(&mut context, deposit_amount).await?;
     deposit(&mut context, deposit_amount).await?;
+    lock_collateral(&mut context, lock_amount).await?;
     
-    let recipient = Keypair::new();
-    let recipient_vault_pda = get_vault_pda(recipient.pubkey());
-    
-    // Initialize recipient vault
-    initialize_vault_for_user(&mut context, &recipient).await?;
-    
-    let transfer_amount = 300_000_000;
+    let vault_pda = get_vault_pda(context.owner.pubkey());
     
-    let tx = context
+    let summary: VaultSummary = context
         .program
         .request()
-        .accounts(collateral_vault::accounts::TransferCollateral {
-            from_user: context.owner.pubkey(),
-            from_vault: get_vault_pda(context.owner.pubkey()),
-            to_vault: recipient_vault_pda,
-            from_vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
-            to_vault_token_account: get_vault_token_account(&recipient_vault_pda, &context.usdt_mint.pubkey()),
-            owner: context.owner.pubkey(),
-            token_program: anchor_spl::token::ID,
-        })
-        .args(collateral_vault::instruction::TransferCollateral { amount: transfer_amount })
-        .signer(&context.owner)
-        .send()
+        .accounts(collateral_vault::accounts::ViewVault { vault: vault_pda })
+        .args(collateral_vault::instruction::GetVaultSummary {})
+        .view()
         .await?;
     
-    assert!(tx.is_success());
+    assert_eq!(summary.version, VIEW_VERSION);
+    assert_eq!(summary.owner, context.owner.pubkey());
+    assert_eq!(summary.total_balance, deposit_amount);
+    assert_eq!(summary.locked_balance, lock_amount);
+    assert!(!summary.frozen);
+    // VaultInitialized, DepositEvent, LockEvent
+    assert_eq!(summary.event_seq, 3);
     
-    let from_vault: CollateralVault = context
+    let withdrawable: WithdrawableView = context
         .program
-        .account(get_vault_pda(context.owner.pubkey()))
+        .request()
+        .accounts(collateral_vault::accounts::ViewVault { vault: vault_pda })
+        .args(collateral_vault::instruction::GetWithdrawable {})
+        .view()
         .await?;
     
-    let to_vault: CollateralVault = context
+    assert_eq!(withdrawable.withdrawable, deposit_amount - lock_amount);
+    assert_eq!(withdrawable.pending_amount, 0);
+    
+    let rate_limit: RateLimitView = context
         .program
-        .account(recipient_vault_pda)
+        .request()
+        .accounts(collateral_vault::accounts::ViewVault { vault: vault_pda })
+        .args(collateral_vault::instruction::GetRemainingRateLimit {})
+        .view()
         .await?;
     
-    assert_eq!(from_vault.total_balance, deposit_amount - transfer_amount);
-    assert_eq!(to_vault.total_balance, transfer_amount);
+    assert_eq!(rate_limit.version, VIEW_VERSION);
+    assert_eq!(rate_limit.remaining, rate_limit.limit_amount);
+    assert_eq!(rate_limit.withdrawn_in_window, 0);
     
     Ok(())
 }
 
 #[tokio::test]
-async fn test_batch_deposit() -> Result<()> {
+async fn test_scheduled_withdrawal_cranked_by_keeper() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
-    let amounts = vec![100_000_000, 200_000_000, 300_000_000];
-    let total_amount: u64 = amounts.iter().sum();
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
     
-    mint_tokens(&mut context, total_amount).await?;
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let recipient = context.owner.pubkey();
     
     let tx = context
         .program
         .request()
-        .accounts(collateral_vault::accounts::BatchDeposit {
+        .accounts(collateral_vault::accounts::ManageWhitelist {
             user: context.owner.pubkey(),
-            vault: get_vault_pda(context.owner.pubkey()),
-            user_token_account: context.user_token_account,
-            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+            vault: vault_pda,
             owner: context.owner.pubkey(),
-            token_program: anchor_spl::token::ID,
         })
-        .args(collateral_vault::instruction::BatchDeposit { amounts: amounts.clone() })
+        .args(collateral_vault::instruction::AddToWhitelist { address: recipient })
         .signer(&context.owner)
         .send()
         .await?;
-    
     assert!(tx.is_success());
     
-    let vault_account: CollateralVault = context
+    let schedule_id = 1u64;
+    let schedule_pda = get_scheduled_withdrawal_pda(&vault_pda, schedule_id);
+    let amount = 100_000_000;
+    let keeper_tip = 1_000_000;
+    
+    let tx = context
         .program
-        .account(get_vault_pda(context.owner.pubkey()))
+        .request()
+        .accounts(collateral_vault::accounts::CreateScheduledWithdrawal {
+            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            scheduled_withdrawal: schedule_pda,
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::CreateScheduledWithdrawal {
+            schedule_id,
+            recipient,
+            amount,
+            interval: 86_400,
+            first_execution_at: chrono_now(),
+            max_executions: 2,
+            keeper_tip,
+        })
+        .signer(&context.owner)
+        .send()
         .await?;
+    assert!(tx.is_success());
     
-    assert_eq!(vault_account.total_balance, total_amount);
-    assert_eq!(vault_account.total_deposited, total_amount);
+    // the keeper is an unrelated signer and takes its tip into the owner's account here
+    let keeper = Keypair::new();
+    let vault_token_account = get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey());
+    
+    for run in 0..2 {
+        let result = context
+            .program
+            .request()
+            .accounts(collateral_vault::accounts::ExecuteScheduledWithdrawal {
+                keeper: keeper.pubkey(),
+                vault: vault_pda,
+                scheduled_withdrawal: schedule_pda,
+                vault_token_account,
+                recipient_token_account: context.user_token_account,
+                keeper_token_account: context.user_token_account,
+                protocol_config: get_protocol_config_pda(),
+                token_program: anchor_spl::token::ID,
+                journal: None,
+            })
+            .args(collateral_vault::instruction::ExecuteScheduledWithdrawal { schedule_id })
+            .signer(&keeper)
+            .send()
+            .await;
+        
+        if run == 0 {
+            assert!(result?.is_success());
+        } else {
+            // the next run is a day out, cranking again right away has to fail
+            assert!(result.is_err());
+        }
+    }
+    
+    let schedule: ScheduledWithdrawal = context.program.account(schedule_pda).await?;
+    assert_eq!(schedule.executions, 1);
+    assert_eq!(schedule.total_paid, amount);
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.total_balance, deposit_amount - amount - keeper_tip);
     
     Ok(())
 }
 
 #[tokio::test]
-async fn test_batch_withdraw() -> Result<()> {
+async fn test_withdraw_with_permit_relayed_once() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
     mint_tokens(&mut context, deposit_amount).await?;
     deposit(&mut context, deposit_amount).await?;
     
-    let amounts = vec![100_000_000, 200_000_000, 300_000_000];
-    let total_withdraw: u64 = amounts.iter().sum();
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let amount = 200_000_000;
+    let permit = WithdrawalPermit {
+        vault: vault_pda,
+        amount,
+        recipient: context.owner.pubkey(),
+        nonce: 0,
+        expires_at: chrono_now() + 600,
+    };
+    
+    // the owner only signs the message, the relayer is the one sending the transaction
+    let signature = context.owner.sign_message(&permit.message());
+    let relayer = Keypair::new();
+    
+    for attempt in 0..2 {
+        let result = context
+            .program
+            .request()
+            .instruction(ed25519_verify_ix(&context.owner.pubkey(), signature.as_ref(), &permit.message()))
+            .accounts(collateral_vault::accounts::WithdrawWithPermit {
+                relayer: relayer.pubkey(),
+                vault: vault_pda,
+                vault_token_account: get_vault_token_account(&vault_pda, &context.usdt_mint.pubkey()),
+                recipient_token_account: context.user_token_account,
+                owner: context.owner.pubkey(),
+                protocol_config: get_protocol_config_pda(),
+                instructions: solana_sdk::sysvar::instructions::ID,
+                token_program: anchor_spl::token::ID,
+                journal: None,
+            })
+            .args(collateral_vault::instruction::WithdrawWithPermit { permit: permit.clone() })
+            .signer(&relayer)
+            .send()
+            .await;
+        
+        if attempt == 0 {
+            assert!(result?.is_success());
+        } else {
+            // the nonce moved on, so the same signed permit can't be replayed
+            assert!(result.is_err());
+        }
+    }
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.total_balance, deposit_amount - amount);
+    assert_eq!(vault_account.permit_nonce, 1);
     
-    let tx = context
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_key_scope_and_cap() -> Result<()> {
+    let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
+    initialize_vault(&mut context).await?;
+    
+    let deposit_amount = 1_000_000_000;
+    mint_tokens(&mut context, deposit_amount).await?;
+    deposit(&mut context, deposit_amount).await?;
+    
+    let vault_pda = get_vault_pda(context.owner.pubkey());
+    let sub_account_id = 1u16;
+    let sub_account_pda = get_sub_account_pda(&vault_pda, sub_account_id);
+    
+    context
         .program
         .request()
-        .accounts(collateral_vault::accounts::BatchWithdraw {
-            user: context.owner.pubkey(),
-            vault: get_vault_pda(context.owner.pubkey()),
-            user_token_account: context.user_token_account,
-            vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
+        .accounts(collateral_vault::accounts::CreateSubAccount {
             owner: context.owner.pubkey(),
-            token_program: anchor_spl::token::ID,
+            vault: vault_pda,
+            sub_account: sub_account_pda,
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::CreateSubAccount {
+            sub_account_id,
+            name: "perps".to_string(),
         })
-        .args(collateral_vault::instruction::BatchWithdraw { amounts: amounts.clone() })
         .signer(&context.owner)
         .send()
         .await?;
     
-    assert!(tx.is_success());
-    
-    let vault_account: CollateralVault = context
-        .program
-        .account(get_vault_pda(context.owner.pubkey()))
-        .await?;
-    
-    assert_eq!(vault_account.total_balance, deposit_amount - total_withdraw);
-    assert_eq!(vault_account.total_withdrawn, total_withdraw);
-    
-    Ok(())
-}
-
-#[tokio::test]
-async fn test_initialize_authority() -> Result<()> {
-    let mut context = setup_test_context().await?;
-    
-    let authorized_programs = vec![
-        Pubkey::from_str("11111111111111111111111111111111").unwrap(),
-        Pubkey::from_str("22222222222222222222222222222222").unwrap(),
-    ];
+    let session_signer = Keypair::new();
+    let session_pda = get_session_key_pda(&vault_pda, &session_signer.pubkey());
+    let amount_cap = 300_000_000;
     
     let tx = context
         .program
         .request()
-        .accounts(collateral_vault::accounts::InitializeAuthority {
-            admin: context.owner.pubkey(),
-            vault_authority: get_authority_pda(),
+        .accounts(collateral_vault::accounts::CreateSessionKey {
+            owner: context.owner.pubkey(),
+            vault: vault_pda,
+            session: session_pda,
             system_program: anchor_lang::system_program::ID,
         })
-        .args(collateral_vault::instruction::InitializeAuthority { authorized_programs: authorized_programs.clone() })
+        .args(collateral_vault::instruction::CreateSessionKey {
+            session_key: session_signer.pubkey(),
+            scope: SESSION_SCOPE_LOCK,
+            amount_cap,
+            expires_at: chrono_now() + 3_600,
+        })
         .signer(&context.owner)
         .send()
         .await?;
-    
     assert!(tx.is_success());
     
-    Ok(())
-}
-
-#[tokio::test]
-async fn test_add_authorized_program() -> Result<()> {
-    let mut context = setup_test_context().await?;
-    initialize_authority(&mut context, vec![]).await?;
-    
-    let new_program = Pubkey::from_str("33333333333333333333333333333333").unwrap();
+    let session_accounts = collateral_vault::accounts::SessionManageSubAccount {
+        session_signer: session_signer.pubkey(),
+        session: session_pda,
+        vault: vault_pda,
+        sub_account: sub_account_pda,
+        journal: None,
+    };
     
+    // the owner's wallet isn't involved from here on
     let tx = context
         .program
         .request()
-        .accounts(collateral_vault::accounts::UpdateAuthority {
-            admin: context.owner.pubkey(),
-            vault_authority: get_authority_pda(),
-        })
-        .args(collateral_vault::instruction::AddAuthorizedProgram { program: new_program })
-        .signer(&context.owner)
+        .accounts(session_accounts.clone())
+        .args(collateral_vault::instruction::SessionFundSubAccount { sub_account_id, amount: 200_000_000 })
+        .signer(&session_signer)
         .send()
         .await?;
-    
     assert!(tx.is_success());
     
+    // 200 + 200 goes past the 300 cap
+    let over_cap = context
+        .program
+        .request()
+        .accounts(session_accounts.clone())
+        .args(collateral_vault::instruction::SessionFundSubAccount { sub_account_id, amount: 200_000_000 })
+        .signer(&session_signer)
+        .send()
+        .await;
+    assert!(over_cap.is_err());
+    
+    // unlocking isn't in this session's scope
+    let out_of_scope = context
+        .program
+        .request()
+        .accounts(session_accounts)
+        .args(collateral_vault::instruction::SessionDefundSubAccount { sub_account_id, amount: 1 })
+        .signer(&session_signer)
+        .send()
+        .await;
+    assert!(out_of_scope.is_err());
+    
+    let session: SessionKey = context.program.account(session_pda).await?;
+    assert_eq!(session.amount_used, 200_000_000);
+    
+    let vault_account: CollateralVault = context.program.account(vault_pda).await?;
+    assert_eq!(vault_account.locked_balance, 200_000_000);
+    
     Ok(())
 }
 
@@ -491,7 +2800,7 @@ async fn test_configure_multisig() -> Result<()> {
         .await?;
     
     assert_eq!(vault_account.multisig_threshold, threshold);
-    assert_eq!(vault_account.authorized_signers.len(), signers.len());
+    assert_eq!(vault_account.signers().len(), signers.len());
     
     Ok(())
 }
@@ -523,7 +2832,7 @@ async fn test_add_delegate() -> Result<()> {
         .account(get_vault_pda(context.owner.pubkey()))
         .await?;
     
-    assert!(vault_account.delegated_users.contains(&delegate));
+    assert!(vault_account.delegates().contains(&delegate));
     
     Ok(())
 }
@@ -555,7 +2864,7 @@ async fn test_add_to_whitelist() -> Result<()> {
         .account(get_vault_pda(context.owner.pubkey()))
         .await?;
     
-    assert!(vault_account.withdrawal_whitelist.contains(&whitelist_address));
+    assert!(vault_account.whitelist().contains(&whitelist_address));
     
     Ok(())
 }
@@ -585,7 +2894,7 @@ async fn test_toggle_whitelist() -> Result<()> {
         .account(get_vault_pda(context.owner.pubkey()))
         .await?;
     
-    assert!(vault_account.whitelist_enabled);
+    assert_eq!(vault_account.whitelist_enabled, 1);
     
     Ok(())
 }
@@ -681,7 +2990,7 @@ async fn test_toggle_emergency_mode() -> Result<()> {
         .account(get_vault_pda(context.owner.pubkey()))
         .await?;
     
-    assert!(vault_account.emergency_mode);
+    assert_eq!(vault_account.emergency_mode, 1);
     
     Ok(())
 }
@@ -689,6 +2998,8 @@ async fn test_toggle_emergency_mode() -> Result<()> {
 #[tokio::test]
 async fn test_request_withdrawal() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
@@ -708,6 +3019,7 @@ async fn test_request_withdrawal() -> Result<()> {
             user: context.owner.pubkey(),
             vault: get_vault_pda(context.owner.pubkey()),
             owner: context.owner.pubkey(),
+            journal: None,
         })
         .args(collateral_vault::instruction::RequestWithdrawal { amount: withdrawal_amount, recipient })
         .signer(&context.owner)
@@ -721,8 +3033,8 @@ async fn test_request_withdrawal() -> Result<()> {
         .account(get_vault_pda(context.owner.pubkey()))
         .await?;
     
-    assert!(vault_account.pending_withdrawal.is_some());
-    let pending = vault_account.pending_withdrawal.unwrap();
+    assert!(vault_account.pending().is_some());
+    let pending = vault_account.pending().unwrap();
     assert_eq!(pending.amount, withdrawal_amount);
     assert_eq!(pending.recipient, recipient);
     
@@ -754,7 +3066,7 @@ async fn test_configure_yield() -> Result<()> {
         .account(get_vault_pda(context.owner.pubkey()))
         .await?;
     
-    assert!(vault_account.yield_enabled);
+    assert_eq!(vault_account.yield_enabled, 1);
     
     Ok(())
 }
@@ -762,6 +3074,8 @@ async fn test_configure_yield() -> Result<()> {
 #[tokio::test]
 async fn test_lock_insufficient_balance() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
@@ -775,6 +3089,7 @@ async fn test_lock_insufficient_balance() -> Result<()> {
             user: context.owner.pubkey(),
             vault: get_vault_pda(context.owner.pubkey()),
             owner: context.owner.pubkey(),
+            journal: None,
         })
         .args(collateral_vault::instruction::LockCollateral { amount: deposit_amount + 1 })
         .signer(&context.owner)
@@ -789,6 +3104,8 @@ async fn test_lock_insufficient_balance() -> Result<()> {
 #[tokio::test]
 async fn test_withdraw_locked_collateral() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit_amount = 1_000_000_000;
@@ -808,7 +3125,9 @@ async fn test_withdraw_locked_collateral() -> Result<()> {
             user_token_account: context.user_token_account,
             vault_token_account: get_vault_token_account(&get_vault_pda(context.owner.pubkey()), &context.usdt_mint.pubkey()),
             owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
             token_program: anchor_spl::token::ID,
+            journal: None,
         })
         .args(collateral_vault::instruction::Withdraw { amount: deposit_amount - lock_amount + 1 })
         .signer(&context.owner)
@@ -823,6 +3142,8 @@ async fn test_withdraw_locked_collateral() -> Result<()> {
 #[tokio::test]
 async fn test_multiple_deposits() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     let deposit1 = 500_000_000;
@@ -848,6 +3169,8 @@ async fn test_multiple_deposits() -> Result<()> {
 #[tokio::test]
 async fn test_complex_workflow() -> Result<()> {
     let mut context = setup_test_context().await?;
+    initialize_authority(&mut context, vec![]).await?;
+    initialize_protocol_config(&mut context, u64::MAX, u64::MAX).await?;
     initialize_vault(&mut context).await?;
     
     // Initial deposit
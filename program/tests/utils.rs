@@ -41,6 +41,127 @@ pub fn get_authority_pda() -> Pubkey {
     .0
 }
 
+pub fn get_program_registry_pda(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"program_registry", program.as_ref()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_caller_authority_pda(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault_caller"], program).0
+}
+
+pub fn get_program_exposure_pda(program: &Pubkey, vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"program_exposure", program.as_ref(), vault.as_ref()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_protocol_config_pda() -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"protocol_config"],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_lock_record_pda(vault: &Pubkey, lock_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"lock", vault.as_ref(), &lock_id.to_le_bytes()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_vesting_schedule_pda(vault: &Pubkey, schedule_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"vesting", vault.as_ref(), &schedule_id.to_le_bytes()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_scheduled_withdrawal_pda(vault: &Pubkey, schedule_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"scheduled_withdrawal", vault.as_ref(), &schedule_id.to_le_bytes()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_allowance_pda(vault: &Pubkey, spender: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"allowance", vault.as_ref(), spender.as_ref()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_session_key_pda(vault: &Pubkey, session_key: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"session_key", vault.as_ref(), session_key.as_ref()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_escrow_pda(from_vault: &Pubkey, escrow_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"escrow", from_vault.as_ref(), &escrow_id.to_le_bytes()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_sub_account_pda(vault: &Pubkey, sub_account_id: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"sub_account", vault.as_ref(), &sub_account_id.to_le_bytes()],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+pub fn get_insurance_fund_pda() -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"insurance_fund"],
+        &collateral_vault::ID,
+    )
+    .0
+}
+
+// ed25519 program instruction with key, signature and message inline, the shape
+// withdraw_with_permit looks for right before itself
+pub fn ed25519_verify_ix(pubkey: &Pubkey, signature: &[u8], message: &[u8]) -> solana_sdk::instruction::Instruction {
+    let pubkey_offset: u16 = 16;
+    let signature_offset: u16 = pubkey_offset + 32;
+    let message_offset: u16 = signature_offset + 64;
+    
+    let mut data = vec![1u8, 0];
+    for field in [signature_offset, u16::MAX, pubkey_offset, u16::MAX, message_offset, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    
+    solana_sdk::instruction::Instruction {
+        program_id: solana_sdk::ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+pub fn chrono_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
 pub async fn setup_test_context() -> Result<TestContext> {
     let mut program_test = ProgramTest::new(
         "collateral_vault",
@@ -241,9 +362,11 @@ pub async fn deposit(context: &mut TestContext, amount: u64) -> Result<()> {
             user_token_account: context.user_token_account,
             vault_token_account,
             owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
             token_program: anchor_spl::token::ID,
+            journal: None,
         })
-        .args(collateral_vault::instruction::Deposit { amount })
+        .args(collateral_vault::instruction::Deposit { amount, reference: None })
         .signer(&context.owner)
         .send()
         .await?;
@@ -264,7 +387,9 @@ pub async fn withdraw(context: &mut TestContext, amount: u64) -> Result<()> {
             user_token_account: context.user_token_account,
             vault_token_account,
             owner: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
             token_program: anchor_spl::token::ID,
+            journal: None,
         })
         .args(collateral_vault::instruction::Withdraw { amount })
         .signer(&context.owner)
@@ -284,6 +409,7 @@ pub async fn lock_collateral(context: &mut TestContext, amount: u64) -> Result<(
             user: context.owner.pubkey(),
             vault: vault_pda,
             owner: context.owner.pubkey(),
+            journal: None,
         })
         .args(collateral_vault::instruction::LockCollateral { amount })
         .signer(&context.owner)
@@ -303,6 +429,7 @@ pub async fn unlock_collateral(context: &mut TestContext, amount: u64) -> Result
             user: context.owner.pubkey(),
             vault: vault_pda,
             owner: context.owner.pubkey(),
+            journal: None,
         })
         .args(collateral_vault::instruction::UnlockCollateral { amount })
         .signer(&context.owner)
@@ -321,7 +448,77 @@ pub async fn initialize_authority(context: &mut TestContext, authorized_programs
             vault_authority: get_authority_pda(),
             system_program: anchor_lang::system_program::ID,
         })
-        .args(collateral_vault::instruction::InitializeAuthority { authorized_programs })
+        .args(collateral_vault::instruction::InitializeAuthority { authorized_programs: vec![] })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    for program in authorized_programs {
+        register_program(context, program).await?;
+    }
+    
+    Ok(())
+}
+
+pub async fn register_program(context: &mut TestContext, program: Pubkey) -> Result<()> {
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::RegisterProgram {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            program_registry: get_program_registry_pda(&program),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::RegisterProgram { program })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    Ok(())
+}
+
+pub async fn open_program_exposure(context: &mut TestContext, program: Pubkey, vault: Pubkey) -> Result<()> {
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::OpenProgramExposure {
+            payer: context.owner.pubkey(),
+            vault,
+            caller_program: program,
+            program_registry: get_program_registry_pda(&program),
+            program_exposure: get_program_exposure_pda(&program, &vault),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::OpenProgramExposure {})
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    Ok(())
+}
+
+pub async fn transfer_to_vault(
+    context: &mut TestContext,
+    from_vault: Pubkey,
+    to_vault: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::TransferToVault {
+            owner: context.owner.pubkey(),
+            from_vault,
+            to_vault,
+            from_token_account: get_vault_token_account(&from_vault, &context.usdt_mint.pubkey()),
+            to_token_account: get_vault_token_account(&to_vault, &context.usdt_mint.pubkey()),
+            protocol_config: get_protocol_config_pda(),
+            token_program: anchor_spl::token::ID,
+            from_journal: None,
+            to_journal: None,
+        })
+        .args(collateral_vault::instruction::TransferToVault { amount })
         .signer(&context.owner)
         .send()
         .await?;
@@ -347,3 +544,92 @@ pub async fn configure_timelock(context: &mut TestContext, duration: i64) -> Res
     
     Ok(())
 }
+
+pub async fn initialize_protocol_config(
+    context: &mut TestContext,
+    max_vault_balance: u64,
+    global_deposit_cap: u64,
+) -> Result<()> {
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::InitializeProtocolConfig {
+            admin: context.owner.pubkey(),
+            vault_authority: get_authority_pda(),
+            protocol_config: get_protocol_config_pda(),
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::InitializeProtocolConfig {
+            max_vault_balance,
+            global_deposit_cap,
+        })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    Ok(())
+}
+
+pub async fn freeze_vault(context: &mut TestContext, vault: Pubkey, reason_code: u8) -> Result<()> {
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::FreezeVault {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            vault,
+        })
+        .args(collateral_vault::instruction::FreezeVault { reason_code })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    Ok(())
+}
+
+// returns the fund's token account
+pub async fn initialize_insurance_fund(context: &mut TestContext, fee_share_bps: u16) -> Result<Pubkey> {
+    let fund_pda = get_insurance_fund_pda();
+    let fund_token_account = get_vault_token_account(&fund_pda, &context.usdt_mint.pubkey());
+    
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::InitializeInsuranceFund {
+            admin: context.owner.pubkey(),
+            protocol_config: get_protocol_config_pda(),
+            insurance_fund: fund_pda,
+            fund_token_account,
+            mint: context.usdt_mint.pubkey(),
+            token_program: anchor_spl::token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: anchor_lang::system_program::ID,
+        })
+        .args(collateral_vault::instruction::InitializeInsuranceFund { fee_share_bps })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    Ok(fund_token_account)
+}
+
+pub async fn contribute_insurance_fund(context: &mut TestContext, amount: u64) -> Result<()> {
+    let fund_pda = get_insurance_fund_pda();
+    
+    context
+        .program
+        .request()
+        .accounts(collateral_vault::accounts::ContributeInsuranceFund {
+            contributor: context.owner.pubkey(),
+            insurance_fund: fund_pda,
+            contributor_token_account: context.user_token_account,
+            fund_token_account: get_vault_token_account(&fund_pda, &context.usdt_mint.pubkey()),
+            token_program: anchor_spl::token::ID,
+        })
+        .args(collateral_vault::instruction::ContributeInsuranceFund { amount })
+        .signer(&context.owner)
+        .send()
+        .await?;
+    
+    Ok(())
+}